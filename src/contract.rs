@@ -1,43 +1,74 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps, 
-    DepsMut, Env, MessageInfo, Order, Response, StdResult,
+    entry_point, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps, 
+    DepsMut, Env, Event, MessageInfo, Order, Response, StdError, StdResult, Storage, Timestamp, Uint128, Uint256,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 use cw_storage_plus::Bound;
-use std::collections::HashMap;  // ADD THIS
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use std::collections::HashMap;
 
 use crate::error::ContractError;
-use crate::msg::{ConfigResponse, ExecuteMsg, InstantiateMsg, JobResponse, JobsResponse, 
-    MigrateMsg, PricingTier, ProviderResponse, ProvidersResponse, QueryMsg};  // ADD PricingTier
+use crate::msg::{CheapestProvidersResponse, ClientPrefsResponse, ClientStatsResponse, ConfigResponse, FeeDiscountTier, EncryptionInfo, ExecuteMsg, HealthResponse, IdleProvidersResponse, InstantiateMsg, InvariantsResponse, RawStateResponse, KeeperWorkResponse, JobResponse, JobResult, JobWithProviderResponse, JobsResponse, 
+    JobTypeTimeoutResponse, MarketPriceResponse, MigrateMsg, NextJobIdResponse, OldestProvidersResponse, ProviderTenure, ProviderWithPricing, ProvidersWithPricingResponse, PricingTier, ProviderRegistration,
+    ProviderPrice, ProviderRatingsResponse, ProviderResponse, ProviderSelectionResponse, SelectionStrategy,
+    ProviderEarningsResponse, ProviderThroughputResponse, ProviderUptimeResponse, ProvidersResponse,
+    QueryMsg, FailureRateResponse, FeeSplitResponse, RefundReason, RoleResponse, StatusChangeResponse,
+    RefundResponse, RefundsResponse, RoundingMode, SolvencyResponse, TvlResponse, StatusDistributionResponse};
 
 use crate::state::{
-    Config, Job, JobStatus, Provider, CONFIG, JOBS, JOBS_BY_CLIENT, JOBS_BY_PROVIDER,
-    NEXT_JOB_ID, PROVIDERS,
+    can_transition, ClientPrefs, Config, FailureWindow, Job, JobStatus, Provider, RefundRecord, StatusChange, StatusCounts, FAILURE_WINDOW, CONFIG, LEDGERS_BACKFILLED, LEGACY_CONFIG, JOBS, JOBS_BY_CLIENT, JOBS_BY_DEADLINE,
+    JOBS_BY_DEPENDENCY, JOBS_BY_PROVIDER, JOB_ESCROW,
+    JOB_TYPE_TIMEOUTS, NEXT_JOB_ID, OBLIGATIONS_BY_DENOM, OUTSTANDING_OBLIGATIONS, PROVIDERS, PROVIDERS_BY_SERVICE,
+    ACTIVE_JOB_COUNT, CLIENT_ALLOWLIST, CLIENT_PREFS, CLIENT_STATS, COMPLETED_BY_TIME, FEE_DISCOUNT_TIERS, NEXT_REFUND_ID, USED_RESULT_HASHES, REFUNDS, REFUNDS_BY_CLIENT, STATUS_COUNTS,
 };
 
 const CONTRACT_NAME: &str = "crates.io:medas-computing-contract";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// Upper bound on providers read by queries that scan the whole provider set
+const MAX_PROVIDER_SCAN: usize = 500;
+// Upper bound on index entries a filtered job query reads per call
+const MAX_JOB_SCAN: usize = 500;
+// Longest free-text reason stored on a job
+const MAX_REASON_LENGTH: usize = 256;
+// Longest notification URL a client may store
+const MAX_NOTIFY_URL_LENGTH: usize = 256;
+// Status changes kept on a job; the oldest after submission are dropped beyond this
+const MAX_JOB_HISTORY: usize = 32;
+// Slices the rolling failure-rate window is kept in
+const FAILURE_WINDOW_SLICES: u64 = 10;
+// How far ahead GetKeeperWork looks for jobs about to time out
+const EXPIRING_JOB_WINDOW: u64 = 600;
+// Job history a seeded reputation stands for when an import gives no counts
+const REPUTATION_SEED_JOBS: u64 = 100;
+
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     let community_pool = deps.api.addr_validate(&msg.community_pool)?;
+    // Anything above 100 would make every completion underflow
+    if msg.community_fee_percent > 100 {
+        return Err(ContractError::InvalidFeePercent { percent: msg.community_fee_percent });
+    }
 
-   let config = Config {
-    community_pool,
-    community_fee_percent: msg.community_fee_percent,
-    default_job_timeout: msg.default_job_timeout,      
-    heartbeat_timeout: msg.heartbeat_timeout,          
-    paused: false,                                    
-    };
+    let config = new_config(
+        info.sender,
+        community_pool,
+        msg.community_fee_percent,
+        msg.default_job_timeout,
+        msg.heartbeat_timeout,
+    );
     CONFIG.save(deps.storage, &config)?;
     NEXT_JOB_ID.save(deps.storage, &1u64)?;
+    OUTSTANDING_OBLIGATIONS.save(deps.storage, &Uint128::zero())?;
+    LEDGERS_BACKFILLED.save(deps.storage, &true)?;
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
@@ -45,6 +76,57 @@ pub fn instantiate(
         .add_attribute("community_fee_percent", msg.community_fee_percent.to_string()))
 }
 
+/// Config with every optional setting at its default
+fn new_config(
+    admin: Addr,
+    community_pool: Addr,
+    community_fee_percent: u64,
+    default_job_timeout: u64,
+    heartbeat_timeout: u64,
+) -> Config {
+    Config {
+        admin,
+        community_pool,
+        community_fee_percent,
+        default_job_timeout,
+        heartbeat_timeout,
+        paused: false,
+        referral_fee_percent: 0,
+        require_heartbeat: true,
+        exclusive_window: 300,
+        cancel_fee_percent: 0,
+        max_capabilities: 20,
+        query_paused: false,
+        registration_fee: Uint128::zero(),
+        rounding_mode: RoundingMode::TowardProvider,
+        max_providers_per_service: None,
+        capability_reputation_threshold: Decimal::zero(),
+        low_reputation_max_capabilities: 3,
+        update_cooldown: 0,
+        max_total_active_jobs: 0,
+        enforce_unique_results: false,
+        result_deadline_buffer: None,
+        min_heartbeat_interval: 0,
+        cancel_community_fee_percent: 0,
+        max_extensions: 2,
+        max_total_extension: 3600,
+        require_client_allowlist: false,
+        default_reputation: Decimal::percent(50),
+        accepted_denoms: vec!["umedas".to_string()],
+        max_sweep_batch: 100,
+        requeue_fee_discount_percent: 0,
+        refund_on_deactivation: false,
+        deregister_after: 0,
+        probation_period: 0,
+        probation_max_payment: Uint128::zero(),
+        probation_min_jobs: 0,
+        auto_pause_failure_rate: Decimal::zero(),
+        auto_pause_min_jobs: 20,
+        failure_window: 3600,
+        reputation_rating_weight: 0,
+    }
+}
+
 #[entry_point]
 pub fn execute(
     deps: DepsMut,
@@ -52,42 +134,204 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    // Check if contract is paused (except for unpause)
+    // Check if contract is paused - unpause and keeper maintenance still run,
+    // so timed-out jobs keep being refunded during a pause
     let config = CONFIG.load(deps.storage)?;
-    if config.paused && !matches!(msg, ExecuteMsg::UnpauseContract {}) {
+    if config.paused
+        && !matches!(
+            msg,
+            ExecuteMsg::UnpauseContract {}
+                | ExecuteMsg::ProcessTimedOutJobs { .. }
+                | ExecuteMsg::ProcessInactiveProviders {}
+                | ExecuteMsg::PruneStaleProviders { .. }
+        )
+    {
         return Err(ContractError::ContractPaused {});
     }
     
     match msg {
-        ExecuteMsg::RegisterProvider { name, capabilities, pricing, endpoint } => 
-            execute_register_provider(deps, env, info, name, capabilities, pricing, endpoint),
-        ExecuteMsg::SubmitJob { provider, job_type, parameters } => 
-            execute_submit_job(deps, env, info, provider, job_type, parameters),
-        ExecuteMsg::CompleteJob { job_id, result_hash, result_url } => 
-            execute_complete_job(deps, env, info, job_id, result_hash, result_url),
+        ExecuteMsg::RegisterProvider { name, capabilities, pricing, endpoint, refund_guarantee, max_payment } => 
+            execute_register_provider(
+                deps,
+                env,
+                info,
+                name,
+                capabilities,
+                pricing,
+                endpoint,
+                refund_guarantee.unwrap_or(false),
+                max_payment,
+            ),
+        ExecuteMsg::SubmitJob { provider, job_type, parameters, referrer, depends_on, denom, max_price } => 
+            execute_submit_job(deps, env, info, provider, job_type, parameters, referrer, depends_on, denom, max_price),
+        ExecuteMsg::CompleteJob { job_id, result_hash, result_url, encryption, result_size_bytes } => 
+            execute_complete_job(deps, env, info, job_id, result_hash, result_url, encryption, result_size_bytes),
         ExecuteMsg::UpdateProviderStatus { active } => 
             execute_update_provider_status(deps, info, active),
         ExecuteMsg::HeartBeat {} => 
             execute_heartbeat(deps, env, info),
-        ExecuteMsg::UpdateProvider { name, endpoint, pricing, capacity } => 
-            execute_update_provider(deps, env, info, name, endpoint, pricing, capacity),
-        ExecuteMsg::FailJob { job_id, reason } => 
-            execute_fail_job(deps, env, info, job_id, reason),
-        ExecuteMsg::CancelJob { job_id } => 
-            execute_cancel_job(deps, env, info, job_id),
-        ExecuteMsg::ProcessTimedOutJobs {} => 
-            execute_process_timed_out_jobs(deps, env, info),
+        ExecuteMsg::UpdateProvider {
+            name,
+            endpoint,
+            pricing,
+            capacity,
+            payout_address,
+            capabilities,
+            auto_accept,
+            refund_guarantee,
+            max_payment,
+        } => 
+            execute_update_provider(
+                deps,
+                env,
+                info,
+                name,
+                endpoint,
+                pricing,
+                capacity,
+                payout_address,
+                capabilities,
+                auto_accept,
+                refund_guarantee,
+                max_payment,
+            ),
+        ExecuteMsg::FailJob { job_id, reason, refund_percent } => 
+            execute_fail_job(deps, env, info, job_id, reason, refund_percent),
+        ExecuteMsg::CancelJob { job_id, reason } => 
+            execute_cancel_job(deps, env, info, job_id, reason),
+        ExecuteMsg::ProcessTimedOutJobs { limit } => 
+            execute_process_timed_out_jobs(deps, env, info, limit),
+        ExecuteMsg::PruneStaleProviders { limit } => 
+            execute_prune_stale_providers(deps, env, info, limit),
         ExecuteMsg::ProcessInactiveProviders {} => 
             execute_process_inactive_providers(deps, env, info),
-        ExecuteMsg::UpdateConfig { default_job_timeout, heartbeat_timeout } => 
-            execute_update_config(deps, info, default_job_timeout, heartbeat_timeout),
+        ExecuteMsg::UpdateConfig {
+            default_job_timeout,
+            heartbeat_timeout,
+            referral_fee_percent,
+            require_heartbeat,
+            exclusive_window,
+            cancel_fee_percent,
+            max_capabilities,
+            query_paused,
+            registration_fee,
+            rounding_mode,
+            max_providers_per_service,
+            capability_reputation_threshold,
+            low_reputation_max_capabilities,
+            update_cooldown,
+            max_total_active_jobs,
+            enforce_unique_results,
+            result_deadline_buffer,
+            min_heartbeat_interval,
+            cancel_community_fee_percent,
+            max_extensions,
+            max_total_extension,
+            require_client_allowlist,
+            default_reputation,
+            accepted_denoms,
+            max_sweep_batch,
+            requeue_fee_discount_percent,
+            refund_on_deactivation,
+            deregister_after,
+            probation_period,
+            probation_max_payment,
+            probation_min_jobs,
+            auto_pause_failure_rate,
+            auto_pause_min_jobs,
+            failure_window,
+            reputation_rating_weight,
+        } => execute_update_config(
+            deps,
+            info,
+            default_job_timeout,
+            heartbeat_timeout,
+            referral_fee_percent,
+            require_heartbeat,
+            exclusive_window,
+            cancel_fee_percent,
+            max_capabilities,
+            query_paused,
+            registration_fee,
+            rounding_mode,
+            max_providers_per_service,
+            capability_reputation_threshold,
+            low_reputation_max_capabilities,
+            update_cooldown,
+            max_total_active_jobs,
+            enforce_unique_results,
+            result_deadline_buffer,
+            min_heartbeat_interval,
+            cancel_community_fee_percent,
+            max_extensions,
+            max_total_extension,
+            require_client_allowlist,
+            default_reputation,
+            accepted_denoms,
+            max_sweep_batch,
+            requeue_fee_discount_percent,
+            refund_on_deactivation,
+            deregister_after,
+            probation_period,
+            probation_max_payment,
+            probation_min_jobs,
+            auto_pause_failure_rate,
+            auto_pause_min_jobs,
+            failure_window,
+            reputation_rating_weight,
+        ),
         ExecuteMsg::PauseContract {} => 
             execute_pause_contract(deps, info),
         ExecuteMsg::UnpauseContract {} => 
             execute_unpause_contract(deps, info),
+        ExecuteMsg::SetJobTypeTimeout { job_type, timeout } => 
+            execute_set_job_type_timeout(deps, info, job_type, timeout),
+        ExecuteMsg::SubmitJobWithFallback { providers, job_type, parameters, denom } => 
+            execute_submit_job_with_fallback(deps, env, info, providers, job_type, parameters, denom),
+        ExecuteMsg::CompleteJobMulti { job_id, results } => 
+            execute_complete_job_multi(deps, env, info, job_id, results),
+        ExecuteMsg::AcceptJob { job_id } => 
+            execute_accept_job(deps, env, info, job_id),
+        ExecuteMsg::RegisterProvidersBatch { providers } => 
+            execute_register_providers_batch(deps, env, info, providers),
+        ExecuteMsg::AcknowledgeResult { job_id } => 
+            execute_acknowledge_result(deps, env, info, job_id),
+        ExecuteMsg::AutoSubmitJob { job_type, parameters, strategy, denom } => 
+            execute_auto_submit_job(deps, env, info, job_type, parameters, strategy, denom),
+        ExecuteMsg::RequeueJob { job_id } => 
+            execute_requeue_job(deps, env, info, job_id),
+        ExecuteMsg::RateJob { job_id, rating } => 
+            execute_rate_job(deps, info, job_id, rating),
+        ExecuteMsg::CancelJobsBatch { job_ids } => 
+            execute_cancel_jobs_batch(deps, env, info, job_ids),
+        ExecuteMsg::RequestExtension { job_id, additional_seconds } => 
+            execute_request_extension(deps, env, info, job_id, additional_seconds),
+        ExecuteMsg::ReportProgress { job_id, percent } => 
+            execute_report_progress(deps, info, job_id, percent),
+        ExecuteMsg::ApproveProgress { job_id } => 
+            execute_approve_progress(deps, info, job_id),
+        ExecuteMsg::AddAllowedClient { address } => 
+            execute_set_client_allowed(deps, info, address, true),
+        ExecuteMsg::RemoveAllowedClient { address } => 
+            execute_set_client_allowed(deps, info, address, false),
+        ExecuteMsg::SetFeeDiscountTiers { tiers } => 
+            execute_set_fee_discount_tiers(deps, info, tiers),
+        ExecuteMsg::RemoveCapability { service_type } => 
+            execute_remove_capability(deps, info, service_type),
+        ExecuteMsg::SetServiceEnabled { service_type, enabled } => 
+            execute_set_service_enabled(deps, info, service_type, enabled),
+        ExecuteMsg::TopUpJob { job_id } => 
+            execute_top_up_job(deps, env, info, job_id),
+        ExecuteMsg::SweepDust {} => 
+            execute_sweep_dust(deps, env, info),
+        ExecuteMsg::WaivePenalty { job_id } => 
+            execute_waive_penalty(deps, env, info, job_id),
+        ExecuteMsg::SetClientPrefs { notify_url } => 
+            execute_set_client_prefs(deps, info, notify_url),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_register_provider(
     deps: DepsMut,
     env: Env,
@@ -96,9 +340,165 @@ pub fn execute_register_provider(
     capabilities: Vec<crate::msg::ServiceCapability>,
     pricing: std::collections::HashMap<String, crate::msg::PricingTier>,
     endpoint: String,
+    refund_guarantee: bool,
+    max_payment: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    // One-time onboarding fee, forwarded to the community pool
+    let paid = umedas_sent(&info.funds)?;
+    if paid < config.registration_fee {
+        return Err(ContractError::RegistrationFeeRequired {
+            expected: config.registration_fee,
+        });
+    }
+
+    save_new_provider(
+        deps.storage,
+        &env,
+        info.sender.clone(),
+        name.clone(),
+        capabilities,
+        pricing,
+        endpoint,
+        refund_guarantee,
+        max_payment,
+    )?;
+
+    let mut response = Response::new();
+    if !paid.is_zero() {
+        response = response.add_message(BankMsg::Send {
+            to_address: config.community_pool.to_string(),
+            amount: vec![Coin {
+                denom: "umedas".to_string(),
+                amount: paid,
+            }],
+        });
+    }
+
+    Ok(response
+        .add_attribute("action", "register_provider")
+        .add_attribute("provider", info.sender.to_string())
+        .add_attribute("name", name)
+        .add_attribute("registration_fee", paid.to_string()))
+}
+
+/// Register several providers in one transaction - admin only
+/// For operator-managed fleets where the admin vouches for each address,
+/// so no registration fee is charged
+pub fn execute_register_providers_batch(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    providers: Vec<ProviderRegistration>,
 ) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut registered = vec![];
+    for registration in providers {
+        let address = deps.api.addr_validate(&registration.address)?;
+        if let Some(reputation) = registration.reputation {
+            if reputation > Decimal::from_ratio(100u128, 1u128) {
+                return Err(ContractError::InvalidReputation { reputation });
+            }
+        }
+        let seeded = registration.reputation.is_some()
+            || registration.total_completed.is_some()
+            || registration.total_failed.is_some();
+        let (reputation, mut total_completed, mut total_failed) = (
+            registration.reputation,
+            registration.total_completed,
+            registration.total_failed,
+        );
+        // A bare reputation becomes an equivalent history, so the next job's recalculation
+        // still reflects it
+        if let (Some(reputation), None, None) = (reputation, total_completed, total_failed) {
+            let completed = (reputation.to_uint_floor().u128() as u64).min(REPUTATION_SEED_JOBS);
+            total_completed = Some(completed);
+            total_failed = Some(REPUTATION_SEED_JOBS - completed);
+        }
+        save_new_provider(
+            deps.storage,
+            &env,
+            address.clone(),
+            registration.name,
+            registration.capabilities,
+            registration.pricing,
+            registration.endpoint,
+            false,
+            None,
+        )?;
+
+        // Imported providers keep the standing they earned elsewhere
+        if seeded {
+            let mut provider = PROVIDERS.load(deps.storage, &address)?;
+            provider.total_completed = total_completed.unwrap_or_default();
+            provider.total_failed = total_failed.unwrap_or_default();
+            provider.reputation = reputation.unwrap_or_else(|| calculate_reputation(&provider, config.reputation_rating_weight));
+            PROVIDERS.save(deps.storage, &address, &provider)?;
+        }
+        registered.push(address.to_string());
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "register_providers_batch")
+        .add_attribute("registered_count", registered.len().to_string())
+        .add_attribute("providers", registered.join(",")))
+}
+
+/// Move a provider's service index entries from its old to its new capabilities,
+/// enforcing the per-service provider cap on services it newly advertises
+fn reindex_services(
+    storage: &mut dyn Storage,
+    address: &Addr,
+    old: &[crate::msg::ServiceCapability],
+    new: &[crate::msg::ServiceCapability],
+    config: &Config,
+) -> Result<(), ContractError> {
+    for capability in old {
+        PROVIDERS_BY_SERVICE.remove(storage, (capability.service_type.as_str(), address));
+    }
+
+    for capability in new {
+        let key = (capability.service_type.as_str(), address);
+        if PROVIDERS_BY_SERVICE.has(storage, key) {
+            continue;
+        }
+        if let Some(max) = config.max_providers_per_service {
+            let taken = PROVIDERS_BY_SERVICE
+                .prefix(capability.service_type.as_str())
+                .keys(storage, None, None, Order::Ascending)
+                .take(max as usize)
+                .count();
+            if taken >= max as usize {
+                return Err(ContractError::ServiceSlotsFull {
+                    service_type: capability.service_type.clone(),
+                });
+            }
+        }
+        PROVIDERS_BY_SERVICE.save(storage, key, &())?;
+    }
+    Ok(())
+}
+
+/// Validate and store a newly registered provider
+#[allow(clippy::too_many_arguments)]
+fn save_new_provider(
+    storage: &mut dyn Storage,
+    env: &Env,
+    address: Addr,
+    name: String,
+    capabilities: Vec<crate::msg::ServiceCapability>,
+    pricing: HashMap<String, PricingTier>,
+    endpoint: String,
+    refund_guarantee: bool,
+    max_payment: Option<Uint128>,
+) -> Result<(), ContractError> {
     // Check if already registered
-    if PROVIDERS.has(deps.storage, &info.sender) {
+    if PROVIDERS.has(storage, &address) {
         return Err(ContractError::ProviderAlreadyRegistered {});
     }
 
@@ -106,10 +506,15 @@ pub fn execute_register_provider(
     if name.is_empty() || capabilities.is_empty() {
         return Err(ContractError::InvalidProviderData {});
     }
+    let config = CONFIG.load(storage)?;
+    if capabilities.len() > config.max_capabilities as usize {
+        return Err(ContractError::TooManyCapabilities { max: config.max_capabilities });
+    }
+    reindex_services(storage, &address, &[], &capabilities, &config)?;
 
     let provider = Provider {
-        address: info.sender.clone(),
-        name: name.clone(),
+        address: address.clone(),
+        name,
         capabilities,
         pricing,
         endpoint,
@@ -117,20 +522,37 @@ pub fn execute_register_provider(
         active_jobs: 0,
         total_completed: 0,
         total_failed: 0,
-        reputation: Decimal::percent(50),
+        total_completion_time: 0,
+        reputation: config.default_reputation,
         active: true,
         registered_at: env.block.time,
         last_heartbeat: env.block.time.seconds(), 
+        total_heartbeat_windows: 0,
+        missed_windows: 0,
+        window_credited_at: 0,
+        payout_address: None,
+        recent_outcomes: vec![],
+        recent_outcome_jobs: vec![],
+        auto_accept: false,
+        refund_guarantee,
+        max_payment,
+        last_updated_at: None,
+        rating_1: 0,
+        rating_2: 0,
+        rating_3: 0,
+        rating_4: 0,
+        rating_5: 0,
+        on_time_count: 0,
+        late_count: 0,
+        completion_nonce: 0,
+        total_jobs: 0,
     };
 
-    PROVIDERS.save(deps.storage, &info.sender, &provider)?;
-
-    Ok(Response::new()
-        .add_attribute("action", "register_provider")
-        .add_attribute("provider", info.sender.to_string())
-        .add_attribute("name", name))
+    PROVIDERS.save(storage, &address, &provider)?;
+    Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_submit_job(
     deps: DepsMut,
     env: Env,
@@ -138,59 +560,175 @@ pub fn execute_submit_job(
     provider_addr: String,
     job_type: String,
     parameters: String,
+    referrer: Option<String>,
+    depends_on: Option<u64>,
+    denom: String,
+    max_price: Option<Uint128>,
 ) -> Result<Response, ContractError> {
-    let provider = deps.api.addr_validate(&provider_addr)?;
+    // Malformed address, unregistered address and inactive provider are reported separately
+    let provider = deps.api.addr_validate(&provider_addr).map_err(|e| {
+        ContractError::InvalidProviderAddress {
+            address: provider_addr.clone(),
+            reason: e.to_string(),
+        }
+    })?;
+
+    // Referrer must be a third party
+    let referrer = referrer
+        .map(|r| deps.api.addr_validate(&r))
+        .transpose()?;
+    if let Some(r) = &referrer {
+        if *r == info.sender || *r == provider {
+            return Err(ContractError::InvalidReferrer {});
+        }
+    }
 
     // Check if provider exists and is active
     let mut provider_info = PROVIDERS
-        .load(deps.storage, &provider)
-        .map_err(|_| ContractError::ProviderNotFound {})?;
+        .may_load(deps.storage, &provider)?
+        .ok_or(ContractError::ProviderNotRegistered {
+            address: provider.to_string(),
+        })?;
 
-    if !provider_info.active {
-        return Err(ContractError::ProviderNotActive {});
-    }
+    let config = CONFIG.load(deps.storage)?;
+    check_job_eligibility(
+        deps.storage,
+        &config,
+        &provider_info,
+        &info.sender,
+        &job_type,
+        &parameters,
+    )?;
 
-    // Extract payment
-    let payment = info
-        .funds
-        .iter()
-        .find(|c| c.denom == "umedas")
-        .ok_or(ContractError::NoPayment {})?;
+    // Priced for every submission so an overflowing quantity is always rejected, then
+    // guarded against the provider repricing between the client's quote and this submission
+    let price = job_price(&provider_info, &job_type, &parameters)?;
+    if let (Some(max), Some(actual)) = (max_price, price) {
+        if actual > max {
+            return Err(ContractError::PriceExceededMax { max, actual });
+        }
+    }
 
-    if payment.amount.is_zero() {
+    // Extract payment in the client's chosen denom
+    if !config.accepted_denoms.contains(&denom) {
+        return Err(ContractError::DenomNotAccepted { denom });
+    }
+    let payment = job_payment(&info.funds, &denom)?;
+    if payment.is_zero() {
         return Err(ContractError::NoPayment {});
     }
+    check_payment_caps(&config, &provider_info, payment, env.block.time.seconds())?;
 
-    // Load config for timeout - ADD THIS LINE!
-    let config = CONFIG.load(deps.storage)?;
+    // Contract-wide cap on unfinished jobs, zero means unlimited
+    let active_job_count = ACTIVE_JOB_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    if config.max_total_active_jobs > 0 && active_job_count >= config.max_total_active_jobs {
+        return Err(ContractError::GlobalCapacityReached {});
+    }
+
+    let timeout = job_timeout(deps.storage, &job_type)?;
 
-    // Create job
-    let job_id = NEXT_JOB_ID.update(deps.storage, |id| -> StdResult<_> { Ok(id + 1) })?;
+    // A job waiting on an unfinished dependency starts out Blocked
+    let blocked = match depends_on {
+        Some(dependency_id) => {
+            let dependency = JOBS
+                .load(deps.storage, dependency_id)
+                .map_err(|_| ContractError::JobNotFound {})?;
+            if dependency.client != info.sender {
+                return Err(ContractError::ForeignDependency { job_id: dependency_id });
+            }
+            match dependency.status {
+                JobStatus::Completed | JobStatus::Acknowledged => false,
+                JobStatus::Failed | JobStatus::Cancelled => {
+                    return Err(ContractError::InvalidJobState {})
+                }
+                _ => true,
+            }
+        }
+        None => false,
+    };
+
+    // Create job - NEXT_JOB_ID holds the id to assign next
+    let job_id = NEXT_JOB_ID.load(deps.storage)?;
+    if JOBS.has(deps.storage, job_id) {
+        return Err(ContractError::JobAlreadyExists { job_id });
+    }
+    let next_job_id = job_id
+        .checked_add(1)
+        .ok_or(ContractError::JobIdsExhausted {})?;
+    NEXT_JOB_ID.save(deps.storage, &next_job_id)?;
 
-    let job = Job {
+    let mut job = Job {
         id: job_id,
         client: info.sender.clone(),
         provider: provider.clone(),
         job_type: job_type.clone(),
         parameters: parameters.clone(),
-        payment_amount: payment.amount,
-        status: JobStatus::Submitted,
+        payment_amount: payment,
+        // Automated providers take the job straight away
+        status: if blocked {
+            JobStatus::Blocked
+        } else if provider_info.auto_accept {
+            JobStatus::Processing
+        } else {
+            JobStatus::Submitted
+        },
         result_hash: None,
         result_url: None,
         created_at: env.block.time,
         completed_at: None,
-        deadline: env.block.time.seconds() + config.default_job_timeout,  
+        deadline: env.block.time.seconds() + timeout,  
         failure_reason: None,             
+        referrer,
+        results: vec![],
+        preferred_provider: None,
+        exclusive_until: None,
+        fallback_providers: vec![],
+        // Snapshot so the client can later show the standing they chose on
+        provider_reputation_at_submit: provider_info.reputation,
+        cancel_reason: None,
+        encryption: None,
+        result_size_bytes: None,
+        rating: None,
+        depends_on,
+        result_deadline: None,
+        provider_fee: Uint128::zero(),
+        extension_count: 0,
+        total_extension: 0,
+        reported_percent: 0,
+        released_amount: Uint128::zero(),
+        denom: denom.clone(),
+        original_job_id: None,
+        requeued_as: None,
+        penalty_waived: false,
+        counted_toward_pause: false,
+        history: vec![],
     };
+    push_history(&mut job, env.block.time);
+    if job.status == JobStatus::Processing {
+        job.result_deadline = result_deadline(&provider_info, &job.job_type, &config, env.block.time.seconds());
+    }
 
     JOBS.save(deps.storage, job_id, &job)?;
 
     // Update indices
     JOBS_BY_PROVIDER.save(deps.storage, (&provider, job_id), &())?;
     JOBS_BY_CLIENT.save(deps.storage, (&info.sender, job_id), &())?;
+    // Blocked jobs get their deadline once they are promoted
+    if let (true, Some(dependency_id)) = (blocked, depends_on) {
+        JOBS_BY_DEPENDENCY.save(deps.storage, (dependency_id, job_id), &())?;
+    } else {
+        JOBS_BY_DEADLINE.save(deps.storage, (job.deadline, job_id), &())?;
+    }
+    record_status_change(deps.storage, None, &job.status)?;
+    ACTIVE_JOB_COUNT.save(deps.storage, &(active_job_count + 1))?;
+
+    // Escrowed payment is owed until the job reaches a terminal state
+    add_obligation(deps.storage, &denom, payment)?;
+    JOB_ESCROW.save(deps.storage, job_id, &payment)?;
 
     // Update provider active jobs
     provider_info.active_jobs += 1;
+    provider_info.total_jobs += 1;
     PROVIDERS.save(deps.storage, &provider, &provider_info)?;
 
     Ok(Response::new()
@@ -198,9 +736,11 @@ pub fn execute_submit_job(
         .add_attribute("job_id", job_id.to_string())
         .add_attribute("provider", provider.to_string())
         .add_attribute("client", info.sender.to_string())
-        .add_attribute("payment", payment.amount.to_string()))
+        .add_attribute("payment", payment.to_string())
+        .add_attribute("denom", denom))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_complete_job(
     deps: DepsMut,
     env: Env,
@@ -208,6 +748,36 @@ pub fn execute_complete_job(
     job_id: u64,
     result_hash: String,
     result_url: String,
+    encryption: Option<EncryptionInfo>,
+    result_size_bytes: Option<u64>,
+) -> Result<Response, ContractError> {
+    complete_job(deps, env, info, job_id, result_hash, result_url, vec![], encryption, result_size_bytes)
+}
+
+/// Complete a job that produced several artifacts
+/// The first result also fills result_hash/result_url for single-result consumers
+pub fn execute_complete_job_multi(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    job_id: u64,
+    results: Vec<JobResult>,
+) -> Result<Response, ContractError> {
+    let first = results.first().cloned().ok_or(ContractError::NoResults {})?;
+    complete_job(deps, env, info, job_id, first.hash, first.url, results, None, None)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn complete_job(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    job_id: u64,
+    result_hash: String,
+    result_url: String,
+    results: Vec<JobResult>,
+    encryption: Option<EncryptionInfo>,
+    result_size_bytes: Option<u64>,
 ) -> Result<Response, ContractError> {
     let mut job = JOBS
         .load(deps.storage, job_id)
@@ -218,59 +788,193 @@ pub fn execute_complete_job(
         return Err(ContractError::Unauthorized {});
     }
 
-    // Check job status
-    if job.status != JobStatus::Submitted && job.status != JobStatus::Processing {
-        return Err(ContractError::InvalidJobState {});
+    // Checked before anything is written, so a vanished provider record
+    // leaves the job untouched
+    let mut provider = PROVIDERS
+        .may_load(deps.storage, &job.provider)?
+        .ok_or(ContractError::ProviderNotRegistered {
+            address: job.provider.to_string(),
+        })?;
+
+    if let Some(deadline) = job.result_deadline {
+        if env.block.time.seconds() > deadline {
+            return Err(ContractError::ResultDeadlinePassed { deadline });
+        }
+    }
+
+    // Hashes are always recorded so enabling enforcement covers past results too
+    let config = CONFIG.load(deps.storage)?;
+    let mut hashes = vec![result_hash.clone()];
+    hashes.extend(results.iter().map(|r| r.hash.clone()));
+    hashes.sort();
+    hashes.dedup();
+    for hash in &hashes {
+        if config.enforce_unique_results
+            && USED_RESULT_HASHES.has(deps.storage, (&job.provider, hash.as_str()))
+        {
+            return Err(ContractError::ResultHashReused {});
+        }
+    }
+    for hash in &hashes {
+        USED_RESULT_HASHES.save(deps.storage, (&job.provider, hash.as_str()), &())?;
     }
 
     // Update job
-    job.status = JobStatus::Completed;
+    transition_job(deps.storage, &mut job, JobStatus::Completed, env.block.time)?;
     job.result_hash = Some(result_hash);
     job.result_url = Some(result_url);
+    job.results = results;
+    job.encryption = encryption;
+    job.result_size_bytes = result_size_bytes;
     job.completed_at = Some(env.block.time);
-
-    JOBS.save(deps.storage, job_id, &job)?;
+    COMPLETED_BY_TIME.save(deps.storage, (env.block.time.seconds(), job.id), &())?;
 
     // Update provider stats
-    let mut provider = PROVIDERS.load(deps.storage, &job.provider)?;
     provider.active_jobs = provider.active_jobs.saturating_sub(1);
     provider.total_completed += 1;
+    let completion_time = env
+        .block
+        .time
+        .seconds()
+        .saturating_sub(job.created_at.seconds());
+    provider.total_completion_time += completion_time;
+    // Measured against the advertised time for the job type, if still advertised
+    if let Some(capability) = provider
+        .capabilities
+        .iter()
+        .find(|c| c.service_type == job.job_type)
+    {
+        if completion_time <= capability.avg_completion_time {
+            provider.on_time_count += 1;
+        } else {
+            provider.late_count += 1;
+        }
+    }
+    provider.record_outcome(job.id, true);
+    provider.reputation = calculate_reputation(&provider, config.reputation_rating_weight);
+    provider.completion_nonce += 1;
     PROVIDERS.save(deps.storage, &job.provider, &provider)?;
+    // A completion never trips the breaker, it only dilutes the failure rate
+    record_job_outcome(deps.storage, &job, env.block.time.seconds(), false)?;
 
-    // Calculate and distribute payment
-    let config = CONFIG.load(deps.storage)?;
-    let community_fee = job.payment_amount * Decimal::percent(config.community_fee_percent);
-    let provider_fee = job.payment_amount.checked_sub(community_fee)
+    // Fields in a fixed order so off-chain verifiers can rebuild the signed payload
+    let receipt = Event::new("completion_receipt")
+        .add_attribute("job_id", job_id.to_string())
+        .add_attribute("provider", job.provider.to_string())
+        .add_attribute("result_hash", job.result_hash.clone().unwrap_or_default())
+        .add_attribute("completed_at", env.block.time.seconds().to_string())
+        .add_attribute("nonce", provider.completion_nonce.to_string());
+
+    // Jobs waiting on this one become eligible, or are refunded if they no longer are
+    let dependent_refunds = resolve_dependents(deps.storage, &env, job_id, true)?;
+
+    // Calculate and distribute whatever progress releases haven't paid out yet
+    let payable = job.remaining_escrow();
+    let mut community_fee = community_fee(
+        payable,
+        config.community_fee_percent,
+        &config.rounding_mode,
+    );
+
+    // High-volume clients get part of the community fee waived, based on
+    // the jobs they completed before this one
+    let mut client_stats = CLIENT_STATS.may_load(deps.storage, &job.client)?.unwrap_or_default();
+    let discount = fee_discount_percent(deps.storage, client_stats.completed_jobs)?;
+    community_fee -= community_fee.multiply_ratio(discount, 100u128);
+    // Goodwill for clients retrying after a provider failure
+    if job.original_job_id.is_some() {
+        community_fee -= community_fee.multiply_ratio(config.requeue_fee_discount_percent, 100u128);
+    }
+    client_stats.completed_jobs += 1;
+    add_to_totals(&mut client_stats.total_spent, &job.denom, job.payment_amount);
+    CLIENT_STATS.save(deps.storage, &job.client, &client_stats)?;
+    let provider_share = payable.checked_sub(community_fee)
     .map_err(|e| ContractError::Std(cosmwasm_std::StdError::generic_err(e.to_string())))?;
 
-    let mut messages = vec![];
+    // Referral fee comes out of the provider's share
+    let referral_fee = if job.referrer.is_some() {
+        provider_share * Decimal::percent(config.referral_fee_percent)
+    } else {
+        Uint128::zero()
+    };
+    let provider_fee = provider_share.checked_sub(referral_fee)
+    .map_err(|e| ContractError::Std(cosmwasm_std::StdError::generic_err(e.to_string())))?;
+
+    job.provider_fee += provider_fee;
+    JOBS.save(deps.storage, job_id, &job)?;
+
+    let mut messages = dependent_refunds;
 
     // Send to community pool
     if !community_fee.is_zero() {
         messages.push(BankMsg::Send {
             to_address: config.community_pool.to_string(),
             amount: vec![Coin {
-                denom: "umedas".to_string(),
+                denom: job.denom.clone(),
                 amount: community_fee,
             }],
         });
     }
 
-    // Send to provider
-    messages.push(BankMsg::Send {
-        to_address: job.provider.to_string(),
-        amount: vec![Coin {
-            denom: "umedas".to_string(),
-            amount: provider_fee,
-        }],
-    });
+    // Send to referrer
+    if let Some(referrer) = &job.referrer {
+        if !referral_fee.is_zero() {
+            messages.push(BankMsg::Send {
+                to_address: referrer.to_string(),
+                amount: vec![Coin {
+                    denom: job.denom.clone(),
+                    amount: referral_fee,
+                }],
+            });
+        }
+    }
+
+    // Send to provider, or to its payout address if one is set
+    if !provider_fee.is_zero() {
+        let payout_address = provider.payout_address.unwrap_or(job.provider.clone());
+        messages.push(BankMsg::Send {
+            to_address: payout_address.to_string(),
+            amount: vec![Coin {
+                denom: job.denom.clone(),
+                amount: provider_fee,
+            }],
+        });
+    }
 
     Ok(Response::new()
         .add_messages(messages)
+        .add_event(receipt)
         .add_attribute("action", "complete_job")
         .add_attribute("job_id", job_id.to_string())
         .add_attribute("provider_payment", provider_fee.to_string())
-        .add_attribute("community_fee", community_fee.to_string()))
+        .add_attribute("community_fee", community_fee.to_string())
+        .add_attribute("referral_fee", referral_fee.to_string()))
+}
+
+/// Community share of a payment, rounded per the configured mode
+/// The provider share is the remainder, so the two always sum to the total
+fn community_fee(amount: Uint128, percent: u64, mode: &RoundingMode) -> Uint128 {
+    let floor = amount.multiply_ratio(percent, 100u128);
+    let remainder = amount.full_mul(percent) % Uint256::from(100u128);
+    if remainder.is_zero() {
+        return floor;
+    }
+
+    let round_up = match mode {
+        RoundingMode::TowardProvider => false,
+        RoundingMode::TowardCommunity => true,
+        // Round half to even
+        RoundingMode::Banker => {
+            let half = Uint256::from(50u128);
+            remainder > half || (remainder == half && floor.u128() % 2 == 1)
+        }
+    };
+
+    if round_up {
+        floor + Uint128::one()
+    } else {
+        floor
+    }
 }
 
 pub fn execute_update_provider_status(
@@ -292,14 +996,26 @@ pub fn execute_update_provider_status(
 }
 
 #[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    // Incident kill-switch: only the health check and diagnostics stay readable
+    if !matches!(msg, QueryMsg::GetHealth {} | QueryMsg::GetRawState {})
+        && CONFIG.load(deps.storage)?.query_paused
+    {
+        return Err(StdError::generic_err("Queries are paused"));
+    }
+
     match msg {
         QueryMsg::GetConfig {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::PreviewFeeSplit { amount } => to_json_binary(&query_preview_fee_split(deps, amount)?),
+        QueryMsg::GetFailureRate {} => to_json_binary(&query_failure_rate(deps, env)?),
         QueryMsg::GetProvider { address } => to_json_binary(&query_provider(deps, address)?),
         QueryMsg::ListProviders { start_after, limit } => {
             to_json_binary(&query_list_providers(deps, start_after, limit)?)
         }
         QueryMsg::GetJob { job_id } => to_json_binary(&query_job(deps, job_id)?),
+        QueryMsg::GetJobWithProvider { job_id } => {
+            to_json_binary(&query_job_with_provider(deps, job_id)?)
+        }
         QueryMsg::ListJobsByProvider {
             provider,
             start_after,
@@ -310,22 +1026,155 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             start_after,
             limit,
         } => to_json_binary(&query_jobs_by_client(deps, client, start_after, limit)?),
+        QueryMsg::ListJobsByProviderAndStatus {
+            provider,
+            status,
+            start_after,
+            limit,
+        } => to_json_binary(&query_jobs_by_provider_and_status(
+            deps,
+            provider,
+            status,
+            start_after,
+            limit,
+        )?),
         QueryMsg::ListActiveProviders {} => {
             to_json_binary(&query_list_active_providers(deps)?)
         }
         QueryMsg::GetProviderStats { address } => {
             to_json_binary(&query_provider_stats(deps, address)?)
         }
+        QueryMsg::GetProviderThroughput { provider } => {
+            to_json_binary(&query_provider_throughput(deps, env, provider)?)
+        }
+        QueryMsg::GetJobTypeTimeout { job_type } => {
+            to_json_binary(&query_job_type_timeout(deps, job_type)?)
+        }
+        QueryMsg::GetNextJobId {} => to_json_binary(&query_next_job_id(deps)?),
+        QueryMsg::GetProviderUptime { provider } => {
+            to_json_binary(&query_provider_uptime(deps, provider)?)
+        }
+        QueryMsg::GetSolvency {} => to_json_binary(&query_solvency(deps, env)?),
+        QueryMsg::GetTVL {} => to_json_binary(&query_tvl(deps, env)?),
+        QueryMsg::GetMarketPrice { job_type } => {
+            to_json_binary(&query_market_price(deps, job_type)?)
+        }
+        QueryMsg::PreviewProviderSelection { job_type, parameters, strategy } => {
+            to_json_binary(&query_preview_provider_selection(deps, job_type, parameters, strategy)?)
+        }
+        QueryMsg::GetKeeperWork {} => to_json_binary(&query_keeper_work(deps, env)?),
+        QueryMsg::GetStatusDistribution {} => {
+            to_json_binary(&query_status_distribution(deps)?)
+        }
+        QueryMsg::GetProviderRatings { provider } => {
+            to_json_binary(&query_provider_ratings(deps, provider)?)
+        }
+        QueryMsg::CheapestProviders { job_type, limit } => {
+            to_json_binary(&query_cheapest_providers(deps, job_type, limit)?)
+        }
+        QueryMsg::ListProvidersWithPricing { service_type, start_after, limit } => {
+            to_json_binary(&query_providers_with_pricing(deps, service_type, start_after, limit)?)
+        }
+        QueryMsg::OldestProviders { limit } => {
+            to_json_binary(&query_oldest_providers(deps, env, limit)?)
+        }
+        QueryMsg::ListIdleProviders { limit, min_age } => {
+            to_json_binary(&query_idle_providers(deps, env, limit, min_age)?)
+        }
+        QueryMsg::GetProviderEarnings { provider, from, to, start_after, limit } => {
+            to_json_binary(&query_provider_earnings(deps, provider, from, to, start_after, limit)?)
+        }
+        QueryMsg::ListRefunds { start_after, limit } => {
+            to_json_binary(&query_list_refunds(deps, start_after, limit)?)
+        }
+        QueryMsg::GetRefundsByClient { client, start_after, limit } => {
+            to_json_binary(&query_refunds_by_client(deps, client, start_after, limit)?)
+        }
+        QueryMsg::GetClientStats { client } => {
+            to_json_binary(&query_client_stats(deps, client)?)
+        }
+        QueryMsg::GetClientPrefs { client } => {
+            to_json_binary(&query_client_prefs(deps, client)?)
+        }
+        QueryMsg::GetRole { address } => to_json_binary(&query_role(deps, address)?),
+        QueryMsg::GetActionableJobs { provider, start_after, limit } => {
+            to_json_binary(&query_actionable_jobs(deps, env, provider, start_after, limit)?)
+        }
+        QueryMsg::RecentCompletions { limit } => {
+            to_json_binary(&query_recent_completions(deps, limit)?)
+        }
+        QueryMsg::CheckInvariants {} => to_json_binary(&query_check_invariants(deps)?),
+        QueryMsg::GetHealth {} => to_json_binary(&query_health(deps)?),
+        QueryMsg::GetRawState {} => to_json_binary(&query_raw_state(deps)),
     }
 }
+/// Split an arbitrary amount with the current community fee and rounding mode
+/// Ignores referral shares and discount tiers, which depend on the job
+fn query_preview_fee_split(deps: Deps, amount: Uint128) -> StdResult<FeeSplitResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let community_fee = community_fee(amount, config.community_fee_percent, &config.rounding_mode);
+    Ok(FeeSplitResponse {
+        community_fee,
+        provider_payout: amount.checked_sub(community_fee)?,
+    })
+}
+
+/// Failure rate across all providers in the current window
+fn query_failure_rate(deps: Deps, env: Env) -> StdResult<FailureRateResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let (_, window) = rolling_failure_window(deps.storage, &config, env.block.time.seconds())?;
+    Ok(FailureRateResponse {
+        window_seconds: config.failure_window,
+        failures: window.failures,
+        completions: window.completions,
+        failure_rate: window.failure_rate(),
+        auto_pause_failure_rate: config.auto_pause_failure_rate,
+        auto_pause_min_jobs: config.auto_pause_min_jobs,
+    })
+}
+
 fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let config = CONFIG.load(deps.storage)?;
     Ok(ConfigResponse {
+        admin: config.admin.to_string(),
         community_pool: config.community_pool.to_string(),
         community_fee_percent: config.community_fee_percent,
         default_job_timeout: config.default_job_timeout,      
         heartbeat_timeout: config.heartbeat_timeout,          
         paused: config.paused,                                
+        referral_fee_percent: config.referral_fee_percent,
+        require_heartbeat: config.require_heartbeat,
+        exclusive_window: config.exclusive_window,
+        cancel_fee_percent: config.cancel_fee_percent,
+        max_capabilities: config.max_capabilities,
+        query_paused: config.query_paused,
+        registration_fee: config.registration_fee,
+        rounding_mode: config.rounding_mode,
+        max_providers_per_service: config.max_providers_per_service,
+        capability_reputation_threshold: config.capability_reputation_threshold,
+        low_reputation_max_capabilities: config.low_reputation_max_capabilities,
+        update_cooldown: config.update_cooldown,
+        max_total_active_jobs: config.max_total_active_jobs,
+        enforce_unique_results: config.enforce_unique_results,
+        result_deadline_buffer: config.result_deadline_buffer,
+        min_heartbeat_interval: config.min_heartbeat_interval,
+        cancel_community_fee_percent: config.cancel_community_fee_percent,
+        max_extensions: config.max_extensions,
+        max_total_extension: config.max_total_extension,
+        require_client_allowlist: config.require_client_allowlist,
+        default_reputation: config.default_reputation,
+        accepted_denoms: config.accepted_denoms,
+        max_sweep_batch: config.max_sweep_batch,
+        requeue_fee_discount_percent: config.requeue_fee_discount_percent,
+        refund_on_deactivation: config.refund_on_deactivation,
+        deregister_after: config.deregister_after,
+        probation_period: config.probation_period,
+        probation_max_payment: config.probation_max_payment,
+        probation_min_jobs: config.probation_min_jobs,
+        auto_pause_failure_rate: config.auto_pause_failure_rate,
+        auto_pause_min_jobs: config.auto_pause_min_jobs,
+        failure_window: config.failure_window,
+        reputation_rating_weight: config.reputation_rating_weight,
     })
 }
 
@@ -333,19 +1182,7 @@ fn query_provider(deps: Deps, address: String) -> StdResult<ProviderResponse> {
     let addr = deps.api.addr_validate(&address)?;
     let provider = PROVIDERS.load(deps.storage, &addr)?;
 
-    Ok(ProviderResponse {
-        address: provider.address.to_string(),
-        name: provider.name,
-        capabilities: provider.capabilities,
-        pricing: provider.pricing,
-        endpoint: provider.endpoint,
-        capacity: provider.capacity,
-        active_jobs: provider.active_jobs,
-        total_completed: provider.total_completed,
-        reputation: provider.reputation,
-        active: provider.active,
-        registered_at: provider.registered_at,
-    })
+    Ok(provider_response(provider))
 }
 
 fn query_list_providers(
@@ -362,19 +1199,7 @@ fn query_list_providers(
             .take(limit)
             .map(|item| {
                 let (_, provider) = item?;
-                Ok(ProviderResponse {
-                    address: provider.address.to_string(),
-                    name: provider.name,
-                    capabilities: provider.capabilities,
-                    pricing: provider.pricing,
-                    endpoint: provider.endpoint,
-                    capacity: provider.capacity,
-                    active_jobs: provider.active_jobs,
-                    total_completed: provider.total_completed,
-                    reputation: provider.reputation,
-                    active: provider.active,
-                    registered_at: provider.registered_at,
-                })
+                Ok(provider_response(provider))
             })
             .collect()
     } else {
@@ -383,25 +1208,22 @@ fn query_list_providers(
             .take(limit)
             .map(|item| {
                 let (_, provider) = item?;
-                Ok(ProviderResponse {
-                    address: provider.address.to_string(),
-                    name: provider.name,
-                    capabilities: provider.capabilities,
-                    pricing: provider.pricing,
-                    endpoint: provider.endpoint,
-                    capacity: provider.capacity,
-                    active_jobs: provider.active_jobs,
-                    total_completed: provider.total_completed,
-                    reputation: provider.reputation,
-                    active: provider.active,
-                    registered_at: provider.registered_at,
-                })
+                Ok(provider_response(provider))
             })
             .collect()
     };
 
     Ok(ProvidersResponse { providers: providers? })
 }
+fn query_job_with_provider(deps: Deps, job_id: u64) -> StdResult<JobWithProviderResponse> {
+    let job = query_job(deps, job_id)?;
+    let provider = PROVIDERS
+        .may_load(deps.storage, &Addr::unchecked(&job.provider))?
+        .map(provider_response);
+
+    Ok(JobWithProviderResponse { job, provider })
+}
+
 fn query_job(deps: Deps, job_id: u64) -> StdResult<JobResponse> {
     let job = JOBS.load(deps.storage, job_id)?;
 
@@ -417,6 +1239,31 @@ fn query_job(deps: Deps, job_id: u64) -> StdResult<JobResponse> {
         result_url: job.result_url,
         created_at: job.created_at,
         completed_at: job.completed_at,
+        referrer: job.referrer.map(|r| r.to_string()),
+        results: job.results,
+        preferred_provider: job.preferred_provider.map(|p| p.to_string()),
+        exclusive_until: job.exclusive_until,
+        provider_reputation_at_submit: job.provider_reputation_at_submit,
+        cancel_reason: job.cancel_reason,
+        encryption: job.encryption,
+        result_size_bytes: job.result_size_bytes,
+        rating: job.rating,
+        depends_on: job.depends_on,
+        result_deadline: job.result_deadline,
+        deadline: job.deadline,
+        extension_count: job.extension_count,
+        reported_percent: job.reported_percent,
+        released_amount: job.released_amount,
+        original_job_id: job.original_job_id,
+        requeued_as: job.requeued_as,
+        history: job
+            .history
+            .iter()
+            .map(|change| StatusChangeResponse {
+                status: change.status.to_string(),
+                at: change.at,
+            })
+            .collect(),
     })
 }
 
@@ -429,7 +1276,7 @@ fn query_jobs_by_provider(
     let provider_addr = deps.api.addr_validate(&provider)?;
     let limit = limit.unwrap_or(10).min(50) as usize;
 
-    let start = start_after.map(|id| Bound::exclusive(id));
+    let start = start_after.map(Bound::exclusive);
 
     let job_ids: Vec<u64> = JOBS_BY_PROVIDER
         .prefix(&provider_addr)
@@ -437,12 +1284,68 @@ fn query_jobs_by_provider(
         .take(limit)
         .collect::<StdResult<Vec<_>>>()?;
 
+    let next_start_after = match job_ids.last() {
+        Some(&last) if job_ids.len() == limit => Some(last),
+        _ => None,
+    };
     let jobs: Vec<JobResponse> = job_ids
         .into_iter()
         .map(|job_id| query_job(deps, job_id))
         .collect::<StdResult<Vec<_>>>()?;
 
-    Ok(JobsResponse { jobs })
+    Ok(JobsResponse { jobs, next_start_after })
+}
+
+fn query_jobs_by_provider_and_status(
+    deps: Deps,
+    provider: String,
+    status: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<JobsResponse> {
+    let provider_addr = deps.api.addr_validate(&provider)?;
+    let status: JobStatus = status.parse()?;
+
+    scan_provider_jobs(deps, &provider_addr, start_after, limit, |job| job.status == status)
+}
+
+/// Walk a bounded stretch of a provider's job index, keeping the jobs that match.
+/// The cursor is the last job scanned, so the next page skips what this one already read
+fn scan_provider_jobs(
+    deps: Deps,
+    provider: &Addr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    keep: impl Fn(&Job) -> bool,
+) -> StdResult<JobsResponse> {
+    let limit = limit.unwrap_or(10).min(50) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let mut jobs = vec![];
+    let mut scanned = 0;
+    let mut last_scanned = None;
+    for job_id in JOBS_BY_PROVIDER
+        .prefix(provider)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(MAX_JOB_SCAN)
+    {
+        let job = JOBS.load(deps.storage, job_id?)?;
+        scanned += 1;
+        last_scanned = Some(job.id);
+        if keep(&job) {
+            jobs.push(query_job(deps, job.id)?);
+            if jobs.len() == limit {
+                break;
+            }
+        }
+    }
+
+    let next_start_after = if jobs.len() == limit || scanned == MAX_JOB_SCAN {
+        last_scanned
+    } else {
+        None
+    };
+    Ok(JobsResponse { jobs, next_start_after })
 }
 
 fn query_jobs_by_client(
@@ -454,7 +1357,7 @@ fn query_jobs_by_client(
     let client_addr = deps.api.addr_validate(&client)?;
     let limit = limit.unwrap_or(10).min(50) as usize;
 
-    let start = start_after.map(|id| Bound::exclusive(id));
+    let start = start_after.map(Bound::exclusive);
 
     let job_ids: Vec<u64> = JOBS_BY_CLIENT
         .prefix(&client_addr)
@@ -462,13 +1365,49 @@ fn query_jobs_by_client(
         .take(limit)
         .collect::<StdResult<Vec<_>>>()?;
 
+    let next_start_after = match job_ids.last() {
+        Some(&last) if job_ids.len() == limit => Some(last),
+        _ => None,
+    };
     let jobs: Vec<JobResponse> = job_ids
         .into_iter()
         .map(|job_id| query_job(deps, job_id))
         .collect::<StdResult<Vec<_>>>()?;
 
-    Ok(JobsResponse { jobs })
+    Ok(JobsResponse { jobs, next_start_after })
+}
+/// Jobs a provider can act on now - Submitted or Processing, and neither the job
+/// deadline nor the result deadline has passed. Blocked jobs are not yet eligible
+fn query_actionable_jobs(
+    deps: Deps,
+    env: Env,
+    provider: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<JobsResponse> {
+    let provider_addr = deps.api.addr_validate(&provider)?;
+    let now = env.block.time.seconds();
+
+    scan_provider_jobs(deps, &provider_addr, start_after, limit, |job| {
+        matches!(job.status, JobStatus::Submitted | JobStatus::Processing)
+            && now <= job.deadline
+            && job.result_deadline.is_none_or(|deadline| now <= deadline)
+    })
+}
+
+/// Most recently completed jobs across all providers and clients
+fn query_recent_completions(deps: Deps, limit: Option<u32>) -> StdResult<JobsResponse> {
+    let limit = limit.unwrap_or(10).min(50) as usize;
+
+    let jobs: Vec<JobResponse> = COMPLETED_BY_TIME
+        .keys(deps.storage, None, None, Order::Descending)
+        .take(limit)
+        .map(|key| key.and_then(|(_, job_id)| query_job(deps, job_id)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(JobsResponse { jobs, next_start_after: None })
 }
+
 /// Heartbeat handler - providers send regular heartbeats to indicate they are online
 /// This updates the provider's last_heartbeat timestamp and sets them as active
 pub fn execute_heartbeat(
@@ -476,10 +1415,22 @@ pub fn execute_heartbeat(
     env: Env,
     info: MessageInfo,
 ) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
     // Update provider's heartbeat timestamp
     PROVIDERS.update(deps.storage, &info.sender, |provider| -> Result<_, ContractError> {
         let mut p = provider.ok_or(ContractError::ProviderNotFound {})?;
+        // Spamming heartbeats shouldn't make a provider look fresher
+        let retry_after = p.last_heartbeat + config.min_heartbeat_interval;
+        if config.min_heartbeat_interval > 0 && env.block.time.seconds() < retry_after {
+            return Err(ContractError::HeartbeatTooSoon { retry_after });
+        }
         p.last_heartbeat = env.block.time.seconds();
+        // At most one kept window per heartbeat timeout, however often the provider checks in
+        if p.last_heartbeat >= p.window_credited_at + config.heartbeat_timeout {
+            p.total_heartbeat_windows += 1;
+            p.window_credited_at = p.last_heartbeat;
+        }
         p.active = true;
         Ok(p)
     })?;
@@ -491,19 +1442,46 @@ pub fn execute_heartbeat(
 }
 
 /// Update provider information - allows providers to modify their settings
-/// Can update name, endpoint, pricing, and capacity
+/// Can update name, endpoint, pricing, capacity, payout address, capabilities,
+/// auto-accept and the refund guarantee
+#[allow(clippy::too_many_arguments)]
 pub fn execute_update_provider(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     name: Option<String>,
     endpoint: Option<String>,
     pricing: Option<HashMap<String, PricingTier>>,
     capacity: Option<u32>,
+    payout_address: Option<String>,
+    capabilities: Option<Vec<crate::msg::ServiceCapability>>,
+    auto_accept: Option<bool>,
+    refund_guarantee: Option<bool>,
+    max_payment: Option<Uint128>,
 ) -> Result<Response, ContractError> {
+    let payout_address = payout_address
+        .map(|a| deps.api.addr_validate(&a))
+        .transpose()?;
+    let config = CONFIG.load(deps.storage)?;
+    let capabilities_changed = capabilities.is_some();
+    let old_capabilities = PROVIDERS
+        .may_load(deps.storage, &info.sender)?
+        .map(|p| p.capabilities)
+        .unwrap_or_default();
+
     // Load and update provider information
-    PROVIDERS.update(deps.storage, &info.sender, |provider| -> Result<_, ContractError> {
+    let updated = PROVIDERS.update(deps.storage, &info.sender, |provider| -> Result<_, ContractError> {
         let mut p = provider.ok_or(ContractError::ProviderNotFound {})?;
+
+        // Rate limit changes so clients aren't caught mid-submission
+        let now = env.block.time.seconds();
+        if let Some(last) = p.last_updated_at {
+            let retry_after = last + config.update_cooldown;
+            if now < retry_after {
+                return Err(ContractError::UpdateTooFrequent { retry_after });
+            }
+        }
+        p.last_updated_at = Some(now);
         
         // Update fields if provided
         if let Some(n) = name {
@@ -516,26 +1494,135 @@ pub fn execute_update_provider(
             p.pricing = pr;
         }
         if let Some(c) = capacity {
+            if c == 0 {
+                return Err(ContractError::InvalidCapacity {});
+            }
             p.capacity = c;
         }
+        if let Some(a) = payout_address {
+            p.payout_address = Some(a);
+        }
+        if let Some(caps) = capabilities {
+            if caps.is_empty() {
+                return Err(ContractError::InvalidProviderData {});
+            }
+            if caps.len() > config.max_capabilities as usize {
+                return Err(ContractError::TooManyCapabilities { max: config.max_capabilities });
+            }
+            // Providers still proving themselves can only grow up to a smaller limit
+            if caps.len() > p.capabilities.len() && p.reputation < config.capability_reputation_threshold {
+                let max = config.low_reputation_max_capabilities;
+                if caps.len() > max as usize {
+                    return Err(ContractError::ReputationTooLow { max });
+                }
+            }
+            p.capabilities = caps;
+        }
+        if let Some(auto) = auto_accept {
+            p.auto_accept = auto;
+        }
+        if let Some(guarantee) = refund_guarantee {
+            p.refund_guarantee = guarantee;
+        }
+        if let Some(max) = max_payment {
+            p.max_payment = (!max.is_zero()).then_some(max);
+        }
         
         Ok(p)
     })?;
+
+    if capabilities_changed {
+        reindex_services(
+            deps.storage,
+            &info.sender,
+            &old_capabilities,
+            &updated.capabilities,
+            &config,
+        )?;
+    }
     
     Ok(Response::new()
         .add_attribute("action", "update_provider")
         .add_attribute("provider", info.sender.to_string()))
 }
 
-/// Fail a job - provider marks job as failed and client receives full refund
-/// Only the assigned provider can fail their own jobs
+/// Drop a single service from the sender's capabilities, along with its pricing
+/// A provider must keep at least one capability
+pub fn execute_remove_capability(
+    deps: DepsMut,
+    info: MessageInfo,
+    service_type: String,
+) -> Result<Response, ContractError> {
+    let mut provider = PROVIDERS
+        .may_load(deps.storage, &info.sender)?
+        .ok_or(ContractError::ProviderNotFound {})?;
+
+    let index = provider
+        .capabilities
+        .iter()
+        .position(|c| c.service_type == service_type)
+        .ok_or(ContractError::ServiceNotOffered {
+            job_type: service_type.clone(),
+        })?;
+    if provider.capabilities.len() == 1 {
+        return Err(ContractError::LastCapability {});
+    }
+
+    provider.capabilities.remove(index);
+    provider.pricing.remove(&service_type);
+    PROVIDERS_BY_SERVICE.remove(deps.storage, (service_type.as_str(), &info.sender));
+    PROVIDERS.save(deps.storage, &info.sender, &provider)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_capability")
+        .add_attribute("provider", info.sender.to_string())
+        .add_attribute("service_type", service_type))
+}
+
+/// Enable or disable one of the sender's services without touching the others
+/// Jobs already accepted for a disabled service are unaffected
+pub fn execute_set_service_enabled(
+    deps: DepsMut,
+    info: MessageInfo,
+    service_type: String,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    let mut provider = PROVIDERS
+        .may_load(deps.storage, &info.sender)?
+        .ok_or(ContractError::ProviderNotFound {})?;
+
+    let capability = provider
+        .capabilities
+        .iter_mut()
+        .find(|c| c.service_type == service_type)
+        .ok_or(ContractError::ServiceNotOffered {
+            job_type: service_type.clone(),
+        })?;
+    capability.enabled = enabled;
+    PROVIDERS.save(deps.storage, &info.sender, &provider)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_service_enabled")
+        .add_attribute("provider", info.sender.to_string())
+        .add_attribute("service_type", service_type)
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+/// Fail a job - provider marks job as failed and client receives a refund
+/// The provider may keep part of the payment for partial work, unless it
+/// advertises a refund guarantee. Only the assigned provider can fail their own jobs
 pub fn execute_fail_job(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     job_id: u64,
     reason: String,
+    refund_percent: Option<u64>,
 ) -> Result<Response, ContractError> {
+    if let Some(percent) = refund_percent.filter(|percent| *percent > 100) {
+        return Err(ContractError::InvalidRefundPercent { percent });
+    }
+
     // Load job
     let mut job = JOBS.load(deps.storage, job_id)?;
     
@@ -544,71 +1631,116 @@ pub fn execute_fail_job(
         return Err(ContractError::Unauthorized {});
     }
     
-    // Job must be in submitted state
-    if job.status != JobStatus::Submitted {
-        return Err(ContractError::InvalidJobState {});  // ← Verwendet bestehenden Error
-    }
-    
     // Update job status
-    job.status = JobStatus::Failed;
+    transition_job(deps.storage, &mut job, JobStatus::Failed, env.block.time)?;
     job.failure_reason = Some(reason.clone());
     job.completed_at = Some(env.block.time);
+    job.counted_toward_pause = !job.is_self_dealt();
     JOBS.save(deps.storage, job_id, &job)?;
     
     // Update provider statistics
     let mut provider = PROVIDERS.load(deps.storage, &job.provider)?;
     provider.active_jobs = provider.active_jobs.saturating_sub(1);
     provider.total_failed = provider.total_failed.saturating_add(1);
-    provider.reputation = calculate_reputation(&provider);
+    provider.record_outcome(job_id, false);
+    let config = CONFIG.load(deps.storage)?;
+    provider.reputation = calculate_reputation(&provider, config.reputation_rating_weight);
     PROVIDERS.save(deps.storage, &job.provider, &provider)?;
+    // Counted like completions, so the rate compares provider-reported outcomes on both sides
+    let alert = record_job_outcome(deps.storage, &job, env.block.time.seconds(), true)?;
     
-    // Refund full payment to client
-    let refund_msg = BankMsg::Send {
-    to_address: job.client.to_string(),
-    amount: vec![Coin {
-        denom: "umedas".to_string(),
-        amount: job.payment_amount,
-    }],
-};
+    // Guaranteed providers always refund in full
+    let refund_percent = if provider.refund_guarantee {
+        100
+    } else {
+        refund_percent.unwrap_or(100)
+    };
+    // Progress already paid out stays with the provider
+    let remaining = job.remaining_escrow();
+    let refund_amount = remaining.multiply_ratio(refund_percent, 100u128);
+    let retained = remaining.checked_sub(refund_amount)
+    .map_err(|e| ContractError::Std(cosmwasm_std::StdError::generic_err(e.to_string())))?;
+
+    let mut messages = vec![];
+    if !refund_amount.is_zero() {
+        record_refund(deps.storage, &env, &job, refund_amount, RefundReason::Fail)?;
+        messages.push(BankMsg::Send {
+            to_address: job.client.to_string(),
+            amount: vec![Coin {
+                denom: job.denom.clone(),
+                amount: refund_amount,
+            }],
+        });
+    }
+
+    // Retained share is split with the community like a regular payout
+    if !retained.is_zero() {
+        let community_fee = community_fee(retained, config.community_fee_percent, &config.rounding_mode);
+        let provider_fee = retained.checked_sub(community_fee)
+        .map_err(|e| ContractError::Std(cosmwasm_std::StdError::generic_err(e.to_string())))?;
+        if !community_fee.is_zero() {
+            messages.push(BankMsg::Send {
+                to_address: config.community_pool.to_string(),
+                amount: vec![Coin {
+                    denom: job.denom.clone(),
+                    amount: community_fee,
+                }],
+            });
+        }
+        if !provider_fee.is_zero() {
+            let payout_address = provider.payout_address.unwrap_or(job.provider.clone());
+            messages.push(BankMsg::Send {
+                to_address: payout_address.to_string(),
+                amount: vec![Coin {
+                    denom: job.denom.clone(),
+                    amount: provider_fee,
+                }],
+            });
+        }
+    }
+
+    // Jobs waiting on this one can no longer run
+    messages.extend(resolve_dependents(deps.storage, &env, job_id, false)?);
     
     Ok(Response::new()
-        .add_message(refund_msg)
+        .add_messages(messages)
+        .add_events(alert)
         .add_attribute("action", "fail_job")
         .add_attribute("job_id", job_id.to_string())
         .add_attribute("reason", reason)
-        .add_attribute("refund_amount", job.payment_amount.to_string())) 
+        .add_attribute("refund_amount", refund_amount.to_string())
+        .add_attribute("retained_amount", retained.to_string())) 
 }
 
-/// Cancel a job - client can cancel within 5 minutes and receive full refund
+/// Cancel a job - client can cancel within 5 minutes and receive a refund
+/// minus the configured cancellation fee, which goes to the provider
 /// Only the client who submitted the job can cancel it
 pub fn execute_cancel_job(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     job_id: u64,
+    reason: Option<String>,
 ) -> Result<Response, ContractError> {
     // Load job
     let mut job = JOBS.load(deps.storage, job_id)?;
-    
-    // Only the client can cancel their job
-    if info.sender != job.client {
-        return Err(ContractError::Unauthorized {});
-    }
-    
-    // Job must be in submitted state
-    if job.status != JobStatus::Submitted {
-        return Err(ContractError::InvalidJobState {});  // ← Verwendet bestehenden Error
+
+    if let Some(reason) = &reason {
+        if reason.len() > MAX_REASON_LENGTH {
+            return Err(ContractError::ReasonTooLong { max: MAX_REASON_LENGTH });
+        }
     }
+    check_cancellable(&job, &info.sender, &env)?;
     
-    // Check if within 5-minute cancellation window
-    let time_elapsed = env.block.time.seconds() - job.created_at.seconds();
-    if time_elapsed > 300 {  // 300 seconds = 5 minutes
-        return Err(ContractError::CancelWindowExpired {});
+    // A blocked job stops waiting on its dependency
+    if let (JobStatus::Blocked, Some(dependency_id)) = (&job.status, job.depends_on) {
+        JOBS_BY_DEPENDENCY.remove(deps.storage, (dependency_id, job_id));
     }
-    
+
     // Update job status
-    job.status = JobStatus::Cancelled;
+    transition_job(deps.storage, &mut job, JobStatus::Cancelled, env.block.time)?;
     job.completed_at = Some(env.block.time);
+    job.cancel_reason = reason;
     JOBS.save(deps.storage, job_id, &job)?;
     
     // Update provider statistics (no reputation penalty for cancellation)
@@ -616,20 +1748,116 @@ pub fn execute_cancel_job(
     provider.active_jobs = provider.active_jobs.saturating_sub(1);
     PROVIDERS.save(deps.storage, &job.provider, &provider)?;
     
-    // Refund full payment to client
-    let refund_msg = BankMsg::Send {
-    to_address: job.client.to_string(),
-    amount: vec![Coin {
-        denom: "umedas".to_string(),
-        amount: job.payment_amount,
-    }],
-    };
+    // Cancellation fee compensates the provider for the reserved slot
+    let config = CONFIG.load(deps.storage)?;
+    let cancel_fee = job.payment_amount * Decimal::percent(config.cancel_fee_percent);
+    // Optional processing fee retained for the community pool
+    let community_fee = job.payment_amount * Decimal::percent(config.cancel_community_fee_percent);
+    let refund_amount = job.payment_amount.checked_sub(cancel_fee + community_fee)
+    .map_err(|e| ContractError::Std(cosmwasm_std::StdError::generic_err(e.to_string())))?;
+
+    let mut messages = vec![];
+    if !community_fee.is_zero() {
+        messages.push(BankMsg::Send {
+            to_address: config.community_pool.to_string(),
+            amount: vec![Coin {
+                denom: job.denom.clone(),
+                amount: community_fee,
+            }],
+        });
+    }
+    if !cancel_fee.is_zero() {
+        let payout_address = provider.payout_address.unwrap_or(job.provider.clone());
+        messages.push(BankMsg::Send {
+            to_address: payout_address.to_string(),
+            amount: vec![Coin {
+                denom: job.denom.clone(),
+                amount: cancel_fee,
+            }],
+        });
+    }
+
+    // Refund the remaining payment to client
+    if !refund_amount.is_zero() {
+        record_refund(deps.storage, &env, &job, refund_amount, RefundReason::Cancel)?;
+        messages.push(BankMsg::Send {
+            to_address: job.client.to_string(),
+            amount: vec![Coin {
+                denom: job.denom.clone(),
+                amount: refund_amount,
+            }],
+        });
+    }
+
+    // Jobs waiting on this one can no longer run
+    messages.extend(resolve_dependents(deps.storage, &env, job_id, false)?);
     
     Ok(Response::new()
-        .add_message(refund_msg)
+        .add_messages(messages)
         .add_attribute("action", "cancel_job")
         .add_attribute("job_id", job_id.to_string())
-        .add_attribute("refund_amount", job.payment_amount.to_string()))
+        .add_attribute("refund_amount", refund_amount.to_string())
+        .add_attribute("cancel_fee", cancel_fee.to_string())
+        .add_attribute("community_fee", community_fee.to_string()))
+}
+
+/// Why `sender` may not cancel `job` right now, if anything
+/// Shared with the batch so ineligible jobs are found before any state is written
+fn check_cancellable(job: &Job, sender: &Addr, env: &Env) -> Result<(), ContractError> {
+    // Only the client can cancel their job
+    if *sender != job.client {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Job must still be cancellable
+    if !can_transition(&job.status, &JobStatus::Cancelled) {
+        return Err(ContractError::IllegalTransition {
+            from: job.status.to_string(),
+            to: JobStatus::Cancelled.to_string(),
+        });
+    }
+    // Check if within 5-minute cancellation window
+    let time_elapsed = env.block.time.seconds().saturating_sub(job.created_at.seconds());
+    if time_elapsed > 300 {  // 300 seconds = 5 minutes
+        return Err(ContractError::CancelWindowExpired {});
+    }
+    Ok(())
+}
+
+/// Cancel several of the sender's jobs at once, under the same rules as CancelJob
+/// Ineligible jobs are skipped and reported in a per-id attribute instead of failing the tx
+pub fn execute_cancel_jobs_batch(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    job_ids: Vec<u64>,
+) -> Result<Response, ContractError> {
+    let mut response = Response::new().add_attribute("action", "cancel_jobs_batch");
+    let mut cancelled = vec![];
+
+    for job_id in job_ids {
+        // Eligibility is settled before cancelling, so only ineligible jobs are skipped;
+        // anything failing mid-cancel aborts the whole batch rather than leave a half-written job
+        let eligibility = match JOBS.may_load(deps.storage, job_id)? {
+            Some(job) => check_cancellable(&job, &info.sender, &env),
+            None => Err(ContractError::JobNotFound {}),
+        };
+        if let Err(err) = eligibility {
+            response = response.add_attribute(format!("job_{}", job_id), format!("skipped: {}", err));
+            continue;
+        }
+
+        let res = execute_cancel_job(deps.branch(), env.clone(), info.clone(), job_id, None)?;
+        response = response
+            .add_submessages(res.messages)
+            .add_event(Event::new("cancel_job").add_attributes(res.attributes))
+            .add_events(res.events)
+            .add_attribute(format!("job_{}", job_id), "cancelled");
+        cancelled.push(job_id);
+    }
+
+    Ok(response
+        .add_attribute("cancelled_count", cancelled.len().to_string())
+        .add_attribute("cancelled_job_ids", format!("{:?}", cancelled)))
 }
 
 /// Process timed out jobs - automatically fails and refunds jobs that exceeded their deadline
@@ -638,57 +1866,125 @@ pub fn execute_process_timed_out_jobs(
     deps: DepsMut,
     env: Env,
     _info: MessageInfo,
+    limit: Option<u32>,
 ) -> Result<Response, ContractError> {
     let current_time = env.block.time.seconds();
     let mut messages: Vec<CosmosMsg> = vec![];
+    let mut alerts: Vec<Event> = vec![];
     let mut processed_jobs = vec![];
+
+    // Bounded per call so a backlog can be swept across several transactions
+    let config = CONFIG.load(deps.storage)?;
+    let max_batch = match config.max_sweep_batch {
+        0 => u32::MAX,
+        max => max,
+    };
+    let batch = limit.unwrap_or(max_batch).min(max_batch) as usize;
     
-    // Iterate through all jobs to find timed out ones
-    let jobs: Vec<_> = JOBS
-        .range(deps.storage, None, None, Order::Ascending)
+    // Open jobs whose deadline has passed, oldest deadline first, plus one to tell if more are waiting
+    let mut job_ids: Vec<u64> = JOBS_BY_DEADLINE
+        .keys(
+            deps.storage,
+            None,
+            Some(Bound::exclusive((current_time, 0u64))),
+            Order::Ascending,
+        )
+        .take(batch.saturating_add(1))
+        .map(|key| key.map(|(_, job_id)| job_id))
         .collect::<StdResult<Vec<_>>>()?;
+    let has_more = job_ids.len() > batch;
+    job_ids.truncate(batch);
     
-    for (job_id, mut job) in jobs {
-        // Only process submitted jobs
-        if job.status != JobStatus::Submitted {
+    for job_id in job_ids {
+        let mut job = JOBS.load(deps.storage, job_id)?;
+        // Only process jobs that can still fail
+        if !can_transition(&job.status, &JobStatus::Failed) {
             continue;
         }
         
         // Check if job has exceeded its deadline
         if current_time > job.deadline {
-            // Mark job as failed
-            job.status = JobStatus::Failed;
-            job.failure_reason = Some("Timeout: Job not completed within deadline".to_string());
-            job.completed_at = Some(env.block.time);
-            JOBS.save(deps.storage, job_id, &job)?;
-            
-            // Update provider statistics (timeout counts as failure)
-            let mut provider = PROVIDERS.load(deps.storage, &job.provider)?;
-            provider.active_jobs = provider.active_jobs.saturating_sub(1);
-            provider.total_failed = provider.total_failed.saturating_add(1);
-            provider.reputation = calculate_reputation(&provider);
-            PROVIDERS.save(deps.storage, &job.provider, &provider)?;
-            
-            // Prepare refund message
-            messages.push(CosmosMsg::Bank(BankMsg::Send {
-            to_address: job.client.to_string(),
-            amount: vec![Coin {
-            denom: "umedas".to_string(),
-            amount: job.payment_amount,
-            }],
-            }));
-            
+            let (refunds, alert) = fail_and_refund(
+                deps.storage,
+                &env,
+                &mut job,
+                "Timeout: Job not completed within deadline",
+                RefundReason::Timeout,
+                true,
+            )?;
+            messages.extend(refunds);
+            alerts.extend(alert);
             processed_jobs.push(job_id);
         }
     }
     
     Ok(Response::new()
         .add_messages(messages)
+        .add_events(alerts)
         .add_attribute("action", "process_timed_out_jobs")
         .add_attribute("processed_count", processed_jobs.len().to_string())
+        .add_attribute("has_more", has_more.to_string())
         .add_attribute("job_ids", format!("{:?}", processed_jobs)))
 }
 
+/// Fail a job through no fault of the client and refund whatever is still in escrow
+/// Dependents are cancelled. With `count_failure` it counts against the provider and feeds
+/// the auto-pause, whose alert is returned if it trips; administrative refunds pass false
+fn fail_and_refund(
+    storage: &mut dyn Storage,
+    env: &Env,
+    job: &mut Job,
+    reason: &str,
+    refund_reason: RefundReason,
+    count_failure: bool,
+) -> Result<(Vec<CosmosMsg>, Option<Event>), ContractError> {
+    let mut messages = vec![];
+
+    transition_job(storage, job, JobStatus::Failed, env.block.time)?;
+    job.failure_reason = Some(reason.to_string());
+    job.completed_at = Some(env.block.time);
+    // Administrative failures have no penalty to waive
+    job.penalty_waived = !count_failure;
+    job.counted_toward_pause = count_failure && !job.is_self_dealt();
+    JOBS.save(storage, job.id, job)?;
+
+    let mut provider = PROVIDERS.load(storage, &job.provider)?;
+    provider.active_jobs = provider.active_jobs.saturating_sub(1);
+    if count_failure {
+        provider.total_failed = provider.total_failed.saturating_add(1);
+        provider.record_outcome(job.id, false);
+        let rating_weight = CONFIG.load(storage)?.reputation_rating_weight;
+        provider.reputation = calculate_reputation(&provider, rating_weight);
+    }
+    PROVIDERS.save(storage, &job.provider, &provider)?;
+    let alert = if count_failure {
+        record_job_outcome(storage, job, env.block.time.seconds(), true)?
+    } else {
+        None
+    };
+
+    let refund_amount = job.remaining_escrow();
+    if !refund_amount.is_zero() {
+        record_refund(storage, env, job, refund_amount, refund_reason)?;
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: job.client.to_string(),
+            amount: vec![Coin {
+                denom: job.denom.clone(),
+                amount: refund_amount,
+            }],
+        }));
+    }
+
+    // Jobs waiting on this one can no longer run
+    messages.extend(
+        resolve_dependents(storage, env, job.id, false)?
+            .into_iter()
+            .map(CosmosMsg::Bank),
+    );
+
+    Ok((messages, alert))
+}
+
 /// Process inactive providers - deactivates providers that haven't sent heartbeat
 /// Can be called by anyone to clean up inactive providers
 pub fn execute_process_inactive_providers(
@@ -699,6 +1995,22 @@ pub fn execute_process_inactive_providers(
     let config = CONFIG.load(deps.storage)?;
     let current_time = env.block.time.seconds();
     let mut deactivated = vec![];
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut alerts: Vec<Event> = vec![];
+    let mut refunded_jobs = vec![];
+    // Shares the sweep budget; jobs beyond it are left to time out as before
+    let mut refund_budget = match config.max_sweep_batch {
+        0 => usize::MAX,
+        max => max as usize,
+    };
+
+    // Heartbeats disabled - providers are never auto-deactivated
+    if !config.require_heartbeat {
+        return Ok(Response::new()
+            .add_attribute("action", "process_inactive_providers")
+            .add_attribute("deactivated_count", "0")
+            .add_attribute("providers", ""));
+    }
     
     // Iterate through all providers
     let providers: Vec<_> = PROVIDERS
@@ -707,38 +2019,151 @@ pub fn execute_process_inactive_providers(
     
     for (addr, mut provider) in providers {
         if provider.active {
-            // Check time since last heartbeat
-            let time_since_heartbeat = current_time - provider.last_heartbeat;
-            
             // Deactivate if exceeded timeout threshold
-            if time_since_heartbeat > config.heartbeat_timeout {
+            if heartbeat_expired(&provider, &config, current_time) {
                 provider.active = false;
+                provider.total_heartbeat_windows += 1;
+                provider.missed_windows += 1;
                 PROVIDERS.save(deps.storage, &addr, &provider)?;
                 deactivated.push(addr.to_string());
+
+                // Clients of jobs the provider never picked up are refunded straight away
+                // Newest first, and every job looked at spends budget so the scan stays bounded
+                if config.refund_on_deactivation && refund_budget > 0 {
+                    let job_ids = JOBS_BY_PROVIDER
+                        .prefix(&addr)
+                        .keys(deps.storage, None, None, Order::Descending)
+                        .take(refund_budget)
+                        .collect::<StdResult<Vec<_>>>()?;
+                    refund_budget -= job_ids.len();
+                    for job_id in job_ids {
+                        let mut job = JOBS.load(deps.storage, job_id)?;
+                        if job.status != JobStatus::Submitted {
+                            continue;
+                        }
+                        let (refunds, alert) = fail_and_refund(
+                            deps.storage,
+                            &env,
+                            &mut job,
+                            "Provider deactivated",
+                            RefundReason::Fail,
+                            false,
+                        )?;
+                        messages.extend(refunds);
+                        alerts.extend(alert);
+                        refunded_jobs.push(job_id);
+                    }
+                }
             }
         }
     }
     
     Ok(Response::new()
+        .add_messages(messages)
+        .add_events(alerts)
         .add_attribute("action", "process_inactive_providers")
         .add_attribute("deactivated_count", deactivated.len().to_string())
-        .add_attribute("providers", deactivated.join(",")))
+        .add_attribute("providers", deactivated.join(","))
+        .add_attribute("refunded_jobs", format!("{:?}", refunded_jobs)))
+}
+
+/// Prune stale providers - removes providers that have gone without a heartbeat for
+/// longer than deregister_after and have no jobs in flight. Callable by anyone
+pub fn execute_prune_stale_providers(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let current_time = env.block.time.seconds();
+    let mut pruned = vec![];
+
+    if config.deregister_after > 0 {
+        let max_batch = match config.max_sweep_batch {
+            0 => u32::MAX,
+            max => max,
+        };
+        let batch = limit.unwrap_or(max_batch).min(max_batch) as usize;
+
+        // Only providers with no completed or failed jobs go; anyone with a track record keeps
+        // it, so re-registering can't wipe a bad reputation and their jobs stay rateable
+        let stale: Vec<_> = PROVIDERS
+            .range(deps.storage, None, None, Order::Ascending)
+            .take(MAX_PROVIDER_SCAN)
+            .filter(|item| match item {
+                Ok((_, provider)) => {
+                    provider.active_jobs == 0
+                        && provider.total_completed + provider.total_failed == 0
+                        && current_time.saturating_sub(provider.last_heartbeat) > config.deregister_after
+                }
+                Err(_) => true,
+            })
+            .take(batch)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (addr, provider) in stale {
+            for capability in &provider.capabilities {
+                PROVIDERS_BY_SERVICE.remove(deps.storage, (capability.service_type.as_str(), &addr));
+            }
+            PROVIDERS.remove(deps.storage, &addr);
+            pruned.push(addr.to_string());
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "prune_stale_providers")
+        .add_attribute("pruned_count", pruned.len().to_string())
+        .add_attribute("providers", pruned.join(",")))
 }
 
 /// Update contract configuration - admin only
 /// Can update job timeout and heartbeat timeout settings
+#[allow(clippy::too_many_arguments)]
 pub fn execute_update_config(
     deps: DepsMut,
-    _info: MessageInfo,
+    info: MessageInfo,
     default_job_timeout: Option<u64>,
     heartbeat_timeout: Option<u64>,
+    referral_fee_percent: Option<u64>,
+    require_heartbeat: Option<bool>,
+    exclusive_window: Option<u64>,
+    cancel_fee_percent: Option<u64>,
+    max_capabilities: Option<u32>,
+    query_paused: Option<bool>,
+    registration_fee: Option<Uint128>,
+    rounding_mode: Option<RoundingMode>,
+    max_providers_per_service: Option<u32>,
+    capability_reputation_threshold: Option<Decimal>,
+    low_reputation_max_capabilities: Option<u32>,
+    update_cooldown: Option<u64>,
+    max_total_active_jobs: Option<u64>,
+    enforce_unique_results: Option<bool>,
+    result_deadline_buffer: Option<u64>,
+    min_heartbeat_interval: Option<u64>,
+    cancel_community_fee_percent: Option<u64>,
+    max_extensions: Option<u32>,
+    max_total_extension: Option<u64>,
+    require_client_allowlist: Option<bool>,
+    default_reputation: Option<Decimal>,
+    accepted_denoms: Option<Vec<String>>,
+    max_sweep_batch: Option<u32>,
+    requeue_fee_discount_percent: Option<u64>,
+    refund_on_deactivation: Option<bool>,
+    deregister_after: Option<u64>,
+    probation_period: Option<u64>,
+    probation_max_payment: Option<Uint128>,
+    probation_min_jobs: Option<u64>,
+    auto_pause_failure_rate: Option<Decimal>,
+    auto_pause_min_jobs: Option<u32>,
+    failure_window: Option<u64>,
+    reputation_rating_weight: Option<u64>,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
     
-    // TODO: Add admin check
-    // if info.sender != config.admin {
-    //     return Err(ContractError::Unauthorized {});
-    // }
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
     
     // Update config fields if provided
     if let Some(timeout) = default_job_timeout {
@@ -747,6 +2172,131 @@ pub fn execute_update_config(
     if let Some(hb_timeout) = heartbeat_timeout {
         config.heartbeat_timeout = hb_timeout;
     }
+    if let Some(percent) = referral_fee_percent {
+        if percent > 100 {
+            return Err(ContractError::InvalidFeePercent { percent });
+        }
+        config.referral_fee_percent = percent;
+    }
+    if let Some(required) = require_heartbeat {
+        config.require_heartbeat = required;
+    }
+    if let Some(window) = exclusive_window {
+        config.exclusive_window = window;
+    }
+    if let Some(percent) = cancel_fee_percent {
+        if percent > 100 {
+            return Err(ContractError::InvalidFeePercent { percent });
+        }
+        config.cancel_fee_percent = percent;
+    }
+    if let Some(max) = max_capabilities {
+        config.max_capabilities = max;
+    }
+    if let Some(paused) = query_paused {
+        config.query_paused = paused;
+    }
+    if let Some(fee) = registration_fee {
+        config.registration_fee = fee;
+    }
+    if let Some(mode) = rounding_mode {
+        config.rounding_mode = mode;
+    }
+    if let Some(max) = max_providers_per_service {
+        config.max_providers_per_service = if max == 0 { None } else { Some(max) };
+    }
+    if let Some(threshold) = capability_reputation_threshold {
+        config.capability_reputation_threshold = threshold;
+    }
+    if let Some(max) = low_reputation_max_capabilities {
+        config.low_reputation_max_capabilities = max;
+    }
+    if let Some(cooldown) = update_cooldown {
+        config.update_cooldown = cooldown;
+    }
+    if let Some(max) = max_total_active_jobs {
+        config.max_total_active_jobs = max;
+    }
+    if let Some(enforce) = enforce_unique_results {
+        config.enforce_unique_results = enforce;
+    }
+    if let Some(buffer) = result_deadline_buffer {
+        config.result_deadline_buffer = if buffer == 0 { None } else { Some(buffer) };
+    }
+    if let Some(interval) = min_heartbeat_interval {
+        config.min_heartbeat_interval = interval;
+    }
+    if let Some(percent) = cancel_community_fee_percent {
+        config.cancel_community_fee_percent = percent;
+    }
+    if let Some(max) = max_extensions {
+        config.max_extensions = max;
+    }
+    if let Some(max) = max_total_extension {
+        config.max_total_extension = max;
+    }
+    if let Some(required) = require_client_allowlist {
+        config.require_client_allowlist = required;
+    }
+    if let Some(reputation) = default_reputation {
+        if reputation > Decimal::from_ratio(100u128, 1u128) {
+            return Err(ContractError::InvalidReputation { reputation });
+        }
+        config.default_reputation = reputation;
+    }
+    if let Some(denoms) = accepted_denoms {
+        if denoms.is_empty() {
+            return Err(ContractError::NoAcceptedDenoms {});
+        }
+        config.accepted_denoms = denoms;
+    }
+    if let Some(max) = max_sweep_batch {
+        config.max_sweep_batch = max;
+    }
+    if let Some(percent) = requeue_fee_discount_percent {
+        if percent > 100 {
+            return Err(ContractError::InvalidDiscountPercent { percent });
+        }
+        config.requeue_fee_discount_percent = percent;
+    }
+    if let Some(refund) = refund_on_deactivation {
+        config.refund_on_deactivation = refund;
+    }
+    if let Some(after) = deregister_after {
+        config.deregister_after = after;
+    }
+    if let Some(period) = probation_period {
+        config.probation_period = period;
+    }
+    if let Some(max) = probation_max_payment {
+        config.probation_max_payment = max;
+    }
+    if let Some(jobs) = probation_min_jobs {
+        config.probation_min_jobs = jobs;
+    }
+    if let Some(rate) = auto_pause_failure_rate {
+        if rate > Decimal::one() {
+            return Err(ContractError::InvalidFailureRate { rate });
+        }
+        config.auto_pause_failure_rate = rate;
+    }
+    if let Some(jobs) = auto_pause_min_jobs {
+        config.auto_pause_min_jobs = jobs;
+    }
+    if let Some(window) = failure_window {
+        config.failure_window = window;
+    }
+    if let Some(weight) = reputation_rating_weight {
+        if weight > 100 {
+            return Err(ContractError::InvalidRatingWeight { weight });
+        }
+        config.reputation_rating_weight = weight;
+    }
+    // Both cancellation fees come out of the same payment
+    let cancel_total = config.cancel_fee_percent + config.cancel_community_fee_percent;
+    if cancel_total > 100 {
+        return Err(ContractError::InvalidFeePercent { percent: cancel_total });
+    }
     
     CONFIG.save(deps.storage, &config)?;
     
@@ -760,14 +2310,13 @@ pub fn execute_update_config(
 /// Admin only - useful in case of critical issues
 pub fn execute_pause_contract(
     deps: DepsMut,
-    _info: MessageInfo,
+    info: MessageInfo,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
     
-    // TODO: Add admin check
-    // if info.sender != config.admin {
-    //     return Err(ContractError::Unauthorized {});
-    // }
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
     
     config.paused = true;
     CONFIG.save(deps.storage, &config)?;
@@ -781,14 +2330,13 @@ pub fn execute_pause_contract(
 /// Admin only
 pub fn execute_unpause_contract(
     deps: DepsMut,
-    _info: MessageInfo,
+    info: MessageInfo,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
     
-    // TODO: Add admin check
-    // if info.sender != config.admin {
-    //     return Err(ContractError::Unauthorized {});
-    // }
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
     
     config.paused = false;
     CONFIG.save(deps.storage, &config)?;
@@ -798,37 +2346,1416 @@ pub fn execute_unpause_contract(
         .add_attribute("paused", "false"))
 }
 
-/// Calculate provider reputation based on success rate
-/// Returns a decimal percentage (0-100)
-fn calculate_reputation(provider: &Provider) -> Decimal {
-    let total = provider.total_completed + provider.total_failed;
-    
-    // Return 100% if no jobs completed yet
-    if total == 0 {
-        return Decimal::percent(100);
+/// Set or clear a job type specific timeout - admin only
+/// Job types without an override use default_job_timeout
+pub fn execute_set_job_type_timeout(
+    deps: DepsMut,
+    info: MessageInfo,
+    job_type: String,
+    timeout: Option<u64>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
     }
-    
-    // Calculate success rate as percentage
-    let success_rate = provider.total_completed as f64 / total as f64;
-    Decimal::from_ratio((success_rate * 100.0) as u128, 1u128)
-}
-#[entry_point]
-pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+
+    match timeout {
+        Some(t) => JOB_TYPE_TIMEOUTS.save(deps.storage, &job_type, &t)?,
+        None => JOB_TYPE_TIMEOUTS.remove(deps.storage, &job_type),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_job_type_timeout")
+        .add_attribute("job_type", job_type)
+        .add_attribute("timeout", timeout.map_or("default".to_string(), |t| t.to_string())))
+}
+
+/// Add or remove a client on the allow-list - admin only
+/// Only enforced while require_client_allowlist is set
+pub fn execute_set_client_allowed(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+    allowed: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let client = deps.api.addr_validate(&address)?;
+    if allowed {
+        CLIENT_ALLOWLIST.save(deps.storage, &client, &())?;
+    } else {
+        CLIENT_ALLOWLIST.remove(deps.storage, &client);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_client_allowed")
+        .add_attribute("client", client.to_string())
+        .add_attribute("allowed", allowed.to_string()))
+}
+
+/// Sweep rounding remainders and other untracked funds to the community pool - admin only
+/// Only the balance above what open jobs are still owed in each denom is sent
+pub fn execute_sweep_dust(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Until then escrow of jobs from older versions is missing from the ledger and would look like dust
+    if !LEDGERS_BACKFILLED.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(ContractError::LedgersNotBackfilled {});
+    }
+
+    let mut excess = vec![];
+    for coin in deps.querier.query_all_balances(&env.contract.address)? {
+        let owed = OBLIGATIONS_BY_DENOM.may_load(deps.storage, &coin.denom)?.unwrap_or_default();
+        let amount = coin.amount.saturating_sub(owed);
+        if !amount.is_zero() {
+            excess.push(Coin { denom: coin.denom, amount });
+        }
+    }
+
+    let swept = excess
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let mut response = Response::new();
+    if !excess.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: config.community_pool.to_string(),
+            amount: excess,
+        });
+    }
+
+    Ok(response
+        .add_attribute("action", "sweep_dust")
+        .add_attribute("swept", swept))
+}
+
+/// Waive a failed job's penalty - admin only
+/// The failure is dropped from the provider's lifetime and recent stats and its
+/// reputation recomputed. There is no stake to return
+pub fn execute_waive_penalty(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    job_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut job = JOBS
+        .load(deps.storage, job_id)
+        .map_err(|_| ContractError::JobNotFound {})?;
+    if job.status != JobStatus::Failed {
+        return Err(ContractError::InvalidJobState {});
+    }
+    if job.penalty_waived {
+        return Err(ContractError::PenaltyAlreadyWaived { job_id });
+    }
+
+    let mut provider = PROVIDERS
+        .may_load(deps.storage, &job.provider)?
+        .ok_or(ContractError::ProviderNotRegistered {
+            address: job.provider.to_string(),
+        })?;
+    provider.total_failed = provider.total_failed.saturating_sub(1);
+    provider.forget_outcome(job_id);
+    // With no history left the provider is back to its starting standing
+    provider.reputation = if provider.total_completed + provider.total_failed == 0 {
+        config.default_reputation
+    } else {
+        calculate_reputation(&provider, config.reputation_rating_weight)
+    };
+    PROVIDERS.save(deps.storage, &job.provider, &provider)?;
+
+    // Take the failure back out of the auto-pause window too, if it hasn't rolled out
+    if job.counted_toward_pause {
+        if let Some(failed_at) = job.completed_at {
+            forget_job_failure(deps.storage, &config, failed_at.seconds(), env.block.time.seconds())?;
+        }
+        job.counted_toward_pause = false;
+    }
+    job.penalty_waived = true;
+    JOBS.save(deps.storage, job_id, &job)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "waive_penalty")
+        .add_attribute("job_id", job_id.to_string())
+        .add_attribute("provider", job.provider.to_string())
+        .add_attribute("reputation", provider.reputation.to_string()))
+}
+
+/// Add funds to a job that is still pending - client only
+/// The extra payment is escrowed and split like the rest on completion
+pub fn execute_top_up_job(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    job_id: u64,
+) -> Result<Response, ContractError> {
+    let mut job = JOBS
+        .load(deps.storage, job_id)
+        .map_err(|_| ContractError::JobNotFound {})?;
+
+    if info.sender != job.client {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !matches!(job.status, JobStatus::Submitted | JobStatus::Processing) {
+        return Err(ContractError::InvalidJobState {});
+    }
+
+    let amount = job_payment(&info.funds, &job.denom)?;
+    if amount.is_zero() {
+        return Err(ContractError::NoPayment {});
+    }
+    job.payment_amount = job.payment_amount.checked_add(amount).map_err(StdError::from)?;
+
+    // The provider's caps apply to the job's total payment
+    let config = CONFIG.load(deps.storage)?;
+    let provider = PROVIDERS.load(deps.storage, &job.provider)?;
+    check_payment_caps(&config, &provider, job.payment_amount, env.block.time.seconds())?;
+    JOBS.save(deps.storage, job_id, &job)?;
+
+    add_obligation(deps.storage, &job.denom, amount)?;
+    let held = JOB_ESCROW.may_load(deps.storage, job_id)?.unwrap_or_default();
+    JOB_ESCROW.save(deps.storage, job_id, &(held + amount))?;
+
+    Ok(Response::new()
+        .add_attribute("action", "top_up_job")
+        .add_attribute("job_id", job_id.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("payment", job.payment_amount.to_string()))
+}
+
+/// Store the sender's notification preferences for off-chain use
+pub fn execute_set_client_prefs(
+    deps: DepsMut,
+    info: MessageInfo,
+    notify_url: Option<String>,
+) -> Result<Response, ContractError> {
+    match &notify_url {
+        Some(url) if url.len() > MAX_NOTIFY_URL_LENGTH => {
+            return Err(ContractError::NotifyUrlTooLong { max: MAX_NOTIFY_URL_LENGTH });
+        }
+        Some(_) => CLIENT_PREFS.save(deps.storage, &info.sender, &ClientPrefs { notify_url })?,
+        None => CLIENT_PREFS.remove(deps.storage, &info.sender),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_client_prefs")
+        .add_attribute("client", info.sender.to_string()))
+}
+
+/// Replace the volume discount table - admin only
+pub fn execute_set_fee_discount_tiers(
+    deps: DepsMut,
+    info: MessageInfo,
+    tiers: Vec<FeeDiscountTier>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(tier) = tiers.iter().find(|t| t.discount_percent > 100) {
+        return Err(ContractError::InvalidDiscountPercent { percent: tier.discount_percent });
+    }
+    FEE_DISCOUNT_TIERS.save(deps.storage, &tiers)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_fee_discount_tiers")
+        .add_attribute("tier_count", tiers.len().to_string()))
+}
+
+/// Best discount among the tiers a client with `completed_jobs` has reached
+fn fee_discount_percent(storage: &dyn Storage, completed_jobs: u64) -> StdResult<u64> {
+    Ok(FEE_DISCOUNT_TIERS
+        .may_load(storage)?
+        .unwrap_or_default()
+        .iter()
+        .filter(|t| completed_jobs >= t.min_completed_jobs)
+        .map(|t| t.discount_percent)
+        .max()
+        .unwrap_or(0))
+}
+
+/// Submit a job to the first provider in the list that can take it
+/// A provider qualifies if it is active, below capacity and offers the job type.
+/// The chosen provider gets an exclusive window to accept; afterwards any other
+/// listed provider may take the job over via AcceptJob
+pub fn execute_submit_job_with_fallback(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    providers: Vec<String>,
+    job_type: String,
+    parameters: String,
+    denom: String,
+) -> Result<Response, ContractError> {
+    let providers = providers
+        .iter()
+        .map(|p| deps.api.addr_validate(p))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut chosen = None;
+    for (index, addr) in providers.iter().enumerate() {
+        if let Some(provider) = PROVIDERS.may_load(deps.storage, addr)? {
+            if provider_can_accept(&provider, &job_type) {
+                chosen = Some((index, addr.clone()));
+                break;
+            }
+        }
+    }
+    let (index, provider) = chosen.ok_or(ContractError::NoQualifiedProvider {})?;
+
+    let job_id = NEXT_JOB_ID.load(deps.storage)?;
+    let res = execute_submit_job(
+        deps.branch(),
+        env.clone(),
+        info,
+        provider.to_string(),
+        job_type,
+        parameters,
+        None,
+        None,
+        denom,
+        None,
+    )?;
+
+    // Remember the preferred provider and who may take over later
+    let config = CONFIG.load(deps.storage)?;
+    let exclusive_until = env.block.time.seconds() + config.exclusive_window;
+    let mut job = JOBS.load(deps.storage, job_id)?;
+    job.preferred_provider = Some(provider.clone());
+    job.exclusive_until = Some(exclusive_until);
+    job.fallback_providers = providers.into_iter().filter(|p| *p != provider).collect();
+    JOBS.save(deps.storage, job_id, &job)?;
+
+    Ok(res
+        .add_attribute("fallback_index", index.to_string())
+        .add_attribute("exclusive_until", exclusive_until.to_string()))
+}
+
+/// Submit a job to whichever provider the strategy selects
+pub fn execute_auto_submit_job(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    job_type: String,
+    parameters: String,
+    strategy: SelectionStrategy,
+    denom: String,
+) -> Result<Response, ContractError> {
+    let provider = select_provider(deps.storage, &job_type, &strategy)?
+        .ok_or(ContractError::NoQualifiedProvider {})?;
+
+    let res = execute_submit_job(
+        deps,
+        env,
+        info,
+        provider.address.to_string(),
+        job_type,
+        parameters,
+        None,
+        None,
+        denom,
+        None,
+    )?;
+
+    Ok(res.add_attribute("auto_selected", "true"))
+}
+
+/// Resubmit a failed job to the same provider - client only
+pub fn execute_requeue_job(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    job_id: u64,
+) -> Result<Response, ContractError> {
+    let mut original = JOBS
+        .load(deps.storage, job_id)
+        .map_err(|_| ContractError::JobNotFound {})?;
+    if original.client != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if original.status != JobStatus::Failed {
+        return Err(ContractError::InvalidJobState {});
+    }
+    // One discounted retry per failure
+    if original.requeued_as.is_some() {
+        return Err(ContractError::AlreadyRequeued { job_id });
+    }
+
+    let new_job_id = NEXT_JOB_ID.load(deps.storage)?;
+    let res = execute_submit_job(
+        deps.branch(),
+        env,
+        info,
+        original.provider.to_string(),
+        original.job_type.clone(),
+        original.parameters.clone(),
+        original.referrer.as_ref().map(|r| r.to_string()),
+        None,
+        original.denom.clone(),
+        None,
+    )?;
+
+    let mut job = JOBS.load(deps.storage, new_job_id)?;
+    job.original_job_id = Some(job_id);
+    JOBS.save(deps.storage, new_job_id, &job)?;
+    original.requeued_as = Some(new_job_id);
+    JOBS.save(deps.storage, job_id, &original)?;
+
+    Ok(res.add_attribute("original_job_id", job_id.to_string()))
+}
+
+/// Accept a job - moves it from Submitted to Processing
+/// The assigned provider can always accept. A listed fallback provider can take the
+/// job over once the preferred provider's exclusive window has lapsed
+pub fn execute_accept_job(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    job_id: u64,
+) -> Result<Response, ContractError> {
+    let mut job = JOBS
+        .load(deps.storage, job_id)
+        .map_err(|_| ContractError::JobNotFound {})?;
+
+    if info.sender != job.provider {
+        if !job.fallback_providers.contains(&info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+        if let Some(until) = job.exclusive_until {
+            if env.block.time.seconds() < until {
+                return Err(ContractError::ExclusiveWindowActive { until });
+            }
+        }
+
+        let mut new_provider = PROVIDERS
+            .load(deps.storage, &info.sender)
+            .map_err(|_| ContractError::ProviderNotFound {})?;
+        if !provider_can_accept(&new_provider, &job.job_type) {
+            return Err(ContractError::NoQualifiedProvider {});
+        }
+        // The fallback provider must qualify for the job exactly as if it had been submitted to it
+        let config = CONFIG.load(deps.storage)?;
+        check_job_eligibility(
+            deps.storage,
+            &config,
+            &new_provider,
+            &job.client,
+            &job.job_type,
+            &job.parameters,
+        )?;
+        check_payment_caps(&config, &new_provider, job.payment_amount, env.block.time.seconds())?;
+        transition_job(deps.storage, &mut job, JobStatus::Processing, env.block.time)?;
+
+        // Hand the job over to the fallback provider
+        let mut old_provider = PROVIDERS.load(deps.storage, &job.provider)?;
+        old_provider.active_jobs = old_provider.active_jobs.saturating_sub(1);
+        old_provider.total_jobs = old_provider.total_jobs.saturating_sub(1);
+        PROVIDERS.save(deps.storage, &job.provider, &old_provider)?;
+        JOBS_BY_PROVIDER.remove(deps.storage, (&job.provider, job_id));
+
+        new_provider.active_jobs += 1;
+        new_provider.total_jobs += 1;
+        PROVIDERS.save(deps.storage, &info.sender, &new_provider)?;
+        JOBS_BY_PROVIDER.save(deps.storage, (&info.sender, job_id), &())?;
+        job.provider = info.sender.clone();
+    } else {
+        transition_job(deps.storage, &mut job, JobStatus::Processing, env.block.time)?;
+    }
+
+    // Delivery clock starts at acceptance, against the accepting provider's SLA
+    let config = CONFIG.load(deps.storage)?;
+    let provider = PROVIDERS.load(deps.storage, &job.provider)?;
+    job.result_deadline = result_deadline(&provider, &job.job_type, &config, env.block.time.seconds());
+
+    JOBS.save(deps.storage, job_id, &job)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_job")
+        .add_attribute("job_id", job_id.to_string())
+        .add_attribute("provider", info.sender.to_string()))
+}
+
+/// Acknowledge a result - the client confirms it received and accepts the output
+/// Payment is released to the provider on completion already, so this only
+/// moves the job to its final Acknowledged state
+pub fn execute_acknowledge_result(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    job_id: u64,
+) -> Result<Response, ContractError> {
+    let mut job = JOBS
+        .load(deps.storage, job_id)
+        .map_err(|_| ContractError::JobNotFound {})?;
+
+    // Only the client can acknowledge
+    if info.sender != job.client {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    transition_job(deps.storage, &mut job, JobStatus::Acknowledged, env.block.time)?;
+    JOBS.save(deps.storage, job_id, &job)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "acknowledge_result")
+        .add_attribute("job_id", job_id.to_string())
+        .add_attribute("client", info.sender.to_string()))
+}
+
+/// Request more time for a job - assigned provider only, before the deadline passes
+/// Bounded by the configured number of extensions and total extra seconds per job
+pub fn execute_request_extension(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    job_id: u64,
+    additional_seconds: u64,
+) -> Result<Response, ContractError> {
+    let mut job = JOBS
+        .load(deps.storage, job_id)
+        .map_err(|_| ContractError::JobNotFound {})?;
+
+    if info.sender != job.provider {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Blocked jobs have no deadline yet and timed-out jobs can't be rescued
+    if !matches!(job.status, JobStatus::Submitted | JobStatus::Processing)
+        || env.block.time.seconds() > job.deadline
+    {
+        return Err(ContractError::InvalidJobState {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let total_extension = job.total_extension.saturating_add(additional_seconds);
+    if job.extension_count >= config.max_extensions || total_extension > config.max_total_extension {
+        return Err(ContractError::ExtensionLimitReached {
+            max_extensions: config.max_extensions,
+            max_total: config.max_total_extension,
+        });
+    }
+
+    JOBS_BY_DEADLINE.remove(deps.storage, (job.deadline, job_id));
+    job.deadline += additional_seconds;
+    // An accepted job's result window moves with it, otherwise the extension buys nothing
+    job.result_deadline = job.result_deadline.map(|deadline| deadline.saturating_add(additional_seconds));
+    job.extension_count += 1;
+    job.total_extension = total_extension;
+    JOBS_BY_DEADLINE.save(deps.storage, (job.deadline, job_id), &())?;
+    JOBS.save(deps.storage, job_id, &job)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "request_extension")
+        .add_attribute("job_id", job_id.to_string())
+        .add_attribute("deadline", job.deadline.to_string())
+        .add_attribute("extension_count", job.extension_count.to_string()))
+}
+
+/// Report cumulative progress on an accepted job - assigned provider only
+/// Nothing is paid until the client approves the reported progress
+pub fn execute_report_progress(
+    deps: DepsMut,
+    info: MessageInfo,
+    job_id: u64,
+    percent: u64,
+) -> Result<Response, ContractError> {
+    let mut job = JOBS
+        .load(deps.storage, job_id)
+        .map_err(|_| ContractError::JobNotFound {})?;
+
+    if info.sender != job.provider {
+        return Err(ContractError::Unauthorized {});
+    }
+    if job.status != JobStatus::Processing {
+        return Err(ContractError::InvalidJobState {});
+    }
+    if percent <= job.reported_percent || percent > 100 {
+        return Err(ContractError::InvalidProgress { percent });
+    }
+
+    job.reported_percent = percent;
+    JOBS.save(deps.storage, job_id, &job)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "report_progress")
+        .add_attribute("job_id", job_id.to_string())
+        .add_attribute("percent", percent.to_string()))
+}
+
+/// Approve the provider's reported progress - client only
+/// Releases that share of the payment, less anything released before, split
+/// with the community like a regular payout
+pub fn execute_approve_progress(
+    deps: DepsMut,
+    info: MessageInfo,
+    job_id: u64,
+) -> Result<Response, ContractError> {
+    let mut job = JOBS
+        .load(deps.storage, job_id)
+        .map_err(|_| ContractError::JobNotFound {})?;
+
+    if info.sender != job.client {
+        return Err(ContractError::Unauthorized {});
+    }
+    if job.status != JobStatus::Processing {
+        return Err(ContractError::InvalidJobState {});
+    }
+
+    // Never more than the escrow, however the percentages round
+    let approved = job.payment_amount.multiply_ratio(job.reported_percent, 100u128);
+    let release = approved.saturating_sub(job.released_amount).min(job.remaining_escrow());
+    if release.is_zero() {
+        return Err(ContractError::InvalidProgress { percent: job.reported_percent });
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let provider = PROVIDERS.load(deps.storage, &job.provider)?;
+    let community_fee = community_fee(release, config.community_fee_percent, &config.rounding_mode);
+    let provider_fee = release.checked_sub(community_fee)
+    .map_err(|e| ContractError::Std(cosmwasm_std::StdError::generic_err(e.to_string())))?;
+
+    job.released_amount += release;
+    job.provider_fee += provider_fee;
+    JOBS.save(deps.storage, job_id, &job)?;
+
+    release_obligation(deps.storage, &job.denom, release)?;
+    debit_escrow(deps.storage, job_id, release)?;
+
+    let mut messages = vec![];
+    if !community_fee.is_zero() {
+        messages.push(BankMsg::Send {
+            to_address: config.community_pool.to_string(),
+            amount: vec![Coin {
+                denom: job.denom.clone(),
+                amount: community_fee,
+            }],
+        });
+    }
+    if !provider_fee.is_zero() {
+        let payout_address = provider.payout_address.unwrap_or(job.provider.clone());
+        messages.push(BankMsg::Send {
+            to_address: payout_address.to_string(),
+            amount: vec![Coin {
+                denom: job.denom.clone(),
+                amount: provider_fee,
+            }],
+        });
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "approve_progress")
+        .add_attribute("job_id", job_id.to_string())
+        .add_attribute("released_amount", release.to_string())
+        .add_attribute("total_released", job.released_amount.to_string()))
+}
+
+/// Quantity fields recognised in a job's parameters JSON
+#[derive(Deserialize)]
+struct QuantityParameters {
+    digits: Option<u64>,
+    quantity: Option<u64>,
+}
+
+/// Parse the billable quantity out of a job's parameters
+/// pi_calculation jobs are billed per digit, everything else reads `quantity`
+pub fn parse_quantity(parameters: &str, job_type: &str) -> Result<u64, ContractError> {
+    quantity_field(parameters, job_type)?.ok_or_else(|| ContractError::InvalidJobParameters {
+        reason: format!("missing field {} for job type {}", quantity_field_name(job_type), job_type),
+    })
+}
+
+/// Size of a job as checked against max_complexity. Only required when the provider
+/// prices the job type per unit; an unpriced job counts as one unit unless it says otherwise
+fn job_quantity(provider: &Provider, job_type: &str, parameters: &str) -> Result<u64, ContractError> {
+    if provider.pricing.contains_key(job_type) {
+        parse_quantity(parameters, job_type)
+    } else {
+        Ok(quantity_field(parameters, job_type)?.unwrap_or(1))
+    }
+}
+
+fn quantity_field_name(job_type: &str) -> &'static str {
+    match job_type {
+        "pi_calculation" => "digits",
+        _ => "quantity",
+    }
+}
+
+/// The job type's quantity field, if present; malformed parameters and zero are errors
+fn quantity_field(parameters: &str, job_type: &str) -> Result<Option<u64>, ContractError> {
+    let parsed: QuantityParameters = cosmwasm_std::from_json(parameters.as_bytes())
+        .map_err(|e| ContractError::InvalidJobParameters {
+            reason: e.to_string(),
+        })?;
+
+    let value = match job_type {
+        "pi_calculation" => parsed.digits,
+        _ => parsed.quantity,
+    };
+    if value == Some(0) {
+        return Err(ContractError::InvalidJobParameters {
+            reason: format!("{} must be greater than zero", quantity_field_name(job_type)),
+        });
+    }
+    Ok(value)
+}
+
+/// Provider's current price for a job - base price times the billable quantity
+/// None if the job type is unpriced; malformed parameters are an error
+fn job_price(provider: &Provider, job_type: &str, parameters: &str) -> Result<Option<Uint128>, ContractError> {
+    let Some(tier) = provider.pricing.get(job_type) else {
+        return Ok(None);
+    };
+    let quantity = parse_quantity(parameters, job_type)?;
+    let price = Uint128::from(quantity)
+        .checked_mul_ceil(tier.base_price)
+        .map_err(|_| ContractError::PriceOverflow {})?;
+    Ok(Some(price))
+}
+
+/// When results are due for a job accepted at `accepted_at`, if result deadlines are enabled
+/// and the provider advertises the job type
+fn result_deadline(provider: &Provider, job_type: &str, config: &Config, accepted_at: u64) -> Option<u64> {
+    let buffer = config.result_deadline_buffer?;
+    provider
+        .capabilities
+        .iter()
+        .find(|c| c.service_type == job_type)
+        .map(|c| accepted_at + c.avg_completion_time + buffer)
+}
+
+/// Required keys absent from the parameters JSON object
+/// Anything that isn't a JSON object is missing all of them
+fn missing_parameters(parameters: &str, required: &[String]) -> Vec<String> {
+    if required.is_empty() {
+        return vec![];
+    }
+    let present: HashMap<String, IgnoredAny> =
+        cosmwasm_std::from_json(parameters.as_bytes()).unwrap_or_default();
+    required
+        .iter()
+        .filter(|key| !present.contains_key(*key))
+        .cloned()
+        .collect()
+}
+
+/// Total umedas attached to a message
+/// Summed so a malformed tx listing the denom twice is still accounted for in full
+fn umedas_sent(funds: &[Coin]) -> StdResult<Uint128> {
+    funds
+        .iter()
+        .filter(|c| c.denom == "umedas")
+        .try_fold(Uint128::zero(), |total, c| Ok(total.checked_add(c.amount)?))
+}
+
+/// Add `amount` to the running total for `denom`, keeping one coin per denom
+fn add_to_totals(totals: &mut Vec<Coin>, denom: &str, amount: Uint128) {
+    match totals.iter_mut().find(|coin| coin.denom == denom) {
+        Some(coin) => coin.amount += amount,
+        None => totals.push(Coin { denom: denom.to_string(), amount }),
+    }
+}
+
+/// Payment attached for a job, which must be in the job's denom only
+fn job_payment(funds: &[Coin], denom: &str) -> Result<Uint128, ContractError> {
+    if let Some(other) = funds.iter().find(|c| c.denom != denom) {
+        return Err(ContractError::UnexpectedDenom { denom: other.denom.clone() });
+    }
+    Ok(funds
+        .iter()
+        .try_fold(Uint128::zero(), |total, c| total.checked_add(c.amount))
+        .map_err(StdError::from)?)
+}
+
+/// Checks a provider and client must pass for a job to be assigned, on submission
+/// and again when a fallback provider takes a job over
+fn check_job_eligibility(
+    storage: &dyn Storage,
+    config: &Config,
+    provider: &Provider,
+    client: &Addr,
+    job_type: &str,
+    parameters: &str,
+) -> Result<(), ContractError> {
+    if !provider.active {
+        return Err(ContractError::ProviderNotActive {});
+    }
+
+    // Checked against the provider's current capability, so a lowered
+    // max_complexity applies to new jobs while in-flight ones are unaffected
+    let capability = provider
+        .capabilities
+        .iter()
+        .find(|c| c.service_type == job_type)
+        .ok_or(ContractError::ServiceNotOffered {
+            job_type: job_type.to_string(),
+        })?;
+    if !capability.enabled {
+        return Err(ContractError::ServiceDisabled {
+            service_type: job_type.to_string(),
+        });
+    }
+    let missing = missing_parameters(parameters, &capability.required_parameters);
+    if !missing.is_empty() {
+        return Err(ContractError::MissingRequiredParameters {
+            missing: missing.join(", "),
+        });
+    }
+    let complexity = job_quantity(provider, job_type, parameters)?;
+    if complexity > capability.max_complexity {
+        return Err(ContractError::ComplexityTooHigh {
+            complexity,
+            max: capability.max_complexity,
+        });
+    }
+
+    // Private deployments only serve approved clients
+    if config.require_client_allowlist && !CLIENT_ALLOWLIST.has(storage, client) {
+        return Err(ContractError::ClientNotAllowed {});
+    }
+    Ok(())
+}
+
+/// The provider's own payment cap and, while on probation, the contract's
+fn check_payment_caps(
+    config: &Config,
+    provider: &Provider,
+    payment: Uint128,
+    now: u64,
+) -> Result<(), ContractError> {
+    if let Some(max) = provider.max_payment {
+        if payment > max {
+            return Err(ContractError::PaymentTooLarge { max });
+        }
+    }
+    // New providers prove themselves on small jobs first
+    if on_probation(provider, config, now) && payment > config.probation_max_payment {
+        return Err(ContractError::PaymentTooLarge { max: config.probation_max_payment });
+    }
+    Ok(())
+}
+
+/// Whether a provider is still within its probation - the period since registration
+/// has not elapsed, or it has yet to complete the required number of jobs
+fn on_probation(provider: &Provider, config: &Config, current_time: u64) -> bool {
+    if config.probation_period == 0 {
+        return false;
+    }
+    let elapsed = current_time.saturating_sub(provider.registered_at.seconds());
+    elapsed < config.probation_period || provider.total_completed < config.probation_min_jobs
+}
+
+/// Slices of the failure window still inside it at `now`, and their totals
+fn rolling_failure_window(
+    storage: &dyn Storage,
+    config: &Config,
+    now: u64,
+) -> StdResult<(Vec<FailureWindow>, FailureWindow)> {
+    let mut slices = FAILURE_WINDOW.may_load(storage)?.unwrap_or_default();
+    slices.retain(|slice| slice.started_at.saturating_add(config.failure_window) > now);
+
+    let mut total = FailureWindow::default();
+    for slice in &slices {
+        total.failures = total.failures.saturating_add(slice.failures);
+        total.completions = total.completions.saturating_add(slice.completions);
+    }
+    Ok((slices, total))
+}
+
+/// Remove one failure recorded at `failed_at` from the window, if its slice is still in it
+fn forget_job_failure(storage: &mut dyn Storage, config: &Config, failed_at: u64, now: u64) -> StdResult<()> {
+    let (mut slices, _) = rolling_failure_window(storage, config, now)?;
+    if let Some(slice) = slices.iter_mut().rev().find(|slice| slice.started_at <= failed_at) {
+        slice.failures = slice.failures.saturating_sub(1);
+    }
+    FAILURE_WINDOW.save(storage, &slices)
+}
+
+/// Count a job outcome toward the rolling failure rate and pause the contract once
+/// the rate exceeds the configured maximum over enough jobs. Returns the alert when it trips
+/// Jobs a provider submitted to itself are ignored, so nobody can pause the market cheaply
+fn record_job_outcome(
+    storage: &mut dyn Storage,
+    job: &Job,
+    now: u64,
+    failed: bool,
+) -> StdResult<Option<Event>> {
+    if job.is_self_dealt() {
+        return Ok(None);
+    }
+    let mut config = CONFIG.load(storage)?;
+    let (mut slices, mut window) = rolling_failure_window(storage, &config, now)?;
+
+    let slice_length = (config.failure_window / FAILURE_WINDOW_SLICES).max(1);
+    let slice_start = now - now % slice_length;
+    if slices.last().is_none_or(|slice| slice.started_at != slice_start) {
+        slices.push(FailureWindow {
+            started_at: slice_start,
+            ..FailureWindow::default()
+        });
+    }
+    let slice = slices.last_mut().expect("current slice was just ensured");
+    if failed {
+        slice.failures = slice.failures.saturating_add(1);
+        window.failures = window.failures.saturating_add(1);
+    } else {
+        slice.completions = slice.completions.saturating_add(1);
+        window.completions = window.completions.saturating_add(1);
+    }
+    FAILURE_WINDOW.save(storage, &slices)?;
+
+    let sample = window.failures as u64 + window.completions as u64;
+    if config.paused
+        || config.auto_pause_failure_rate.is_zero()
+        || sample < config.auto_pause_min_jobs as u64
+        || window.failure_rate() <= config.auto_pause_failure_rate
+    {
+        return Ok(None);
+    }
+    config.paused = true;
+    CONFIG.save(storage, &config)?;
+
+    Ok(Some(
+        Event::new("auto_pause")
+            .add_attribute("failures", window.failures.to_string())
+            .add_attribute("completions", window.completions.to_string())
+            .add_attribute("failure_rate", window.failure_rate().to_string())
+            .add_attribute("window_seconds", config.failure_window.to_string()),
+    ))
+}
+
+/// Whether an active provider has gone longer than the heartbeat timeout without one
+fn heartbeat_expired(provider: &Provider, config: &Config, current_time: u64) -> bool {
+    current_time.saturating_sub(provider.last_heartbeat) > config.heartbeat_timeout
+}
+
+/// Timeout for a job type, falling back to the default
+fn job_timeout(storage: &dyn Storage, job_type: &str) -> StdResult<u64> {
+    let config = CONFIG.load(storage)?;
+    Ok(JOB_TYPE_TIMEOUTS
+        .may_load(storage, job_type)?
+        .unwrap_or(config.default_job_timeout))
+}
+
+/// Settle the jobs waiting on a dependency: promote them once it completed,
+/// otherwise cancel and refund them along with anything waiting on them in turn
+fn resolve_dependents(
+    storage: &mut dyn Storage,
+    env: &Env,
+    dependency_id: u64,
+    completed: bool,
+) -> Result<Vec<BankMsg>, ContractError> {
+    let config = CONFIG.load(storage)?;
+    let mut refunds = vec![];
+    let mut pending = vec![dependency_id];
+
+    while let Some(dependency_id) = pending.pop() {
+        let dependents: Vec<u64> = JOBS_BY_DEPENDENCY
+            .prefix(dependency_id)
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for job_id in dependents {
+            JOBS_BY_DEPENDENCY.remove(storage, (dependency_id, job_id));
+            let mut job = JOBS.load(storage, job_id)?;
+            if job.status != JobStatus::Blocked {
+                continue;
+            }
+
+            let cancel_reason = if completed {
+                // Promotion is a submission made now, so it passes the same checks
+                let provider = PROVIDERS.load(storage, &job.provider)?;
+                match check_job_eligibility(storage, &config, &provider, &job.client, &job.job_type, &job.parameters) {
+                    Ok(()) => {
+                        // Eligible now - the deadline starts counting from promotion
+                        transition_job(storage, &mut job, JobStatus::Submitted, env.block.time)?;
+                        if provider.auto_accept {
+                            transition_job(storage, &mut job, JobStatus::Processing, env.block.time)?;
+                            job.result_deadline =
+                                result_deadline(&provider, &job.job_type, &config, env.block.time.seconds());
+                        }
+                        job.deadline = env.block.time.seconds() + job_timeout(storage, &job.job_type)?;
+                        JOBS_BY_DEADLINE.save(storage, (job.deadline, job_id), &())?;
+                        None
+                    }
+                    Err(err) => Some(format!("Job no longer eligible once dependency {} completed: {}", dependency_id, err)),
+                }
+            } else {
+                Some(format!("Dependency job {} did not complete", dependency_id))
+            };
+
+            if let Some(reason) = cancel_reason {
+                transition_job(storage, &mut job, JobStatus::Cancelled, env.block.time)?;
+                job.cancel_reason = Some(reason);
+                job.completed_at = Some(env.block.time);
+
+                let mut provider = PROVIDERS.load(storage, &job.provider)?;
+                provider.active_jobs = provider.active_jobs.saturating_sub(1);
+                PROVIDERS.save(storage, &job.provider, &provider)?;
+
+                record_refund(storage, env, &job, job.payment_amount, RefundReason::Cancel)?;
+                refunds.push(BankMsg::Send {
+                    to_address: job.client.to_string(),
+                    amount: vec![Coin {
+                        denom: job.denom.clone(),
+                        amount: job.payment_amount,
+                    }],
+                });
+                pending.push(job_id);
+            }
+            JOBS.save(storage, job_id, &job)?;
+        }
+    }
+
+    Ok(refunds)
+}
+
+/// Rate a finished job from 1 to 5 stars - client only, once per job
+pub fn execute_rate_job(
+    deps: DepsMut,
+    info: MessageInfo,
+    job_id: u64,
+    rating: u8,
+) -> Result<Response, ContractError> {
+    let mut job = JOBS
+        .load(deps.storage, job_id)
+        .map_err(|_| ContractError::JobNotFound {})?;
+
+    if info.sender != job.client {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !matches!(job.status, JobStatus::Completed | JobStatus::Acknowledged) {
+        return Err(ContractError::InvalidJobState {});
+    }
+    if job.rating.is_some() {
+        return Err(ContractError::AlreadyRated { job_id });
+    }
+
+    let mut provider = PROVIDERS.load(deps.storage, &job.provider)?;
+    match rating {
+        1 => provider.rating_1 += 1,
+        2 => provider.rating_2 += 1,
+        3 => provider.rating_3 += 1,
+        4 => provider.rating_4 += 1,
+        5 => provider.rating_5 += 1,
+        _ => return Err(ContractError::InvalidRating { rating }),
+    }
+    // Ratings only move reputation when the operator gives them weight
+    let rating_weight = CONFIG.load(deps.storage)?.reputation_rating_weight;
+    if rating_weight > 0 {
+        provider.reputation = calculate_reputation(&provider, rating_weight);
+    }
+    PROVIDERS.save(deps.storage, &job.provider, &provider)?;
+
+    job.rating = Some(rating);
+    JOBS.save(deps.storage, job_id, &job)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "rate_job")
+        .add_attribute("job_id", job_id.to_string())
+        .add_attribute("provider", job.provider.to_string())
+        .add_attribute("rating", rating.to_string()))
+}
+
+/// Whether a provider can take a new job of the given type right now
+fn provider_can_accept(provider: &Provider, job_type: &str) -> bool {
+    provider.active
+        && provider.active_jobs < provider.capacity
+        && provider
+            .capabilities
+            .iter()
+            .any(|c| c.service_type == job_type && c.enabled)
+}
+
+/// Pick a provider able to take the job type right now
+/// Shared by AutoSubmitJob and its preview query; ties go to the lowest address
+fn select_provider(
+    storage: &dyn Storage,
+    job_type: &str,
+    strategy: &SelectionStrategy,
+) -> StdResult<Option<Provider>> {
+    let mut best: Option<Provider> = None;
+    for item in PROVIDERS
+        .range(storage, None, None, Order::Ascending)
+        .take(MAX_PROVIDER_SCAN)
+    {
+        let (_, provider) = item?;
+        if !provider_can_accept(&provider, job_type) {
+            continue;
+        }
+        let better = match &best {
+            None => true,
+            Some(current) => match strategy {
+                SelectionStrategy::HighestReputation => provider.reputation > current.reputation,
+                // Compare active_jobs / capacity without dividing
+                SelectionStrategy::LowestUtilization => {
+                    (provider.active_jobs as u64) * (current.capacity as u64)
+                        < (current.active_jobs as u64) * (provider.capacity as u64)
+                }
+            },
+        };
+        if better {
+            best = Some(provider);
+        }
+    }
+    Ok(best)
+}
+
+fn provider_response(provider: Provider) -> ProviderResponse {
+    let measured = provider.on_time_count + provider.late_count;
+    let sla_compliance = if measured == 0 {
+        Decimal::from_ratio(100u128, 1u128)
+    } else {
+        Decimal::from_ratio(provider.on_time_count as u128 * 100, measured as u128)
+    };
+
+    ProviderResponse {
+        address: provider.address.to_string(),
+        name: provider.name,
+        capabilities: provider.capabilities,
+        pricing: provider.pricing,
+        endpoint: provider.endpoint,
+        capacity: provider.capacity,
+        active_jobs: provider.active_jobs,
+        total_completed: provider.total_completed,
+        total_failed: provider.total_failed,
+        last_heartbeat: provider.last_heartbeat,
+        reputation: provider.reputation,
+        active: provider.active,
+        registered_at: provider.registered_at,
+        payout_address: provider.payout_address.map(|a| a.to_string()),
+        auto_accept: provider.auto_accept,
+        refund_guarantee: provider.refund_guarantee,
+        max_payment: provider.max_payment,
+        on_time_count: provider.on_time_count,
+        late_count: provider.late_count,
+        completion_nonce: provider.completion_nonce,
+        total_job_count: provider.total_jobs,
+        sla_compliance,
+    }
+}
+
+/// Escrow now owed in `denom`; umedas also feeds the solvency total
+fn add_obligation(storage: &mut dyn Storage, denom: &str, amount: Uint128) -> StdResult<()> {
+    let owed = OBLIGATIONS_BY_DENOM.may_load(storage, denom)?.unwrap_or_default();
+    OBLIGATIONS_BY_DENOM.save(storage, denom, &(owed + amount))?;
+    if denom == "umedas" {
+        let total = OUTSTANDING_OBLIGATIONS.may_load(storage)?.unwrap_or_default();
+        OUTSTANDING_OBLIGATIONS.save(storage, &(total + amount))?;
+    }
+    Ok(())
+}
+
+/// Recompute what open jobs are owed in each denom from the jobs themselves
+/// Used on upgrade, since older versions kept no per-denom ledger
+fn rebuild_obligations(storage: &mut dyn Storage) -> StdResult<()> {
+    let stale = OBLIGATIONS_BY_DENOM
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for denom in stale {
+        OBLIGATIONS_BY_DENOM.remove(storage, &denom);
+    }
+    OUTSTANDING_OBLIGATIONS.save(storage, &Uint128::zero())?;
+
+    let open_jobs = JOBS
+        .range(storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((_, job)) if job.status.is_terminal() => None,
+            Ok((_, job)) => Some(Ok((job.denom.clone(), job.remaining_escrow()))),
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    for (denom, amount) in open_jobs {
+        add_obligation(storage, &denom, amount)?;
+    }
+    Ok(())
+}
+
+/// Escrow in `denom` no longer owed, because it was paid out or refunded
+fn release_obligation(storage: &mut dyn Storage, denom: &str, amount: Uint128) -> StdResult<()> {
+    let owed = OBLIGATIONS_BY_DENOM.may_load(storage, denom)?.unwrap_or_default();
+    OBLIGATIONS_BY_DENOM.save(storage, denom, &owed.saturating_sub(amount))?;
+    if denom == "umedas" {
+        let total = OUTSTANDING_OBLIGATIONS.may_load(storage)?.unwrap_or_default();
+        OUTSTANDING_OBLIGATIONS.save(storage, &total.saturating_sub(amount))?;
+    }
+    Ok(())
+}
+
+/// Take `amount` out of a job's own escrow, failing rather than drawing on other jobs' funds
+/// Jobs from before the escrow ledger have no entry and are left to settle as before
+fn debit_escrow(storage: &mut dyn Storage, job_id: u64, amount: Uint128) -> StdResult<()> {
+    let Some(held) = JOB_ESCROW.may_load(storage, job_id)? else {
+        return Ok(());
+    };
+    let left = held.checked_sub(amount)?;
+    if left.is_zero() {
+        JOB_ESCROW.remove(storage, job_id);
+    } else {
+        JOB_ESCROW.save(storage, job_id, &left)?;
+    }
+    Ok(())
+}
+
+/// Move a job to a new status, rejecting transitions the state machine doesn't allow
+/// Releases the job's payment and active job slot once it is terminal
+fn transition_job(
+    storage: &mut dyn Storage,
+    job: &mut Job,
+    to: JobStatus,
+    at: Timestamp,
+) -> Result<(), ContractError> {
+    if !can_transition(&job.status, &to) {
+        return Err(ContractError::IllegalTransition {
+            from: job.status.to_string(),
+            to: to.to_string(),
+        });
+    }
+
+    if to.is_terminal() && !job.status.is_terminal() {
+        release_obligation(storage, &job.denom, job.remaining_escrow())?;
+        // Whatever is left is paid out or refunded by the caller
+        debit_escrow(storage, job.id, job.remaining_escrow())?;
+        JOBS_BY_DEADLINE.remove(storage, (job.deadline, job.id));
+        let active = ACTIVE_JOB_COUNT.may_load(storage)?.unwrap_or_default();
+        ACTIVE_JOB_COUNT.save(storage, &active.saturating_sub(1))?;
+    }
+
+    record_status_change(storage, Some(&job.status), &to)?;
+    job.status = to;
+    push_history(job, at);
+    Ok(())
+}
+
+/// Record the job's current status in its history
+/// The submission entry is always kept; the oldest later entries make room past the cap
+fn push_history(job: &mut Job, at: Timestamp) {
+    if job.history.len() >= MAX_JOB_HISTORY {
+        job.history.remove(1);
+    }
+    job.history.push(StatusChange {
+        status: job.status.clone(),
+        at,
+    });
+}
+
+/// Append a refund to the ledger
+fn record_refund(
+    storage: &mut dyn Storage,
+    env: &Env,
+    job: &Job,
+    amount: Uint128,
+    reason: RefundReason,
+) -> StdResult<()> {
+    let id = NEXT_REFUND_ID.may_load(storage)?.unwrap_or(1);
+    NEXT_REFUND_ID.save(storage, &(id + 1))?;
+    REFUNDS.save(
+        storage,
+        id,
+        &RefundRecord {
+            job_id: job.id,
+            client: job.client.clone(),
+            amount,
+            reason,
+            timestamp: env.block.time,
+        },
+    )?;
+    REFUNDS_BY_CLIENT.save(storage, (&job.client, id), &())
+}
+
+fn record_status_change(
+    storage: &mut dyn Storage,
+    from: Option<&JobStatus>,
+    to: &JobStatus,
+) -> StdResult<()> {
+    let mut counts = STATUS_COUNTS.may_load(storage)?.unwrap_or_default();
+    counts.record(from, to);
+    STATUS_COUNTS.save(storage, &counts)
+}
+
+/// Calculate provider reputation from success rate, blended with client ratings
+/// by `rating_weight` percent once the provider has any ratings
+/// Returns a decimal percentage (0-100)
+fn calculate_reputation(provider: &Provider, rating_weight: u64) -> Decimal {
+    let success = success_reputation(provider);
+    match rating_score(provider) {
+        Some(rating) if rating_weight > 0 => {
+            success * Decimal::percent(100 - rating_weight) + rating * Decimal::percent(rating_weight)
+        }
+        _ => success,
+    }
+}
+
+/// Average client rating on the 0-100 scale, 1 star being 0 and 5 stars 100
+fn rating_score(provider: &Provider) -> Option<Decimal> {
+    let counts = [
+        provider.rating_1,
+        provider.rating_2,
+        provider.rating_3,
+        provider.rating_4,
+        provider.rating_5,
+    ];
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let points: u128 = counts
+        .iter()
+        .zip(0u128..)
+        .map(|(count, step)| *count as u128 * step * 25)
+        .sum();
+    Some(Decimal::from_ratio(points, total))
+}
+
+/// Reputation from job outcomes alone
+fn success_reputation(provider: &Provider) -> Decimal {
+    let total = provider.total_completed + provider.total_failed;
+    
+    // Return 100% if no jobs completed yet
+    if total == 0 {
+        return Decimal::percent(100);
+    }
+    
+    // Calculate success rate as percentage
+    let success_rate = provider.total_completed as f64 / total as f64;
+    let lifetime = (success_rate * 100.0) as u128;
+
+    // Weight the recent window more heavily so providers can recover
+    // from an early bad streak
+    if provider.recent_outcomes.is_empty() {
+        return Decimal::from_ratio(lifetime, 1u128);
+    }
+    let recent_successes = provider.recent_outcomes.iter().filter(|ok| **ok).count() as u128;
+    let recent = recent_successes * 100 / provider.recent_outcomes.len() as u128;
+    Decimal::from_ratio(lifetime * 30 + recent * 70, 100u128)
+}
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    // 0.1.0 stored a config without an admin, which the migration must name
+    let mut config = match CONFIG.may_load(deps.storage) {
+        Ok(Some(config)) => config,
+        _ => {
+            let legacy = LEGACY_CONFIG.load(deps.storage)?;
+            let admin = msg.admin.ok_or(ContractError::MigrationAdminRequired {})?;
+            let mut config = new_config(
+                deps.api.addr_validate(&admin)?,
+                legacy.community_pool,
+                legacy.community_fee_percent,
+                legacy.default_job_timeout,
+                legacy.heartbeat_timeout,
+            );
+            config.paused = legacy.paused;
+            config
+        }
+    };
+
     // Update config with new timeout values if provided
-    let mut config = CONFIG.load(deps.storage)?;
     
     if let Some(timeout) = msg.default_job_timeout {
         config.default_job_timeout = timeout;
     }
-    if let Some(hb_timeout) = msg.heartbeat_timeout {
-        config.heartbeat_timeout = hb_timeout;
+    if let Some(hb_timeout) = msg.heartbeat_timeout {
+        config.heartbeat_timeout = hb_timeout;
+    }
+    
+    CONFIG.save(deps.storage, &config)?;
+
+    // Earlier versions stored the last assigned id in NEXT_JOB_ID;
+    // move past it so the next submission doesn't collide
+    let next_job_id = NEXT_JOB_ID.load(deps.storage)?;
+    if JOBS.has(deps.storage, next_job_id) {
+        let next_job_id = next_job_id
+            .checked_add(1)
+            .ok_or(ContractError::JobIdsExhausted {})?;
+        NEXT_JOB_ID.save(deps.storage, &next_job_id)?;
+    }
+
+    // Build the status counts for deployments that predate them
+    if STATUS_COUNTS.may_load(deps.storage)?.is_none() {
+        let mut counts = StatusCounts::default();
+        for item in JOBS.range(deps.storage, None, None, Order::Ascending) {
+            let (_, job) = item?;
+            counts.record(None, &job.status);
+        }
+        STATUS_COUNTS.save(deps.storage, &counts)?;
+    }
+
+    // Count unfinished jobs for deployments that predate the global cap
+    if ACTIVE_JOB_COUNT.may_load(deps.storage)?.is_none() {
+        let mut active = 0u64;
+        for item in JOBS.range(deps.storage, None, None, Order::Ascending) {
+            let (_, job) = item?;
+            if !job.status.is_terminal() {
+                active += 1;
+            }
+        }
+        ACTIVE_JOB_COUNT.save(deps.storage, &active)?;
+    }
+
+    // Index open jobs by deadline, once per upgrade from an older version
+    let stored_version = get_contract_version(deps.storage)?.version;
+    if stored_version != CONTRACT_VERSION {
+        let open_jobs = JOBS
+            .range(deps.storage, None, None, Order::Ascending)
+            .filter_map(|item| match item {
+                Ok((_, job)) if job.status.is_terminal() || job.status == JobStatus::Blocked => None,
+                Ok((job_id, job)) => Some(Ok((job.deadline, job_id))),
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        for key in open_jobs {
+            JOBS_BY_DEADLINE.save(deps.storage, key, &())?;
+        }
+
+        rebuild_obligations(deps.storage)?;
+
+        // Older versions kept no per-job escrow; seed it from what each open job still holds
+        let unseeded = JOBS
+            .range(deps.storage, None, None, Order::Ascending)
+            .filter_map(|item| match item {
+                Ok((_, job)) if job.status.is_terminal() => None,
+                Ok((job_id, job)) => Some(Ok((job_id, job.remaining_escrow()))),
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        for (job_id, amount) in unseeded {
+            if !JOB_ESCROW.has(deps.storage, job_id) && !amount.is_zero() {
+                JOB_ESCROW.save(deps.storage, job_id, &amount)?;
+            }
+        }
+        LEDGERS_BACKFILLED.save(deps.storage, &true)?;
+    }
+
+    // Backfill the service index for providers registered before it existed
+    let providers = PROVIDERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (address, provider) in providers {
+        for capability in provider.capabilities {
+            PROVIDERS_BY_SERVICE.save(deps.storage, (capability.service_type.as_str(), &address), &())?;
+        }
     }
     
-    CONFIG.save(deps.storage, &config)?;
-    
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     Ok(Response::new()
         .add_attribute("action", "migrate")
-        .add_attribute("from_version", CONTRACT_VERSION)
+        .add_attribute("from_version", stored_version)
         .add_attribute("to_version", env!("CARGO_PKG_VERSION")))
 }
 // Neue Query-Funktionen hinzufügen
@@ -839,19 +3766,7 @@ fn query_list_active_providers(deps: Deps) -> StdResult<ProvidersResponse> {
             match item {
                 Ok((_, provider)) => {
                     if provider.active {
-                        Some(Ok(ProviderResponse {
-                            address: provider.address.to_string(),
-                            name: provider.name,
-                            capabilities: provider.capabilities,
-                            pricing: provider.pricing,
-                            endpoint: provider.endpoint,
-                            capacity: provider.capacity,
-                            active_jobs: provider.active_jobs,
-                            total_completed: provider.total_completed,
-                            reputation: provider.reputation,
-                            active: provider.active,
-                            registered_at: provider.registered_at,
-                        }))
+                        Some(Ok(provider_response(provider)))
                     } else {
                         None
                     }
@@ -868,17 +3783,642 @@ fn query_provider_stats(deps: Deps, address: String) -> StdResult<ProviderRespon
     let addr = deps.api.addr_validate(&address)?;
     let provider = PROVIDERS.load(deps.storage, &addr)?;
     
-    Ok(ProviderResponse {
-        address: provider.address.to_string(),
-        name: provider.name,
-        capabilities: provider.capabilities,
-        pricing: provider.pricing,
-        endpoint: provider.endpoint,
-        capacity: provider.capacity,
-        active_jobs: provider.active_jobs,
+    Ok(provider_response(provider))
+}
+
+/// Provider throughput - completed jobs per day since registration
+/// and the average time from submission to completion
+fn query_provider_throughput(
+    deps: Deps,
+    env: Env,
+    provider: String,
+) -> StdResult<ProviderThroughputResponse> {
+    let addr = deps.api.addr_validate(&provider)?;
+    let provider = PROVIDERS.load(deps.storage, &addr)?;
+
+    let elapsed = env
+        .block
+        .time
+        .seconds()
+        .saturating_sub(provider.registered_at.seconds());
+    let completed_per_day = if elapsed == 0 {
+        Decimal::zero()
+    } else {
+        Decimal::from_ratio(provider.total_completed as u128 * 86_400u128, elapsed as u128)
+    };
+
+    let avg_completion_time = provider
+        .total_completion_time
+        .checked_div(provider.total_completed);
+
+    Ok(ProviderThroughputResponse {
+        provider: provider.address.to_string(),
         total_completed: provider.total_completed,
-        reputation: provider.reputation,
-        active: provider.active,
-        registered_at: provider.registered_at,
+        completed_per_day,
+        avg_completion_time,
+    })
+}
+
+fn query_job_type_timeout(deps: Deps, job_type: String) -> StdResult<JobTypeTimeoutResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let custom = JOB_TYPE_TIMEOUTS.may_load(deps.storage, &job_type)?;
+
+    Ok(JobTypeTimeoutResponse {
+        job_type,
+        timeout: custom.unwrap_or(config.default_job_timeout),
+        is_default: custom.is_none(),
+    })
+}
+
+fn query_next_job_id(deps: Deps) -> StdResult<NextJobIdResponse> {
+    Ok(NextJobIdResponse {
+        next_job_id: NEXT_JOB_ID.load(deps.storage)?,
+    })
+}
+
+/// Provider uptime - share of heartbeat windows that were not missed
+/// Returns 100% for providers without any recorded windows
+fn query_provider_uptime(deps: Deps, provider: String) -> StdResult<ProviderUptimeResponse> {
+    let addr = deps.api.addr_validate(&provider)?;
+    let provider = PROVIDERS.load(deps.storage, &addr)?;
+
+    let uptime_percent = if provider.total_heartbeat_windows == 0 {
+        Decimal::from_ratio(100u128, 1u128)
+    } else {
+        let kept = provider
+            .total_heartbeat_windows
+            .saturating_sub(provider.missed_windows);
+        Decimal::from_ratio(kept as u128 * 100, provider.total_heartbeat_windows as u128)
+    };
+
+    Ok(ProviderUptimeResponse {
+        provider: provider.address.to_string(),
+        total_heartbeat_windows: provider.total_heartbeat_windows,
+        missed_windows: provider.missed_windows,
+        uptime_percent,
+    })
+}
+
+/// Solvency check - contract balance against escrow owed to non-terminal jobs
+fn query_solvency(deps: Deps, env: Env) -> StdResult<SolvencyResponse> {
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, "umedas")?
+        .amount;
+    let obligations = OUTSTANDING_OBLIGATIONS
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    Ok(SolvencyResponse {
+        balance,
+        obligations,
+        solvent: balance >= obligations,
+    })
+}
+
+/// Value locked per accepted denom, alongside what non-terminal jobs are owed in each
+fn query_tvl(deps: Deps, env: Env) -> StdResult<TvlResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let balances = deps
+        .querier
+        .query_all_balances(&env.contract.address)?
+        .into_iter()
+        .filter(|coin| config.accepted_denoms.contains(&coin.denom))
+        .collect();
+    let obligations = config
+        .accepted_denoms
+        .iter()
+        .map(|denom| {
+            let amount = OBLIGATIONS_BY_DENOM.may_load(deps.storage, denom)?.unwrap_or_default();
+            Ok(Coin { denom: denom.clone(), amount })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TvlResponse { balances, obligations })
+}
+
+/// Market price - min/max/avg base price across providers pricing this job type
+fn query_market_price(deps: Deps, job_type: String) -> StdResult<MarketPriceResponse> {
+    let prices: Vec<Decimal> = PROVIDERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(MAX_PROVIDER_SCAN)
+        .filter_map(|item| match item {
+            Ok((_, provider)) => provider
+                .pricing
+                .get(&job_type)
+                .map(|tier| Ok(tier.base_price)),
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let provider_count = prices.len() as u32;
+    let min_price = prices.iter().min().copied().unwrap_or_default();
+    let max_price = prices.iter().max().copied().unwrap_or_default();
+    let total = prices
+        .iter()
+        .try_fold(Decimal::zero(), |acc, p| acc.checked_add(*p))?;
+    let avg_price = if provider_count == 0 {
+        Decimal::zero()
+    } else {
+        total
+            .checked_div(Decimal::from_ratio(provider_count as u128, 1u128))
+            .map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?
+    };
+
+    Ok(MarketPriceResponse {
+        job_type,
+        min_price,
+        max_price,
+        avg_price,
+        provider_count,
+    })
+}
+
+fn query_preview_provider_selection(
+    deps: Deps,
+    job_type: String,
+    parameters: String,
+    strategy: SelectionStrategy,
+) -> StdResult<ProviderSelectionResponse> {
+    let selected = select_provider(deps.storage, &job_type, &strategy)?;
+    let estimated_cost = match &selected {
+        Some(provider) => job_price(provider, &job_type, &parameters)
+            .map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?,
+        None => None,
+    };
+
+    Ok(ProviderSelectionResponse {
+        estimated_cost,
+        provider: selected.map(|p| p.address.to_string()),
+    })
+}
+
+/// Keeper work - what ProcessTimedOutJobs / ProcessInactiveProviders would act on
+fn query_keeper_work(deps: Deps, env: Env) -> StdResult<KeeperWorkResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let current_time = env.block.time.seconds();
+
+    // Each count reads one entry past its cap so a truncated figure can be flagged
+    let timed_out_job_count = JOBS_BY_DEADLINE
+        .keys(
+            deps.storage,
+            None,
+            Some(Bound::exclusive((current_time, 0u64))),
+            Order::Ascending,
+        )
+        .take(MAX_JOB_SCAN + 1)
+        .count();
+    let expiring_job_count = JOBS_BY_DEADLINE
+        .keys(
+            deps.storage,
+            Some(Bound::inclusive((current_time, 0u64))),
+            Some(Bound::inclusive((current_time + EXPIRING_JOB_WINDOW, u64::MAX))),
+            Order::Ascending,
+        )
+        .take(MAX_JOB_SCAN + 1)
+        .count();
+
+    let mut inactive_provider_count = 0u64;
+    let mut providers_truncated = false;
+    if config.require_heartbeat {
+        for (scanned, item) in PROVIDERS
+            .range(deps.storage, None, None, Order::Ascending)
+            .take(MAX_PROVIDER_SCAN + 1)
+            .enumerate()
+        {
+            if scanned == MAX_PROVIDER_SCAN {
+                providers_truncated = true;
+                break;
+            }
+            let (_, provider) = item?;
+            if provider.active && heartbeat_expired(&provider, &config, current_time) {
+                inactive_provider_count += 1;
+            }
+        }
+    }
+
+    Ok(KeeperWorkResponse {
+        timed_out_job_count: timed_out_job_count.min(MAX_JOB_SCAN) as u64,
+        inactive_provider_count,
+        expiring_job_count: expiring_job_count.min(MAX_JOB_SCAN) as u64,
+        has_more: timed_out_job_count > MAX_JOB_SCAN
+            || expiring_job_count > MAX_JOB_SCAN
+            || providers_truncated,
+    })
+}
+
+fn query_status_distribution(deps: Deps) -> StdResult<StatusDistributionResponse> {
+    let counts = STATUS_COUNTS.may_load(deps.storage)?.unwrap_or_default();
+
+    Ok(StatusDistributionResponse {
+        submitted: counts.submitted,
+        processing: counts.processing,
+        completed: counts.completed,
+        failed: counts.failed,
+        cancelled: counts.cancelled,
+        acknowledged: counts.acknowledged,
+        blocked: counts.blocked,
+    })
+}
+
+fn query_provider_ratings(deps: Deps, provider: String) -> StdResult<ProviderRatingsResponse> {
+    let addr = deps.api.addr_validate(&provider)?;
+    let p = PROVIDERS.load(deps.storage, &addr)?;
+
+    let total_ratings = p.rating_1 + p.rating_2 + p.rating_3 + p.rating_4 + p.rating_5;
+    let stars = p.rating_1 + 2 * p.rating_2 + 3 * p.rating_3 + 4 * p.rating_4 + 5 * p.rating_5;
+    let average = if total_ratings == 0 {
+        None
+    } else {
+        Some(Decimal::from_ratio(stars, total_ratings))
+    };
+
+    Ok(ProviderRatingsResponse {
+        provider: addr.to_string(),
+        rating_1: p.rating_1,
+        rating_2: p.rating_2,
+        rating_3: p.rating_3,
+        rating_4: p.rating_4,
+        rating_5: p.rating_5,
+        total_ratings,
+        average,
+    })
+}
+
+fn query_client_stats(deps: Deps, client: String) -> StdResult<ClientStatsResponse> {
+    let addr = deps.api.addr_validate(&client)?;
+    let stats = CLIENT_STATS.may_load(deps.storage, &addr)?.unwrap_or_default();
+
+    Ok(ClientStatsResponse {
+        client: addr.to_string(),
+        completed_jobs: stats.completed_jobs,
+        total_spent: stats.total_spent,
+        fee_discount_percent: fee_discount_percent(deps.storage, stats.completed_jobs)?,
+    })
+}
+
+fn query_client_prefs(deps: Deps, client: String) -> StdResult<ClientPrefsResponse> {
+    let addr = deps.api.addr_validate(&client)?;
+    let prefs = CLIENT_PREFS.may_load(deps.storage, &addr)?.unwrap_or_default();
+
+    Ok(ClientPrefsResponse {
+        client: addr.to_string(),
+        notify_url: prefs.notify_url,
+    })
+}
+
+/// Which roles an address holds, so front-ends can pick the right views
+fn query_role(deps: Deps, address: String) -> StdResult<RoleResponse> {
+    let addr = deps.api.addr_validate(&address)?;
+    let config = CONFIG.load(deps.storage)?;
+    let has_jobs_as_client = JOBS_BY_CLIENT
+        .prefix(&addr)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+
+    Ok(RoleResponse {
+        is_admin: addr == config.admin,
+        is_provider: PROVIDERS.has(deps.storage, &addr),
+        has_jobs_as_client,
+        address: addr.to_string(),
+    })
+}
+
+/// Recompute maintained counters from the stored jobs and report any drift
+fn query_check_invariants(deps: Deps) -> StdResult<InvariantsResponse> {
+    let mut violations = vec![];
+
+    let mut counts = StatusCounts::default();
+    let mut open_jobs = 0u64;
+    let mut escrow = Uint128::zero();
+    let mut max_job_id = None;
+    for item in JOBS.range(deps.storage, None, None, Order::Ascending) {
+        let (job_id, job) = item?;
+        counts.record(None, &job.status);
+        if !job.status.is_terminal() {
+            open_jobs += 1;
+            if job.denom == "umedas" {
+                escrow += job.remaining_escrow();
+            }
+            let held = JOB_ESCROW.may_load(deps.storage, job_id)?.unwrap_or_default();
+            if held != job.remaining_escrow() {
+                violations.push(format!(
+                    "job {} escrow {} != remaining payment {}",
+                    job_id, held, job.remaining_escrow()
+                ));
+            }
+        }
+        max_job_id = Some(job_id);
+    }
+
+    let active_jobs: u64 = PROVIDERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, provider)| provider.active_jobs as u64))
+        .sum::<StdResult<u64>>()?;
+    if active_jobs != open_jobs {
+        violations.push(format!(
+            "provider active_jobs total {} != open jobs {}",
+            active_jobs, open_jobs
+        ));
+    }
+
+    let next_job_id = NEXT_JOB_ID.load(deps.storage)?;
+    if let Some(max_job_id) = max_job_id.filter(|id| *id >= next_job_id) {
+        violations.push(format!(
+            "next job id {} does not exceed job {}",
+            next_job_id, max_job_id
+        ));
+    }
+
+    let active_job_count = ACTIVE_JOB_COUNT.may_load(deps.storage)?.unwrap_or_default();
+    if active_job_count != open_jobs {
+        violations.push(format!(
+            "active job count {} != open jobs {}",
+            active_job_count, open_jobs
+        ));
+    }
+
+    let stored_counts = STATUS_COUNTS.may_load(deps.storage)?.unwrap_or_default();
+    if stored_counts != counts {
+        violations.push(format!(
+            "status counts {:?} != actual {:?}",
+            stored_counts, counts
+        ));
+    }
+
+    let obligations = OUTSTANDING_OBLIGATIONS.may_load(deps.storage)?.unwrap_or_default();
+    if obligations != escrow {
+        violations.push(format!(
+            "outstanding obligations {} != open escrow {}",
+            obligations, escrow
+        ));
+    }
+
+    Ok(InvariantsResponse { violations })
+}
+
+/// What can still be read from storage - flags instead of errors, for diagnosing a broken deployment
+/// Counts walk keys only, so corrupted records are still counted
+fn query_raw_state(deps: Deps) -> RawStateResponse {
+    RawStateResponse {
+        config_ok: CONFIG.load(deps.storage).is_ok(),
+        next_job_id: NEXT_JOB_ID.load(deps.storage).ok(),
+        provider_count: PROVIDERS
+            .keys_raw(deps.storage, None, None, Order::Ascending)
+            .count() as u64,
+        job_count: JOBS
+            .keys_raw(deps.storage, None, None, Order::Ascending)
+            .count() as u64,
+    }
+}
+
+fn query_health(deps: Deps) -> StdResult<HealthResponse> {
+    let version = get_contract_version(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    Ok(HealthResponse {
+        contract: version.contract,
+        version: version.version,
+        paused: config.paused,
+        query_paused: config.query_paused,
+    })
+}
+
+/// Cheapest active providers for a job type, from the service index
+fn query_cheapest_providers(
+    deps: Deps,
+    job_type: String,
+    limit: Option<u32>,
+) -> StdResult<CheapestProvidersResponse> {
+    let limit = limit.unwrap_or(10).min(50) as usize;
+
+    let mut providers = vec![];
+    for address in PROVIDERS_BY_SERVICE
+        .prefix(job_type.as_str())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(MAX_PROVIDER_SCAN)
+    {
+        let provider = PROVIDERS.load(deps.storage, &address?)?;
+        if !provider.active {
+            continue;
+        }
+        if let Some(tier) = provider.pricing.get(&job_type) {
+            providers.push(ProviderPrice {
+                provider: provider.address.to_string(),
+                base_price: tier.base_price,
+                reputation: provider.reputation,
+            });
+        }
+    }
+
+    providers.sort_by(|a, b| {
+        a.base_price
+            .cmp(&b.base_price)
+            .then_with(|| b.reputation.cmp(&a.reputation))
+    });
+    providers.truncate(limit);
+
+    Ok(CheapestProvidersResponse { job_type, providers })
+}
+
+/// Providers advertising a service, each with its pricing tier for that service
+fn query_providers_with_pricing(
+    deps: Deps,
+    service_type: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ProvidersWithPricingResponse> {
+    let limit = limit.unwrap_or(10).min(50) as usize;
+    let start_after = start_after
+        .map(|a| deps.api.addr_validate(&a))
+        .transpose()?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    let providers = PROVIDERS_BY_SERVICE
+        .prefix(service_type.as_str())
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|address| {
+            let provider = PROVIDERS.load(deps.storage, &address?)?;
+            Ok(ProviderWithPricing {
+                pricing: provider.pricing.get(&service_type).cloned(),
+                provider: provider.address.to_string(),
+                name: provider.name,
+                active: provider.active,
+                reputation: provider.reputation,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ProvidersWithPricingResponse { service_type, providers })
+}
+
+/// Providers by tenure, oldest registration first; ties go to the lowest address
+fn query_oldest_providers(
+    deps: Deps,
+    env: Env,
+    limit: Option<u32>,
+) -> StdResult<OldestProvidersResponse> {
+    let limit = limit.unwrap_or(10).min(50) as usize;
+
+    let mut providers = PROVIDERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(MAX_PROVIDER_SCAN)
+        .map(|item| item.map(|(_, provider)| provider))
+        .collect::<StdResult<Vec<_>>>()?;
+    // Stable sort keeps address order among providers registered in the same block
+    providers.sort_by_key(|p| p.registered_at);
+    providers.truncate(limit);
+
+    let providers = providers
+        .into_iter()
+        .map(|p| ProviderTenure {
+            provider: p.address.to_string(),
+            name: p.name,
+            registered_at: p.registered_at,
+            tenure_days: env.block.time.seconds().saturating_sub(p.registered_at.seconds()) / 86_400,
+        })
+        .collect();
+
+    Ok(OldestProvidersResponse { providers })
+}
+
+/// Providers with no completed job, in address order
+/// `min_age` skips providers registered less than that many seconds ago
+fn query_idle_providers(
+    deps: Deps,
+    env: Env,
+    limit: Option<u32>,
+    min_age: Option<u64>,
+) -> StdResult<IdleProvidersResponse> {
+    let limit = limit.unwrap_or(10).min(50) as usize;
+    let now = env.block.time.seconds();
+    let min_age = min_age.unwrap_or_default();
+
+    let providers = PROVIDERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(MAX_PROVIDER_SCAN)
+        .filter_map(|item| match item {
+            Ok((_, p)) if p.total_completed == 0 => {
+                let age = now.saturating_sub(p.registered_at.seconds());
+                (age >= min_age).then(|| {
+                    Ok(ProviderTenure {
+                        provider: p.address.to_string(),
+                        name: p.name,
+                        registered_at: p.registered_at,
+                        tenure_days: age / 86_400,
+                    })
+                })
+            }
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(IdleProvidersResponse { providers })
+}
+
+/// Provider earnings - payouts for jobs completed within [from, to]
+fn query_provider_earnings(
+    deps: Deps,
+    provider: String,
+    from: u64,
+    to: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ProviderEarningsResponse> {
+    let addr = deps.api.addr_validate(&provider)?;
+    let limit = (limit.unwrap_or(100) as usize).min(MAX_JOB_SCAN);
+    let start = start_after.map(Bound::exclusive);
+
+    // One page of the provider's jobs; the caller adds pages up
+    let job_ids = JOBS_BY_PROVIDER
+        .prefix(&addr)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let next_start_after = if job_ids.len() == limit {
+        job_ids.last().copied()
+    } else {
+        None
+    };
+
+    let mut earnings = vec![];
+    let mut job_count = 0u64;
+    for job_id in job_ids {
+        let job = JOBS.load(deps.storage, job_id)?;
+        if !matches!(job.status, JobStatus::Completed | JobStatus::Acknowledged) {
+            continue;
+        }
+        let completed_at = match job.completed_at {
+            Some(time) => time.seconds(),
+            None => continue,
+        };
+        if completed_at >= from && completed_at <= to {
+            add_to_totals(&mut earnings, &job.denom, job.provider_fee);
+            job_count += 1;
+        }
+    }
+
+    Ok(ProviderEarningsResponse {
+        provider: addr.to_string(),
+        from,
+        to,
+        earnings,
+        job_count,
+        next_start_after,
     })
 }
+
+fn refund_response(id: u64, record: RefundRecord) -> RefundResponse {
+    RefundResponse {
+        id,
+        job_id: record.job_id,
+        client: record.client.to_string(),
+        amount: record.amount,
+        reason: record.reason,
+        timestamp: record.timestamp,
+    }
+}
+
+fn query_list_refunds(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<RefundsResponse> {
+    let limit = limit.unwrap_or(10).min(50) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let refunds = REFUNDS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(id, record)| refund_response(id, record)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(RefundsResponse { refunds })
+}
+
+fn query_refunds_by_client(
+    deps: Deps,
+    client: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<RefundsResponse> {
+    let client_addr = deps.api.addr_validate(&client)?;
+    let limit = limit.unwrap_or(10).min(50) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let refunds = REFUNDS_BY_CLIENT
+        .prefix(&client_addr)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|id| {
+            let id = id?;
+            Ok(refund_response(id, REFUNDS.load(deps.storage, id)?))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(RefundsResponse { refunds })
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,20 +18,50 @@ pub enum ContractError {
     #[error("Provider not active")]
     ProviderNotActive {},
 
+    #[error("Provider does not offer job type {job_type}")]
+    ServiceNotOffered { job_type: String },
+
+    #[error("Service {service_type} is temporarily disabled by the provider")]
+    ServiceDisabled { service_type: String },
+
     #[error("Job not found")]
     JobNotFound {},
 
     #[error("Invalid provider data")]
     InvalidProviderData {},
 
+    #[error("Denom {denom} is not accepted")]
+    DenomNotAccepted { denom: String },
+
+    #[error("At least one accepted denom is required")]
+    NoAcceptedDenoms {},
+
+    #[error("Unexpected funds in {denom} - only the job's denom may be attached")]
+    UnexpectedDenom { denom: String },
+
+    #[error("Payment exceeds the provider's maximum of {max}")]
+    PaymentTooLarge { max: Uint128 },
+
     #[error("No payment provided")]
     NoPayment {},
 
     #[error("Insufficient payment: expected {expected}, received {received}")]
     InsufficientPayment { expected: String, received: String },
 
-    #[error("Invalid job parameters")]
-    InvalidJobParameters {},
+    #[error("Price {actual} exceeds the client's maximum {max}")]
+    PriceExceededMax { max: Uint128, actual: Uint128 },
+
+    #[error("Job price overflows - quantity or base price too large")]
+    PriceOverflow {},
+
+    #[error("Invalid job parameters: {reason}")]
+    InvalidJobParameters { reason: String },
+
+    #[error("Missing required parameters: {missing}")]
+    MissingRequiredParameters { missing: String },
+
+    #[error("Job complexity {complexity} exceeds provider maximum {max}")]
+    ComplexityTooHigh { complexity: u64, max: u64 },
 
     #[error("Job not in correct state")]
     InvalidJobState {},
@@ -40,5 +70,119 @@ pub enum ContractError {
     CancelWindowExpired {},
 
     #[error("Contract is paused - operations are temporarily disabled")]
-    ContractPaused {},         
+    ContractPaused {},
+
+    #[error("Invalid referrer - referrer cannot be the client or the provider")]
+    InvalidReferrer {},
+
+    #[error("Invalid fee percent: {percent} (must be 0-100)")]
+    InvalidFeePercent { percent: u64 },
+
+    #[error("Invalid discount percent: {percent} (must be 0-100)")]
+    InvalidDiscountPercent { percent: u64 },
+
+    #[error("Invalid rating weight: {weight} (must be 0-100)")]
+    InvalidRatingWeight { weight: u64 },
+
+    #[error("Invalid failure rate {rate} - must be at most 1")]
+    InvalidFailureRate { rate: Decimal },
+
+    #[error("Invalid refund percent: {percent} (must be 0-100)")]
+    InvalidRefundPercent { percent: u64 },
+
+    #[error("An admin must be given when migrating from a config without one")]
+    MigrationAdminRequired {},
+
+    #[error("Obligation ledgers not yet backfilled - migrate before sweeping")]
+    LedgersNotBackfilled {},
+
+    #[error("Job id space exhausted")]
+    JobIdsExhausted {},
+
+    #[error("Invalid progress {percent} - must exceed the last report and be at most 100")]
+    InvalidProgress { percent: u64 },
+
+    #[error("Job {job_id} already exists")]
+    JobAlreadyExists { job_id: u64 },
+
+    #[error("Illegal job status transition from {from} to {to}")]
+    IllegalTransition { from: String, to: String },
+
+    #[error("No provider in the list can accept this job")]
+    NoQualifiedProvider {},
+
+    #[error("Result deadline {deadline} has passed")]
+    ResultDeadlinePassed { deadline: u64 },
+
+    #[error("Result hash was already used by this provider")]
+    ResultHashReused {},
+
+    #[error("At least one result is required")]
+    NoResults {},
+
+    #[error("Job is reserved for the preferred provider until {until}")]
+    ExclusiveWindowActive { until: u64 },
+
+    #[error("Invalid capacity - must be at least 1, deactivate the provider instead")]
+    InvalidCapacity {},
+
+    #[error("Invalid provider address {address}: {reason}")]
+    InvalidProviderAddress { address: String, reason: String },
+
+    #[error("Address {address} is not a registered provider")]
+    ProviderNotRegistered { address: String },
+
+    #[error("Invalid reputation {reputation} - at most 100 allowed")]
+    InvalidReputation { reputation: Decimal },
+
+    #[error("Cannot remove the last capability - deactivate the provider instead")]
+    LastCapability {},
+
+    #[error("Too many capabilities - at most {max} allowed")]
+    TooManyCapabilities { max: u32 },         
+
+    #[error("Registration fee of {expected} umedas required")]
+    RegistrationFeeRequired { expected: Uint128 },
+
+    #[error("Reason too long - at most {max} characters")]
+    ReasonTooLong { max: usize },
+
+    #[error("Notify URL too long - at most {max} characters")]
+    NotifyUrlTooLong { max: usize },
+
+    #[error("No provider slots left for service {service_type}")]
+    ServiceSlotsFull { service_type: String },
+
+    #[error("Reputation too low to advertise more than {max} capabilities")]
+    ReputationTooLow { max: u32 },
+
+    #[error("Provider updated too recently - retry after {retry_after}")]
+    UpdateTooFrequent { retry_after: u64 },
+
+    #[error("Heartbeat sent too soon - retry after {retry_after}")]
+    HeartbeatTooSoon { retry_after: u64 },
+
+    #[error("Extension limit reached - at most {max_extensions} extensions totalling {max_total} seconds")]
+    ExtensionLimitReached { max_extensions: u32, max_total: u64 },
+
+    #[error("Client is not on the allow-list")]
+    ClientNotAllowed {},
+
+    #[error("Contract-wide active job limit reached")]
+    GlobalCapacityReached {},
+
+    #[error("Penalty for job {job_id} has already been waived")]
+    PenaltyAlreadyWaived { job_id: u64 },
+
+    #[error("Invalid rating {rating} - must be 1 to 5")]
+    InvalidRating { rating: u8 },
+
+    #[error("Job {job_id} has already been rated")]
+    AlreadyRated { job_id: u64 },
+
+    #[error("Job {job_id} has already been requeued")]
+    AlreadyRequeued { job_id: u64 },
+
+    #[error("Job {job_id} belongs to another client and can't be depended on")]
+    ForeignDependency { job_id: u64 },
 }
@@ -1,5 +1,5 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Decimal, Timestamp, Uint128};
+use cosmwasm_std::{Coin, Decimal, Timestamp, Uint128};
 use std::collections::HashMap;
 
 #[cw_serde]
@@ -10,6 +10,8 @@ pub struct InstantiateMsg {
     pub heartbeat_timeout: u64,  
 }
 
+// UpdateConfig carries every tunable; messages are deserialized once per call
+#[allow(clippy::large_enum_variant)]
 #[cw_serde]
 pub enum ExecuteMsg {
     RegisterProvider {
@@ -17,16 +19,24 @@ pub enum ExecuteMsg {
         capabilities: Vec<ServiceCapability>,
         pricing: HashMap<String, PricingTier>,
         endpoint: String,
+        refund_guarantee: Option<bool>,
+        max_payment: Option<Uint128>, // largest job payment accepted, None for no cap
     },
     SubmitJob {
         provider: String,
         job_type: String,
         parameters: String,
+        referrer: Option<String>,
+        depends_on: Option<u64>, // client's own job that must complete before this one is eligible
+        denom: String, // must be one of accepted_denoms, and the only denom attached
+        max_price: Option<Uint128>, // reject if the provider's current price is higher
     },
     CompleteJob {
         job_id: u64,
         result_hash: String,
         result_url: String,
+        encryption: Option<EncryptionInfo>,
+        result_size_bytes: Option<u64>, // reported output size, for storage-style billing
     },
     UpdateProviderStatus {
         active: bool,
@@ -36,23 +46,150 @@ pub enum ExecuteMsg {
         endpoint: Option<String>,
         pricing: Option<HashMap<String, PricingTier>>,
         capacity: Option<u32>,
+        payout_address: Option<String>,
+        capabilities: Option<Vec<ServiceCapability>>,
+        auto_accept: Option<bool>,
+        refund_guarantee: Option<bool>,
+        max_payment: Option<Uint128>, // zero removes the cap
     },
     HeartBeat {},                     
     FailJob {                          
         job_id: u64,
         reason: String,
+        refund_percent: Option<u64>, // share refunded to the client, defaults to 100
     },
     CancelJob {                       
         job_id: u64,
+        reason: Option<String>,
+    },
+    ProcessTimedOutJobs {
+        limit: Option<u32>, // capped by max_sweep_batch
     },
-    ProcessTimedOutJobs {},            
     ProcessInactiveProviders {},       
+    // Remove providers silent for longer than deregister_after that never took a job to an outcome
+    PruneStaleProviders {
+        limit: Option<u32>, // capped by max_sweep_batch
+    },
     UpdateConfig {                     
         default_job_timeout: Option<u64>,
         heartbeat_timeout: Option<u64>,
+        referral_fee_percent: Option<u64>,
+        require_heartbeat: Option<bool>,
+        exclusive_window: Option<u64>,
+        cancel_fee_percent: Option<u64>,
+        max_capabilities: Option<u32>,
+        query_paused: Option<bool>,
+        registration_fee: Option<Uint128>,
+        rounding_mode: Option<RoundingMode>,
+        max_providers_per_service: Option<u32>, // 0 removes the cap
+        capability_reputation_threshold: Option<Decimal>,
+        low_reputation_max_capabilities: Option<u32>,
+        update_cooldown: Option<u64>,
+        max_total_active_jobs: Option<u64>, // 0 means unlimited
+        enforce_unique_results: Option<bool>,
+        result_deadline_buffer: Option<u64>, // 0 disables result deadlines
+        min_heartbeat_interval: Option<u64>,
+        cancel_community_fee_percent: Option<u64>,
+        max_extensions: Option<u32>,
+        max_total_extension: Option<u64>,
+        require_client_allowlist: Option<bool>,
+        default_reputation: Option<Decimal>, // at most 100
+        accepted_denoms: Option<Vec<String>>, // must not be empty
+        max_sweep_batch: Option<u32>, // 0 removes the cap
+        requeue_fee_discount_percent: Option<u64>, // 0-100
+        refund_on_deactivation: Option<bool>,
+        deregister_after: Option<u64>, // seconds, 0 disables pruning
+        probation_period: Option<u64>, // seconds after registration, 0 disables probation
+        probation_max_payment: Option<Uint128>,
+        probation_min_jobs: Option<u64>, // completions also required to leave probation
+        auto_pause_failure_rate: Option<Decimal>, // zero disables the auto-pause
+        auto_pause_min_jobs: Option<u32>,
+        failure_window: Option<u64>, // seconds
+        reputation_rating_weight: Option<u64>, // 0-100
     },
     PauseContract {},                  
     UnpauseContract {},                
+    SetJobTypeTimeout {
+        job_type: String,
+        timeout: Option<u64>, // None removes the override
+    },
+    SubmitJobWithFallback {
+        providers: Vec<String>, // tried in order
+        job_type: String,
+        parameters: String,
+        denom: String, // must be one of accepted_denoms, and the only denom attached
+    },
+    CompleteJobMulti {
+        job_id: u64,
+        results: Vec<JobResult>,
+    },
+    AcceptJob {
+        job_id: u64,
+    },
+    RegisterProvidersBatch {
+        providers: Vec<ProviderRegistration>,
+    },
+    AcknowledgeResult {
+        job_id: u64,
+    },
+    AutoSubmitJob {
+        job_type: String,
+        parameters: String,
+        strategy: SelectionStrategy,
+        denom: String, // must be one of accepted_denoms, and the only denom attached
+    },
+    // Resubmit a failed job to the same provider, paying again with the attached funds
+    RequeueJob {
+        job_id: u64,
+    },
+    RateJob {
+        job_id: u64,
+        rating: u8, // 1-5
+    },
+    CancelJobsBatch {
+        job_ids: Vec<u64>, // ineligible ids are skipped, not fatal
+    },
+    RequestExtension {
+        job_id: u64,
+        additional_seconds: u64,
+    },
+    ReportProgress {
+        job_id: u64,
+        percent: u64, // cumulative, 1-100
+    },
+    ApproveProgress {
+        job_id: u64,
+    },
+    AddAllowedClient {
+        address: String,
+    },
+    RemoveAllowedClient {
+        address: String,
+    },
+    SetFeeDiscountTiers {
+        tiers: Vec<FeeDiscountTier>,
+    },
+    RemoveCapability {
+        service_type: String,
+    },
+    // Stop or resume taking new jobs for one service, e.g. during maintenance
+    SetServiceEnabled {
+        service_type: String,
+        enabled: bool,
+    },
+    SetClientPrefs {
+        notify_url: Option<String>, // None clears the stored prefs
+    },
+    // Add the attached funds to a pending job's payment
+    TopUpJob {
+        job_id: u64,
+    },
+    // Admin: send any balance not owed to open jobs to the community pool
+    SweepDust {},
+    // Admin: undo the reputation hit of a failure declared force majeure
+    WaivePenalty {
+        job_id: u64,
+    },
 }
 
 
@@ -74,6 +211,18 @@ pub enum QueryMsg {
     #[returns(JobResponse)]
     GetJob { job_id: u64 },
     
+    // Job plus its provider's current profile, None if the provider is gone
+    #[returns(JobWithProviderResponse)]
+    GetJobWithProvider { job_id: u64 },
+
+    // The provider's jobs it can work on right now, oldest first
+    #[returns(JobsResponse)]
+    GetActionableJobs {
+        provider: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    
     #[returns(JobsResponse)]
     ListJobsByProvider {
         provider: String,
@@ -87,12 +236,143 @@ pub enum QueryMsg {
         start_after: Option<u64>,
         limit: Option<u32>,
     },
+
+    #[returns(JobsResponse)]
+    ListJobsByProviderAndStatus {
+        provider: String,
+        status: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    // Dry run of AutoSubmitJob's provider selection
+    #[returns(ProviderSelectionResponse)]
+    PreviewProviderSelection {
+        job_type: String,
+        parameters: String, // priced the same way SubmitJob prices them
+        strategy: SelectionStrategy,
+    },
+
+    // Counts of maintenance work waiting for a keeper
+    #[returns(KeeperWorkResponse)]
+    GetKeeperWork {},
+
+    #[returns(StatusDistributionResponse)]
+    GetStatusDistribution {},
+
+    #[returns(ProviderRatingsResponse)]
+    GetProviderRatings { provider: String },
+
+    // Active providers for a job type, cheapest first, higher reputation breaking ties
+    #[returns(CheapestProvidersResponse)]
+    CheapestProviders {
+        job_type: String,
+        limit: Option<u32>,
+    },
+
+    // Providers advertising a service with their price for it, by address
+    #[returns(ProvidersWithPricingResponse)]
+    ListProvidersWithPricing {
+        service_type: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    // Longest-registered providers first
+    #[returns(OldestProvidersResponse)]
+    OldestProviders { limit: Option<u32> },
+
+    // Providers that have never completed a job, optionally only those registered
+    // at least min_age seconds ago
+    #[returns(IdleProvidersResponse)]
+    ListIdleProviders {
+        limit: Option<u32>,
+        min_age: Option<u64>,
+    },
+
+    // Sum of provider payouts for jobs completed within [from, to], in seconds
+    #[returns(ProviderEarningsResponse)]
+    GetProviderEarnings {
+        provider: String,
+        from: u64,
+        to: u64,
+        start_after: Option<u64>, // job id; sum pages by following next_start_after
+        limit: Option<u32>, // jobs read per page
+    },
+
+    #[returns(RefundsResponse)]
+    ListRefunds {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    #[returns(RefundsResponse)]
+    GetRefundsByClient {
+        client: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    #[returns(ClientStatsResponse)]
+    GetClientStats { client: String },
+
+    #[returns(ClientPrefsResponse)]
+    GetClientPrefs { client: String },
+
+    #[returns(RoleResponse)]
+    GetRole { address: String },
+
+    // Marketplace-wide completions, newest first
+    #[returns(JobsResponse)]
+    RecentCompletions { limit: Option<u32> },
+
+    // Debug/audit check of maintained counters against stored jobs; scans every job
+    #[returns(InvariantsResponse)]
+    CheckInvariants {},
+
+    // Always answered, even while queries are paused
+    #[returns(HealthResponse)]
+    GetHealth {},
+
+    // Diagnostics that never fail on unreadable state - always answered
+    #[returns(RawStateResponse)]
+    GetRawState {},
+
+    // How an amount would split between community pool and provider at current settings
+    #[returns(FeeSplitResponse)]
+    PreviewFeeSplit { amount: Uint128 },
+
+    // Contract-wide failure rate in the current auto-pause window
+    #[returns(FailureRateResponse)]
+    GetFailureRate {},
     
     #[returns(ProvidersResponse)]  // ADD THIS
     ListActiveProviders {},
     
     #[returns(ProviderResponse)]    // ADD THIS  
     GetProviderStats { address: String }, 
+
+    #[returns(ProviderThroughputResponse)]
+    GetProviderThroughput { provider: String },
+
+    #[returns(JobTypeTimeoutResponse)]
+    GetJobTypeTimeout { job_type: String },
+
+    #[returns(NextJobIdResponse)]
+    GetNextJobId {},
+
+    #[returns(ProviderUptimeResponse)]
+    GetProviderUptime { provider: String },
+
+    #[returns(SolvencyResponse)]
+    GetSolvency {},
+
+    // Balance and escrow owed per accepted denom
+    #[returns(TvlResponse)]
+    GetTVL {},
+
+    #[returns(MarketPriceResponse)]
+    GetMarketPrice { job_type: String },
 }
 
 #[cw_serde]
@@ -100,6 +380,35 @@ pub struct ServiceCapability {
     pub service_type: String,
     pub max_complexity: u64,
     pub avg_completion_time: u64, // seconds
+    #[serde(default)]
+    pub required_parameters: Vec<String>, // top-level keys every job's parameters must contain
+    #[serde(default = "default_enabled")]
+    pub enabled: bool, // false pauses new jobs for this service only
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[cw_serde]
+pub struct ProviderRegistration {
+    pub address: String,
+    pub name: String,
+    pub capabilities: Vec<ServiceCapability>,
+    pub pricing: HashMap<String, PricingTier>,
+    pub endpoint: String,
+    // Track record carried over from elsewhere; reputation is 0-100 and is
+    // derived from the counts when omitted, or stands for 100 jobs when given alone
+    pub reputation: Option<Decimal>,
+    pub total_completed: Option<u64>,
+    pub total_failed: Option<u64>,
+}
+
+#[cw_serde]
+pub struct JobResult {
+    pub hash: String,
+    pub url: String,
+    pub content_type: String,
 }
 
 #[cw_serde]
@@ -108,14 +417,85 @@ pub struct PricingTier {
     pub unit: String,
 }
 
+/// Community fee discount for clients with at least `min_completed_jobs` completed jobs
+#[cw_serde]
+pub struct FeeDiscountTier {
+    pub min_completed_jobs: u64,
+    pub discount_percent: u64, // share of the community fee waived, 0-100
+}
+
+/// Describes how an encrypted result is protected - metadata only, the contract never decrypts
+#[cw_serde]
+pub struct EncryptionInfo {
+    pub scheme: String,
+    pub pubkey_hint: Option<String>,
+}
+
+/// How AutoSubmitJob picks among providers offering the job type
+#[cw_serde]
+pub enum SelectionStrategy {
+    HighestReputation,
+    LowestUtilization,
+}
+
+/// Why the contract returned a payment to the client
+#[cw_serde]
+pub enum RefundReason {
+    Cancel,
+    Fail,
+    Timeout,
+}
+
+/// How the community fee is rounded when it doesn't divide evenly
+#[cw_serde]
+pub enum RoundingMode {
+    TowardProvider,
+    TowardCommunity,
+    Banker,
+}
+
 // Response types
 #[cw_serde]
 pub struct ConfigResponse {
+    pub admin: String,
     pub community_pool: String,
     pub community_fee_percent: u64,
     pub default_job_timeout: u64,      
     pub heartbeat_timeout: u64,          
     pub paused: bool,          
+    pub referral_fee_percent: u64,
+    pub require_heartbeat: bool,
+    pub exclusive_window: u64,
+    pub cancel_fee_percent: u64,
+    pub max_capabilities: u32,
+    pub query_paused: bool,
+    pub registration_fee: Uint128,
+    pub rounding_mode: RoundingMode,
+    pub max_providers_per_service: Option<u32>,
+    pub capability_reputation_threshold: Decimal,
+    pub low_reputation_max_capabilities: u32,
+    pub update_cooldown: u64,
+    pub max_total_active_jobs: u64,
+    pub enforce_unique_results: bool,
+    pub result_deadline_buffer: Option<u64>,
+    pub min_heartbeat_interval: u64,
+    pub cancel_community_fee_percent: u64,
+    pub max_extensions: u32,
+    pub max_total_extension: u64,
+    pub require_client_allowlist: bool,
+    pub default_reputation: Decimal,
+    pub accepted_denoms: Vec<String>,
+    pub max_sweep_batch: u32,
+    pub requeue_fee_discount_percent: u64,
+    pub refund_on_deactivation: bool,
+    pub deregister_after: u64,
+    pub probation_period: u64,
+    pub probation_max_payment: Uint128,
+    pub probation_min_jobs: u64,
+    pub auto_pause_failure_rate: Decimal,
+    pub auto_pause_min_jobs: u32,
+    pub failure_window: u64,
+    pub reputation_rating_weight: u64,
 }
 
 #[cw_serde]
@@ -128,9 +508,20 @@ pub struct ProviderResponse {
     pub capacity: u32,
     pub active_jobs: u32,
     pub total_completed: u64,
+    pub total_failed: u64,
+    pub last_heartbeat: u64,
     pub reputation: Decimal,
     pub active: bool,
     pub registered_at: Timestamp,
+    pub payout_address: Option<String>,
+    pub auto_accept: bool,
+    pub refund_guarantee: bool,
+    pub max_payment: Option<Uint128>,
+    pub on_time_count: u64,
+    pub late_count: u64,
+    pub completion_nonce: u64,
+    pub total_job_count: u64, // lets front-ends plan paging through ListJobsByProvider
+    pub sla_compliance: Decimal, // 0-100, share of completions within the advertised time
 }
 
 #[cw_serde]
@@ -151,14 +542,265 @@ pub struct JobResponse {
     pub result_url: Option<String>,
     pub created_at: Timestamp,
     pub completed_at: Option<Timestamp>,
+    pub referrer: Option<String>,
+    pub results: Vec<JobResult>,
+    pub preferred_provider: Option<String>,
+    pub exclusive_until: Option<u64>,
+    pub provider_reputation_at_submit: Decimal,
+    pub cancel_reason: Option<String>,
+    pub encryption: Option<EncryptionInfo>,
+    pub result_size_bytes: Option<u64>,
+    pub rating: Option<u8>,
+    pub depends_on: Option<u64>,
+    pub result_deadline: Option<u64>,
+    pub deadline: u64,
+    pub extension_count: u32,
+    pub reported_percent: u64,
+    pub released_amount: Uint128,
+    pub original_job_id: Option<u64>,
+    pub requeued_as: Option<u64>,
+    pub history: Vec<StatusChangeResponse>,
+}
+
+#[cw_serde]
+pub struct FeeSplitResponse {
+    pub community_fee: Uint128,
+    pub provider_payout: Uint128,
+}
+
+#[cw_serde]
+pub struct FailureRateResponse {
+    pub window_seconds: u64,
+    pub failures: u32,
+    pub completions: u32,
+    pub failure_rate: Decimal,
+    pub auto_pause_failure_rate: Decimal,
+    pub auto_pause_min_jobs: u32,
+}
+
+#[cw_serde]
+pub struct StatusChangeResponse {
+    pub status: String,
+    pub at: Timestamp,
+}
+
+#[cw_serde]
+pub struct JobWithProviderResponse {
+    pub job: JobResponse,
+    pub provider: Option<ProviderResponse>,
 }
 
 #[cw_serde]
 pub struct JobsResponse {
     pub jobs: Vec<JobResponse>,
+    /// Pass as `start_after` to continue; None once the index is exhausted
+    pub next_start_after: Option<u64>,
+}
+#[cw_serde]
+pub struct ProviderThroughputResponse {
+    pub provider: String,
+    pub total_completed: u64,
+    pub completed_per_day: Decimal,
+    pub avg_completion_time: Option<u64>, // seconds
+}
+
+#[cw_serde]
+pub struct JobTypeTimeoutResponse {
+    pub job_type: String,
+    pub timeout: u64,
+    pub is_default: bool,
+}
+
+#[cw_serde]
+pub struct NextJobIdResponse {
+    pub next_job_id: u64,
+}
+
+#[cw_serde]
+pub struct ProviderUptimeResponse {
+    pub provider: String,
+    pub total_heartbeat_windows: u64,
+    pub missed_windows: u64,
+    pub uptime_percent: Decimal, // 0-100
+}
+
+#[cw_serde]
+pub struct SolvencyResponse {
+    pub balance: Uint128,
+    pub obligations: Uint128,
+    pub solvent: bool,
+}
+
+#[cw_serde]
+pub struct TvlResponse {
+    pub balances: Vec<Coin>,
+    pub obligations: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct MarketPriceResponse {
+    pub job_type: String,
+    pub min_price: Decimal,
+    pub max_price: Decimal,
+    pub avg_price: Decimal,
+    pub provider_count: u32,
+}
+
+#[cw_serde]
+pub struct ProviderSelectionResponse {
+    pub provider: Option<String>,
+    pub estimated_cost: Option<Uint128>, // what submitting these parameters would cost, if priced
+}
+
+#[cw_serde]
+pub struct KeeperWorkResponse {
+    pub timed_out_job_count: u64,
+    pub inactive_provider_count: u64,
+    pub expiring_job_count: u64, // deadline within the next 10 minutes
+    pub has_more: bool, // a count stopped at its scan cap, so the real figure may be higher
+}
+
+#[cw_serde]
+pub struct StatusDistributionResponse {
+    pub submitted: u64,
+    pub processing: u64,
+    pub completed: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    pub acknowledged: u64,
+    pub blocked: u64,
+}
+
+#[cw_serde]
+pub struct ProviderRatingsResponse {
+    pub provider: String,
+    pub rating_1: u64,
+    pub rating_2: u64,
+    pub rating_3: u64,
+    pub rating_4: u64,
+    pub rating_5: u64,
+    pub total_ratings: u64,
+    pub average: Option<Decimal>, // None until the first rating
+}
+
+#[cw_serde]
+pub struct ProviderPrice {
+    pub provider: String,
+    pub base_price: Decimal,
+    pub reputation: Decimal,
 }
+
+#[cw_serde]
+pub struct CheapestProvidersResponse {
+    pub job_type: String,
+    pub providers: Vec<ProviderPrice>,
+}
+
+#[cw_serde]
+pub struct ProviderWithPricing {
+    pub provider: String,
+    pub name: String,
+    pub active: bool,
+    pub reputation: Decimal,
+    pub pricing: Option<PricingTier>, // None if the service is advertised but unpriced
+}
+
+#[cw_serde]
+pub struct ProvidersWithPricingResponse {
+    pub service_type: String,
+    pub providers: Vec<ProviderWithPricing>,
+}
+
+#[cw_serde]
+pub struct ProviderTenure {
+    pub provider: String,
+    pub name: String,
+    pub registered_at: Timestamp,
+    pub tenure_days: u64,
+}
+
+#[cw_serde]
+pub struct OldestProvidersResponse {
+    pub providers: Vec<ProviderTenure>,
+}
+
+#[cw_serde]
+pub struct IdleProvidersResponse {
+    pub providers: Vec<ProviderTenure>,
+}
+
+#[cw_serde]
+pub struct ProviderEarningsResponse {
+    pub provider: String,
+    pub from: u64,
+    pub to: u64,
+    pub earnings: Vec<Coin>, // one entry per denom earned in
+    pub job_count: u64,
+    pub next_start_after: Option<u64>, // set while the provider has more jobs to read
+}
+
+#[cw_serde]
+pub struct RefundResponse {
+    pub id: u64,
+    pub job_id: u64,
+    pub client: String,
+    pub amount: Uint128,
+    pub reason: RefundReason,
+    pub timestamp: Timestamp,
+}
+
+#[cw_serde]
+pub struct RefundsResponse {
+    pub refunds: Vec<RefundResponse>,
+}
+
+#[cw_serde]
+pub struct ClientStatsResponse {
+    pub client: String,
+    pub completed_jobs: u64,
+    pub total_spent: Vec<Coin>,
+    pub fee_discount_percent: u64, // discount applied to the client's next completion
+}
+
+#[cw_serde]
+pub struct ClientPrefsResponse {
+    pub client: String,
+    pub notify_url: Option<String>,
+}
+
+#[cw_serde]
+pub struct RoleResponse {
+    pub address: String,
+    pub is_admin: bool,
+    pub is_provider: bool,
+    pub has_jobs_as_client: bool,
+}
+
+/// Violations found by CheckInvariants, empty when the state is consistent
+#[cw_serde]
+pub struct InvariantsResponse {
+    pub violations: Vec<String>,
+}
+
+#[cw_serde]
+pub struct RawStateResponse {
+    pub config_ok: bool,
+    pub next_job_id: Option<u64>, // None if it could not be loaded
+    pub provider_count: u64,
+    pub job_count: u64,
+}
+
+#[cw_serde]
+pub struct HealthResponse {
+    pub contract: String,
+    pub version: String,
+    pub paused: bool,
+    pub query_paused: bool,
+}
+
 #[cw_serde]
 pub struct MigrateMsg {
-    pub default_job_timeout: Option<u64>,  // ADD THIS
+    pub default_job_timeout: Option<u64>,
     pub heartbeat_timeout: Option<u64>,
+    pub admin: Option<String>, // required when migrating from 0.1.0, which had no admin
 }
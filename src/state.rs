@@ -1,18 +1,63 @@
-use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128};
+use cosmwasm_std::{Addr, Coin, Decimal, Timestamp, Uint128};
 use cw_storage_plus::{Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::msg::{PricingTier, ServiceCapability};
+use crate::msg::{EncryptionInfo, FeeDiscountTier, JobResult, PricingTier, RefundReason, RoundingMode, ServiceCapability};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
+    pub admin: Addr,
     pub community_pool: Addr,
     pub community_fee_percent: u64,
     pub default_job_timeout: u64,      
     pub heartbeat_timeout: u64,        
     pub paused: bool,                  
+    pub referral_fee_percent: u64,
+    pub require_heartbeat: bool,
+    pub exclusive_window: u64,
+    pub cancel_fee_percent: u64,
+    pub max_capabilities: u32,
+    pub query_paused: bool,
+    pub registration_fee: Uint128,
+    pub rounding_mode: RoundingMode,
+    pub max_providers_per_service: Option<u32>,
+    pub capability_reputation_threshold: Decimal, // zero disables the gate
+    pub low_reputation_max_capabilities: u32,
+    pub update_cooldown: u64, // seconds between UpdateProvider calls, 0 disables
+    pub max_total_active_jobs: u64, // 0 means unlimited
+    pub enforce_unique_results: bool,
+    pub result_deadline_buffer: Option<u64>, // seconds on top of avg_completion_time, None disables
+    pub min_heartbeat_interval: u64, // 0 disables
+    pub cancel_community_fee_percent: u64, // share of a cancelled job's payment kept for the community pool
+    pub max_extensions: u32, // deadline extensions a provider may request per job
+    pub max_total_extension: u64, // seconds, across all extensions of a job
+    pub require_client_allowlist: bool,
+    pub default_reputation: Decimal, // starting reputation for new providers
+    pub accepted_denoms: Vec<String>, // denoms a job may be paid in
+    pub max_sweep_batch: u32, // timed-out jobs processed per call, 0 means unlimited
+    pub requeue_fee_discount_percent: u64, // community fee waived on requeued jobs
+    pub refund_on_deactivation: bool, // fail and refund Submitted jobs when a provider is auto-deactivated
+    pub deregister_after: u64, // seconds without a heartbeat before a provider may be pruned, 0 disables
+    pub probation_period: u64, // seconds after registration during which payments are capped, 0 disables
+    pub probation_max_payment: Uint128,
+    pub probation_min_jobs: u64, // completed jobs needed, on top of the period, to leave probation
+    pub auto_pause_failure_rate: Decimal, // failure rate above which the contract pauses itself, zero disables
+    pub auto_pause_min_jobs: u32, // outcomes needed in the window before the rate is acted on
+    pub failure_window: u64, // seconds the rolling failure-rate window spans
+    pub reputation_rating_weight: u64, // share of reputation taken from client ratings, 0 uses success rate only
+}
+
+/// Config as stored by 0.1.0, before the admin and later settings existed
+/// Read once by migrate and converted into the current Config
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyConfig {
+    pub community_pool: Addr,
+    pub community_fee_percent: u64,
+    pub default_job_timeout: u64,
+    pub heartbeat_timeout: u64,
+    pub paused: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -26,10 +71,77 @@ pub struct Provider {
     pub active_jobs: u32,
     pub total_completed: u64,
     pub total_failed: u64,
+    #[serde(default)]
+    pub total_completion_time: u64,
     pub reputation: Decimal,
     pub active: bool,
     pub registered_at: Timestamp,
     pub last_heartbeat: u64,
+    #[serde(default)]
+    pub total_heartbeat_windows: u64,
+    #[serde(default)]
+    pub missed_windows: u64,
+    #[serde(default)]
+    pub window_credited_at: u64, // heartbeat that last earned a window
+    #[serde(default)]
+    pub payout_address: Option<Addr>,
+    #[serde(default)]
+    pub recent_outcomes: Vec<bool>,
+    #[serde(default)]
+    pub recent_outcome_jobs: Vec<u64>, // job behind each entry of recent_outcomes, same order
+    #[serde(default)]
+    pub auto_accept: bool, // new jobs start in Processing, skipping AcceptJob
+    #[serde(default)]
+    pub refund_guarantee: bool, // failed jobs are always fully refunded
+    #[serde(default)]
+    pub max_payment: Option<Uint128>, // larger submissions are rejected
+    #[serde(default)]
+    pub last_updated_at: Option<u64>,
+    #[serde(default)]
+    pub rating_1: u64,
+    #[serde(default)]
+    pub rating_2: u64,
+    #[serde(default)]
+    pub rating_3: u64,
+    #[serde(default)]
+    pub rating_4: u64,
+    #[serde(default)]
+    pub rating_5: u64,
+    #[serde(default)]
+    pub on_time_count: u64, // completions within the capability's avg_completion_time
+    #[serde(default)]
+    pub late_count: u64,
+    #[serde(default)]
+    pub completion_nonce: u64, // incremented on every completion, carried in the completion receipt
+    #[serde(default)]
+    pub total_jobs: u64, // jobs in the provider's index, whatever their status
+}
+
+/// Number of most recent job outcomes kept per provider for reputation
+pub const RECENT_OUTCOMES_WINDOW: usize = 10;
+
+impl Provider {
+    /// Record a job outcome, dropping the oldest once the window is full
+    pub fn record_outcome(&mut self, job_id: u64, success: bool) {
+        if self.recent_outcomes.len() >= RECENT_OUTCOMES_WINDOW {
+            self.recent_outcomes.remove(0);
+            if self.recent_outcome_jobs.len() > self.recent_outcomes.len() {
+                self.recent_outcome_jobs.remove(0);
+            }
+        }
+        self.recent_outcomes.push(success);
+        self.recent_outcome_jobs.push(job_id);
+    }
+
+    /// Drop a job's outcome from the window, if it is still there
+    /// Outcomes recorded before jobs were tracked sit at the front with no job id
+    pub fn forget_outcome(&mut self, job_id: u64) {
+        let untracked = self.recent_outcomes.len().saturating_sub(self.recent_outcome_jobs.len());
+        if let Some(index) = self.recent_outcome_jobs.iter().position(|id| *id == job_id) {
+            self.recent_outcome_jobs.remove(index);
+            self.recent_outcomes.remove(untracked + index);
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -47,6 +159,74 @@ pub struct Job {
     pub completed_at: Option<Timestamp>,
     pub deadline: u64,                 
     pub failure_reason: Option<String>, 
+    #[serde(default)]
+    pub referrer: Option<Addr>,
+    #[serde(default)]
+    pub results: Vec<JobResult>,
+    #[serde(default)]
+    pub preferred_provider: Option<Addr>,
+    #[serde(default)]
+    pub exclusive_until: Option<u64>,
+    #[serde(default)]
+    pub fallback_providers: Vec<Addr>,
+    #[serde(default)]
+    pub provider_reputation_at_submit: Decimal,
+    #[serde(default)]
+    pub cancel_reason: Option<String>,
+    #[serde(default)]
+    pub encryption: Option<EncryptionInfo>,
+    #[serde(default)]
+    pub result_size_bytes: Option<u64>,
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub depends_on: Option<u64>,
+    #[serde(default)]
+    pub result_deadline: Option<u64>, // set on acceptance when result deadlines are enabled
+    #[serde(default)]
+    pub provider_fee: Uint128, // amount paid out to the provider on completion
+    #[serde(default)]
+    pub extension_count: u32,
+    #[serde(default)]
+    pub total_extension: u64,
+    #[serde(default)]
+    pub reported_percent: u64, // latest progress reported by the provider
+    #[serde(default)]
+    pub released_amount: Uint128, // paid out ahead of completion on approved progress
+    #[serde(default = "default_denom")]
+    pub denom: String, // payment denom, used for every payout and refund of this job
+    #[serde(default)]
+    pub original_job_id: Option<u64>, // failed job this one was requeued from
+    #[serde(default)]
+    pub requeued_as: Option<u64>, // job this failed one was requeued into; only one requeue is allowed
+    #[serde(default)]
+    pub penalty_waived: bool, // failure no longer counts against the provider
+    #[serde(default)]
+    pub counted_toward_pause: bool, // failure is in the auto-pause window as of completed_at
+    #[serde(default)]
+    pub history: Vec<StatusChange>, // every status the job has entered, oldest first
+}
+
+fn default_denom() -> String {
+    "umedas".to_string()
+}
+
+impl Job {
+    /// Payment still held in escrow for this job
+    pub fn remaining_escrow(&self) -> Uint128 {
+        self.payment_amount.saturating_sub(self.released_amount)
+    }
+
+    /// Submitted by the provider to itself; such jobs never move the auto-pause
+    pub fn is_self_dealt(&self) -> bool {
+        self.client == self.provider
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatusChange {
+    pub status: JobStatus,
+    pub at: Timestamp,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -56,22 +236,190 @@ pub enum JobStatus {
     Completed,
     Failed,
     Cancelled, 
+    Acknowledged,
+    Blocked,
+}
+impl std::fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            JobStatus::Submitted => "submitted",
+            JobStatus::Processing => "processing",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Acknowledged => "acknowledged",
+            JobStatus::Blocked => "blocked",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for JobStatus {
+    type Err = cosmwasm_std::StdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "submitted" => Ok(JobStatus::Submitted),
+            "processing" => Ok(JobStatus::Processing),
+            "completed" => Ok(JobStatus::Completed),
+            "failed" => Ok(JobStatus::Failed),
+            "cancelled" => Ok(JobStatus::Cancelled),
+            "acknowledged" => Ok(JobStatus::Acknowledged),
+            "blocked" => Ok(JobStatus::Blocked),
+            _ => Err(cosmwasm_std::StdError::parse_err("JobStatus", format!("unknown status {}", s))),
+        }
+    }
 }
+
 impl JobStatus {
-    pub fn to_string(&self) -> String {
-        match self {
-            JobStatus::Submitted => "submitted".to_string(),
-            JobStatus::Processing => "processing".to_string(),
-            JobStatus::Completed => "completed".to_string(),
-            JobStatus::Failed => "failed".to_string(),
-            JobStatus::Cancelled => "cancelled".to_string(),  // Add this
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed
+                | JobStatus::Failed
+                | JobStatus::Cancelled
+                | JobStatus::Acknowledged
+        )
+    }
+}
+
+/// A payment returned to a client, kept for auditing
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RefundRecord {
+    pub job_id: u64,
+    pub client: Addr,
+    pub amount: Uint128,
+    pub reason: RefundReason,
+    pub timestamp: Timestamp,
+}
+
+/// Per-client activity, maintained on completion
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ClientStats {
+    pub completed_jobs: u64,
+    pub total_spent: Vec<Coin>, // one entry per denom paid in
+}
+
+/// Off-chain notification preferences a client stores for front-ends; never called by the contract
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ClientPrefs {
+    pub notify_url: Option<String>,
+}
+
+/// Job outcomes counted toward the auto-pause during one slice of the rolling window
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct FailureWindow {
+    pub started_at: u64,
+    pub failures: u32,
+    pub completions: u32,
+}
+
+impl FailureWindow {
+    /// Share of the window's outcomes that were failures
+    pub fn failure_rate(&self) -> Decimal {
+        let total = self.failures as u128 + self.completions as u128;
+        if total == 0 {
+            return Decimal::zero();
+        }
+        Decimal::from_ratio(self.failures as u128, total)
+    }
+}
+
+/// Number of jobs in each status, maintained on every transition
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct StatusCounts {
+    pub submitted: u64,
+    pub processing: u64,
+    pub completed: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    pub acknowledged: u64,
+    pub blocked: u64,
+}
+
+impl StatusCounts {
+    fn count_mut(&mut self, status: &JobStatus) -> &mut u64 {
+        match status {
+            JobStatus::Submitted => &mut self.submitted,
+            JobStatus::Processing => &mut self.processing,
+            JobStatus::Completed => &mut self.completed,
+            JobStatus::Failed => &mut self.failed,
+            JobStatus::Cancelled => &mut self.cancelled,
+            JobStatus::Acknowledged => &mut self.acknowledged,
+            JobStatus::Blocked => &mut self.blocked,
         }
     }
+
+    /// Move one job from `from` (None for a new job) to `to`
+    pub fn record(&mut self, from: Option<&JobStatus>, to: &JobStatus) {
+        if let Some(from) = from {
+            let count = self.count_mut(from);
+            *count = count.saturating_sub(1);
+        }
+        *self.count_mut(to) += 1;
+    }
+}
+
+/// Legal job state machine:
+/// Submitted -> Processing | Completed | Failed | Cancelled
+/// Processing -> Completed | Failed
+/// Completed -> Acknowledged (client confirmation, no further effect on funds)
+/// Blocked -> Submitted (dependency completed) | Cancelled
+/// Completed, Failed, Cancelled and Acknowledged are terminal
+pub fn can_transition(from: &JobStatus, to: &JobStatus) -> bool {
+    matches!(
+        (from, to),
+        (JobStatus::Submitted, JobStatus::Processing)
+            | (JobStatus::Submitted, JobStatus::Completed)
+            | (JobStatus::Submitted, JobStatus::Failed)
+            | (JobStatus::Submitted, JobStatus::Cancelled)
+            | (JobStatus::Processing, JobStatus::Completed)
+            | (JobStatus::Processing, JobStatus::Failed)
+            | (JobStatus::Completed, JobStatus::Acknowledged)
+            | (JobStatus::Blocked, JobStatus::Submitted)
+            | (JobStatus::Blocked, JobStatus::Cancelled)
+    )
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
+// Same key as CONFIG, only read when migrating from 0.1.0
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
 pub const PROVIDERS: Map<&Addr, Provider> = Map::new("providers");
 pub const JOBS: Map<u64, Job> = Map::new("jobs");
 pub const NEXT_JOB_ID: Item<u64> = Item::new("next_job_id");
 pub const JOBS_BY_PROVIDER: Map<(&Addr, u64), ()> = Map::new("jobs_by_provider");
 pub const JOBS_BY_CLIENT: Map<(&Addr, u64), ()> = Map::new("jobs_by_client");
+// Service type -> providers advertising it
+pub const PROVIDERS_BY_SERVICE: Map<(&str, &Addr), ()> = Map::new("providers_by_service");
+// (deadline, job_id) for jobs that have not reached a terminal state
+pub const JOBS_BY_DEADLINE: Map<(u64, u64), ()> = Map::new("jobs_by_deadline");
+// (dependency job_id, dependent job_id) for jobs still Blocked
+pub const JOBS_BY_DEPENDENCY: Map<(u64, u64), ()> = Map::new("jobs_by_dependency");
+// (completed_at seconds, job_id) for every completed job
+pub const COMPLETED_BY_TIME: Map<(u64, u64), ()> = Map::new("completed_by_time");
+// (provider, result hash) for every result a provider has submitted
+pub const USED_RESULT_HASHES: Map<(&Addr, &str), ()> = Map::new("used_result_hashes");
+// Jobs not yet in a terminal state, across all providers
+pub const ACTIVE_JOB_COUNT: Item<u64> = Item::new("active_job_count");
+pub const STATUS_COUNTS: Item<StatusCounts> = Item::new("status_counts");
+pub const JOB_TYPE_TIMEOUTS: Map<&str, u64> = Map::new("job_type_timeouts");
+/// Sum of umedas escrow across all non-terminal jobs
+pub const OUTSTANDING_OBLIGATIONS: Item<Uint128> = Item::new("outstanding_obligations");
+/// Funds held for each non-terminal job, in the job's denom; removed once paid out or refunded
+pub const JOB_ESCROW: Map<u64, Uint128> = Map::new("job_escrow");
+/// Escrow owed to non-terminal jobs, per payment denom
+pub const OBLIGATIONS_BY_DENOM: Map<&str, Uint128> = Map::new("obligations_by_denom");
+/// Set once the obligation and escrow ledgers cover every open job, at instantiate or after migrate's backfill
+pub const LEDGERS_BACKFILLED: Item<bool> = Item::new("ledgers_backfilled");
+// Refund ledger, keyed by an auto-incrementing id
+pub const REFUNDS: Map<u64, RefundRecord> = Map::new("refunds");
+pub const NEXT_REFUND_ID: Item<u64> = Item::new("next_refund_id");
+pub const REFUNDS_BY_CLIENT: Map<(&Addr, u64), ()> = Map::new("refunds_by_client");
+// Clients allowed to submit jobs when require_client_allowlist is set
+pub const CLIENT_ALLOWLIST: Map<&Addr, ()> = Map::new("client_allowlist");
+pub const CLIENT_STATS: Map<&Addr, ClientStats> = Map::new("client_stats");
+pub const CLIENT_PREFS: Map<&Addr, ClientPrefs> = Map::new("client_prefs");
+// Volume-based community fee discounts, any order
+pub const FEE_DISCOUNT_TIERS: Item<Vec<FeeDiscountTier>> = Item::new("fee_discount_tiers");
+// Slices of the rolling window the auto-pause failure rate is measured over, oldest first
+pub const FAILURE_WINDOW: Item<Vec<FailureWindow>> = Item::new("failure_window");
@@ -1,13 +1,192 @@
 #[cfg(test)]
 mod tests {
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, Addr, Decimal};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coins, from_json, Addr, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, Uint128};
     use std::collections::HashMap;
 
-    use medas_computing_contract::contract::{execute, instantiate, query};
+    use medas_computing_contract::contract::{execute, instantiate, migrate, parse_quantity, query};
     use medas_computing_contract::msg::{
-        ExecuteMsg, InstantiateMsg, PricingTier, QueryMsg, ServiceCapability,
+        ClientPrefsResponse, ClientStatsResponse, ConfigResponse, FailureRateResponse, FeeSplitResponse, EncryptionInfo, FeeDiscountTier, ExecuteMsg, InstantiateMsg, HealthResponse, IdleProvidersResponse, InvariantsResponse, RawStateResponse, MigrateMsg, JobResponse, JobWithProviderResponse, KeeperWorkResponse, JobsResponse, MarketPriceResponse, JobResult, JobTypeTimeoutResponse,
+        NextJobIdResponse, OldestProvidersResponse, PricingTier, ProvidersWithPricingResponse, ProviderRatingsResponse, ProviderRegistration, ProviderResponse, ProvidersResponse,
+        CheapestProvidersResponse, ProviderEarningsResponse, ProviderSelectionResponse, ProviderThroughputResponse, ProviderUptimeResponse, QueryMsg,
+        RefundReason, RefundsResponse, RoleResponse, RoundingMode, SelectionStrategy, ServiceCapability,
+        SolvencyResponse, StatusDistributionResponse, TvlResponse,
     };
+    use medas_computing_contract::state::{can_transition, JobStatus, CONFIG, JOBS, JOB_ESCROW, JOBS_BY_CLIENT, JOBS_BY_DEADLINE, JOBS_BY_PROVIDER, LEDGERS_BACKFILLED, NEXT_JOB_ID, PROVIDERS, RECENT_OUTCOMES_WINDOW};
+
+    // Shared setup helpers
+
+    /// UpdateConfig that changes nothing
+    fn empty_config_update() -> ExecuteMsg {
+        ExecuteMsg::UpdateConfig {
+            default_job_timeout: None,
+            heartbeat_timeout: None,
+            referral_fee_percent: None,
+            require_heartbeat: None,
+            exclusive_window: None,
+            cancel_fee_percent: None,
+            max_capabilities: None,
+            query_paused: None,
+            registration_fee: None,
+            rounding_mode: None,
+            max_providers_per_service: None,
+            capability_reputation_threshold: None,
+            low_reputation_max_capabilities: None,
+            update_cooldown: None,
+            max_total_active_jobs: None,
+            enforce_unique_results: None,
+            result_deadline_buffer: None,
+            min_heartbeat_interval: None,
+            cancel_community_fee_percent: None,
+            max_extensions: None,
+            max_total_extension: None,
+            require_client_allowlist: None,
+            default_reputation: None,
+            accepted_denoms: None,
+            max_sweep_batch: None,
+            requeue_fee_discount_percent: None,
+            refund_on_deactivation: None,
+            deregister_after: None,
+            probation_period: None,
+            probation_max_payment: None,
+            probation_min_jobs: None,
+            auto_pause_failure_rate: None,
+            auto_pause_min_jobs: None,
+            failure_window: None,
+            reputation_rating_weight: None,
+        }
+    }
+
+    /// UpdateConfig that sets only the listed fields, e.g. `config_update! { max_sweep_batch: Some(2) }`
+    macro_rules! config_update {
+        ($($field:ident: $value:expr),* $(,)?) => {{
+            let mut update = empty_config_update();
+            $(
+                let value = $value;
+                if let ExecuteMsg::UpdateConfig { $field: slot, .. } = &mut update {
+                    *slot = value;
+                }
+            )*
+            update
+        }};
+    }
+
+    fn setup_contract(deps: DepsMut) {
+        let init_msg = InstantiateMsg {
+            community_pool: "medas1community...".to_string(),
+            community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
+        };
+        instantiate(deps, mock_env(), mock_info("creator", &[]), init_msg).unwrap();
+    }
+
+    /// Write state the way 0.1.0 stored it: an open job 1 and a completed job 2
+    /// for "provider", both paid 1_000_000 umedas by "client"
+    fn store_baseline_state(deps: DepsMut) {
+        deps.storage.set(
+            b"config",
+            br#"{"community_pool":"medas1community...","community_fee_percent":15,"default_job_timeout":3600,"heartbeat_timeout":300,"paused":false}"#,
+        );
+        let provider = Addr::unchecked("provider");
+        deps.storage.set(
+            &PROVIDERS.key(&provider),
+            br#"{"address":"provider","name":"Provider","capabilities":[{"service_type":"pi_calculation","max_complexity":100000,"avg_completion_time":180}],"pricing":{"pi_calculation":{"base_price":"0.01","unit":"digit"}},"endpoint":"https://test.com","capacity":10,"active_jobs":1,"total_completed":1,"total_failed":0,"reputation":"100","active":true,"registered_at":"1571797419879305533","last_heartbeat":1571797419}"#,
+        );
+        deps.storage.set(
+            &JOBS.key(1),
+            br#"{"id":1,"client":"client","provider":"provider","job_type":"pi_calculation","parameters":"{\"digits\":100}","payment_amount":"1000000","status":"Submitted","result_hash":null,"result_url":null,"created_at":"1571797419879305533","completed_at":null,"deadline":1571801019,"failure_reason":null}"#,
+        );
+        deps.storage.set(
+            &JOBS.key(2),
+            br#"{"id":2,"client":"client","provider":"provider","job_type":"pi_calculation","parameters":"{\"digits\":100}","payment_amount":"1000000","status":"Completed","result_hash":"hash2","result_url":"https://result.test","created_at":"1571797419879305533","completed_at":"1571797419879305533","deadline":1571801019,"failure_reason":null}"#,
+        );
+        for job_id in [1, 2] {
+            JOBS_BY_PROVIDER.save(deps.storage, (&provider, job_id), &()).unwrap();
+            JOBS_BY_CLIENT.save(deps.storage, (&Addr::unchecked("client"), job_id), &()).unwrap();
+        }
+        NEXT_JOB_ID.save(deps.storage, &3).unwrap();
+        cw2::set_contract_version(deps.storage, "crates.io:medas-computing-contract", "0.1.0").unwrap();
+    }
+
+    fn register_provider(deps: DepsMut, env: Env, provider: &str) {
+        let mut pricing = HashMap::new();
+        pricing.insert("pi_calculation".to_string(), PricingTier {
+            base_price: Decimal::percent(1),
+            unit: "digit".to_string(),
+        });
+
+        let register = ExecuteMsg::RegisterProvider {
+            name: "Provider".to_string(),
+            capabilities: vec![ServiceCapability {
+                service_type: "pi_calculation".to_string(),
+                max_complexity: 100000,
+                avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
+            }],
+            pricing,
+            endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps, env, mock_info(provider, &[]), register).unwrap();
+    }
+
+    fn submit_job(deps: DepsMut, env: Env, client: &str, provider: &str, amount: u128) -> u64 {
+        let submit = ExecuteMsg::SubmitJob {
+            provider: provider.to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let res = execute(deps, env, mock_info(client, &coins(amount, "umedas")), submit).unwrap();
+
+        res.attributes.iter()
+            .find(|a| a.key == "job_id")
+            .unwrap()
+            .value
+            .parse()
+            .unwrap()
+    }
+
+    fn complete_job(deps: DepsMut, env: Env, provider: &str, job_id: u64) {
+        let complete = ExecuteMsg::CompleteJob {
+            job_id,
+            result_hash: format!("hash{}", job_id),
+            result_url: "https://result.test".to_string(),
+            encryption: None,
+            result_size_bytes: None,
+        };
+        execute(deps, env, mock_info(provider, &[]), complete).unwrap();
+    }
+
+    fn pi_registration(address: &str) -> ProviderRegistration {
+        let mut pricing = HashMap::new();
+        pricing.insert("pi_calculation".to_string(), PricingTier {
+            base_price: Decimal::percent(1),
+            unit: "digit".to_string(),
+        });
+        ProviderRegistration {
+            address: address.to_string(),
+            name: format!("Fleet node {}", address),
+            capabilities: vec![ServiceCapability {
+                service_type: "pi_calculation".to_string(),
+                max_complexity: 100000,
+                avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
+            }],
+            pricing,
+            endpoint: format!("https://{}.fleet.test", address),
+            reputation: None,
+            total_completed: None,
+            total_failed: None,
+        }
+    }
 
     #[test]
     fn test_instantiate() {
@@ -16,6 +195,8 @@ mod tests {
         let msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
 
         let info = mock_info("creator", &coins(0, "umedas"));
@@ -32,6 +213,8 @@ mod tests {
         let init_msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
 
@@ -51,9 +234,13 @@ mod tests {
                 service_type: "pi_calculation".to_string(),
                 max_complexity: 100000,
                 avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
             }],
             pricing,
             endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
         };
 
         let info = mock_info("provider1", &[]);
@@ -70,6 +257,8 @@ mod tests {
         let init_msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
 
@@ -89,9 +278,13 @@ mod tests {
                 service_type: "pi_calculation".to_string(),
                 max_complexity: 100000,
                 avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
             }],
             pricing,
             endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
         };
 
         execute(
@@ -107,6 +300,10 @@ mod tests {
             provider: "provider1".to_string(),
             job_type: "pi_calculation".to_string(),
             parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
         };
 
         let info = mock_info("client1", &coins(1_000_000, "umedas"));
@@ -126,6 +323,8 @@ mod tests {
             job_id,
             result_hash: "abc123".to_string(),
             result_url: "https://test.com/result".to_string(),
+            encryption: None,
+            result_size_bytes: None,
         };
 
         let info = mock_info("provider1", &[]);
@@ -142,6 +341,8 @@ mod tests {
         let init_msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
 
@@ -161,9 +362,13 @@ mod tests {
                 service_type: "pi_calculation".to_string(),
                 max_complexity: 100000,
                 avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
             }],
             pricing,
             endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
         };
 
         execute(
@@ -191,6 +396,8 @@ mod tests {
         let init_msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
 
@@ -207,9 +414,13 @@ mod tests {
                 service_type: "pi_calculation".to_string(),
                 max_complexity: 100000,
                 avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
             }],
             pricing,
             endpoint: "https://berlin.test".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
         };
         execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), register).unwrap();
 
@@ -218,6 +429,10 @@ mod tests {
             provider: "provider".to_string(),
             job_type: "pi_calculation".to_string(),
             parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
         };
         let res = execute(
             deps.as_mut(),
@@ -238,6 +453,8 @@ mod tests {
             job_id,
             result_hash: "test123".to_string(),
             result_url: "https://result.test".to_string(),
+            encryption: None,
+            result_size_bytes: None,
         };
         let res = execute(
             deps.as_mut(),
@@ -256,6 +473,8 @@ mod tests {
         let init_msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
 
@@ -271,16 +490,24 @@ mod tests {
                 service_type: "pi_calculation".to_string(),
                 max_complexity: 100000,
                 avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
             }],
             pricing,
             endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
         };
         execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), register).unwrap();
 
         let submit = ExecuteMsg::SubmitJob {
             provider: "provider".to_string(),
             job_type: "pi_calculation".to_string(),
-            parameters: "{}".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
         };
         let res = execute(
             deps.as_mut(),
@@ -300,6 +527,8 @@ mod tests {
             job_id,
             result_hash: "test".to_string(),
             result_url: "test".to_string(),
+            encryption: None,
+            result_size_bytes: None,
         };
         
         let err = execute(
@@ -318,6 +547,8 @@ mod tests {
         let init_msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
 
@@ -333,9 +564,13 @@ mod tests {
                 service_type: "pi_calculation".to_string(),
                 max_complexity: 100000,
                 avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
             }],
             pricing: pricing.clone(),
             endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
         };
 
         // Erste Registrierung
@@ -353,6 +588,8 @@ mod tests {
         let init_msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
 
@@ -368,16 +605,24 @@ mod tests {
                 service_type: "pi_calculation".to_string(),
                 max_complexity: 100000,
                 avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
             }],
             pricing,
             endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
         };
         execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), register).unwrap();
 
         let submit = ExecuteMsg::SubmitJob {
             provider: "provider".to_string(),
             job_type: "pi_calculation".to_string(),
-            parameters: "{}".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
         };
 
         // Job ohne Payment sollte fehlschlagen
@@ -398,6 +643,8 @@ mod tests {
         let init_msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
 
@@ -413,9 +660,13 @@ mod tests {
                 service_type: "pi_calculation".to_string(),
                 max_complexity: 100000,
                 avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
             }],
             pricing,
             endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
         };
         execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), register).unwrap();
 
@@ -427,7 +678,11 @@ mod tests {
         let submit = ExecuteMsg::SubmitJob {
             provider: "provider".to_string(),
             job_type: "pi_calculation".to_string(),
-            parameters: "{}".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
         };
 
         let err = execute(
@@ -447,13 +702,19 @@ mod tests {
         let init_msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
 
         let submit = ExecuteMsg::SubmitJob {
             provider: "nonexistent".to_string(),
             job_type: "pi_calculation".to_string(),
-            parameters: "{}".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
         };
 
         let err = execute(
@@ -463,7 +724,11 @@ mod tests {
             submit,
         ).unwrap_err();
 
-        assert!(matches!(err, medas_computing_contract::ContractError::ProviderNotFound {}));
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::ProviderNotRegistered { ref address }
+                if address == "nonexistent"
+        ));
     }
 
     #[test]
@@ -473,6 +738,8 @@ mod tests {
         let init_msg = InstantiateMsg {
             community_pool: "medas1community...".to_string(),
             community_fee_percent: 15,
+            default_job_timeout: 3600,
+            heartbeat_timeout: 300,
         };
         instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
 
@@ -488,16 +755,24 @@ mod tests {
                 service_type: "pi_calculation".to_string(),
                 max_complexity: 100000,
                 avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
             }],
             pricing,
             endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
         };
         execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), register).unwrap();
 
         let submit = ExecuteMsg::SubmitJob {
             provider: "provider".to_string(),
             job_type: "pi_calculation".to_string(),
-            parameters: "{}".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
         };
 
         let res = execute(
@@ -518,6 +793,8 @@ mod tests {
             job_id,
             result_hash: "test".to_string(),
             result_url: "test".to_string(),
+            encryption: None,
+            result_size_bytes: None,
         };
         
         let res = execute(
@@ -545,4 +822,5308 @@ mod tests {
         assert_eq!(community_fee, "150000");
         assert_eq!(provider_payment, "850000");
     }
+
+    #[test]
+    fn test_provider_throughput() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        // Two jobs, taking 1h and 3h respectively
+        let mut env = mock_env();
+        let job1 = submit_job(deps.as_mut(), env.clone(), "client", "provider", 1_000_000);
+        let job2 = submit_job(deps.as_mut(), env.clone(), "client", "provider", 1_000_000);
+        env.block.time = env.block.time.plus_seconds(3600);
+        complete_job(deps.as_mut(), env.clone(), "provider", job1);
+        env.block.time = env.block.time.plus_seconds(7200);
+        complete_job(deps.as_mut(), env.clone(), "provider", job2);
+
+        // Query four days after registration: 2 jobs / 4 days = 0.5 per day
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(4 * 86_400);
+        let res: ProviderThroughputResponse = from_json(
+            query(deps.as_ref(), env, QueryMsg::GetProviderThroughput {
+                provider: "provider".to_string(),
+            }).unwrap(),
+        ).unwrap();
+
+        assert_eq!(res.total_completed, 2);
+        assert_eq!(res.completed_per_day, Decimal::percent(50));
+        assert_eq!(res.avg_completion_time, Some(7200));
+    }
+
+    #[test]
+    fn test_provider_throughput_without_completions() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        // Same block as registration - no elapsed time, no division by zero
+        let res: ProviderThroughputResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetProviderThroughput {
+                provider: "provider".to_string(),
+            }).unwrap(),
+        ).unwrap();
+
+        assert_eq!(res.completed_per_day, Decimal::zero());
+        assert_eq!(res.avg_completion_time, None);
+    }
+
+    #[test]
+    fn test_referral_fee_split() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = config_update! { referral_fee_percent: Some(10) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let submit = ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: Some("referrer".to_string()),
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit,
+        ).unwrap();
+        let job_id: u64 = res.attributes.iter()
+            .find(|a| a.key == "job_id")
+            .unwrap()
+            .value
+            .parse()
+            .unwrap();
+
+        let complete = ExecuteMsg::CompleteJob {
+            job_id,
+            result_hash: "test".to_string(),
+            result_url: "test".to_string(),
+            encryption: None,
+            result_size_bytes: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), complete).unwrap();
+
+        // Community 15% = 150,000; referrer 10% of the remaining 850,000 = 85,000
+        assert_eq!(res.messages.len(), 3);
+        let attr = |key: &str| -> u128 {
+            res.attributes.iter().find(|a| a.key == key).unwrap().value.parse().unwrap()
+        };
+        assert_eq!(attr("community_fee"), 150_000);
+        assert_eq!(attr("referral_fee"), 85_000);
+        assert_eq!(attr("provider_payment"), 765_000);
+        assert_eq!(
+            attr("community_fee") + attr("referral_fee") + attr("provider_payment"),
+            1_000_000
+        );
+    }
+
+    #[test]
+    fn test_no_referrer_payout_unchanged() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = config_update! { referral_fee_percent: Some(10) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let complete = ExecuteMsg::CompleteJob {
+            job_id,
+            result_hash: "test".to_string(),
+            result_url: "test".to_string(),
+            encryption: None,
+            result_size_bytes: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), complete).unwrap();
+
+        assert_eq!(res.messages.len(), 2);
+        let provider_payment = res.attributes.iter()
+            .find(|a| a.key == "provider_payment")
+            .unwrap()
+            .value
+            .clone();
+        assert_eq!(provider_payment, "850000");
+    }
+
+    #[test]
+    fn test_referrer_cannot_be_client_or_provider() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        for referrer in ["client", "provider"] {
+            let submit = ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                referrer: Some(referrer.to_string()),
+                depends_on: None,
+                denom: "umedas".to_string(),
+                max_price: None,
+            };
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("client", &coins(1_000_000, "umedas")),
+                submit,
+            ).unwrap_err();
+            assert!(matches!(err, medas_computing_contract::ContractError::InvalidReferrer {}));
+        }
+    }
+
+    #[test]
+    fn test_process_inactive_providers_with_future_heartbeat() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        // Heartbeat recorded in the future relative to the processing block
+        let mut future = mock_env();
+        future.block.time = future.block.time.plus_seconds(1000);
+        register_provider(deps.as_mut(), future, "provider");
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("keeper", &[]),
+            ExecuteMsg::ProcessInactiveProviders {},
+        ).unwrap();
+
+        let deactivated = res.attributes.iter()
+            .find(|a| a.key == "deactivated_count")
+            .unwrap()
+            .value
+            .clone();
+        assert_eq!(deactivated, "0");
+    }
+
+    #[test]
+    fn test_job_type_timeout() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let set = ExecuteMsg::SetJobTypeTimeout {
+            job_type: "pi_calculation".to_string(),
+            timeout: Some(86_400),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), set).unwrap();
+
+        // Mapped job type gets the custom deadline
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let job = JOBS.load(&deps.storage, job_id).unwrap();
+        assert_eq!(job.deadline, mock_env().block.time.seconds() + 86_400);
+
+        // Unmapped job type falls back to default_job_timeout
+        let mut offered = vec![];
+        for service_type in ["pi_calculation", "prime_search"] {
+            offered.push(ServiceCapability {
+                service_type: service_type.to_string(),
+                max_complexity: 100000,
+                avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
+            });
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::UpdateProvider {
+                name: None,
+                endpoint: None,
+                pricing: None,
+                capacity: None,
+                payout_address: None,
+                capabilities: Some(offered),
+                auto_accept: None,
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap();
+        let submit = ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: "prime_search".to_string(),
+            parameters: r#"{"quantity":1}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit,
+        ).unwrap();
+        let job_id: u64 = res.attributes.iter()
+            .find(|a| a.key == "job_id")
+            .unwrap()
+            .value
+            .parse()
+            .unwrap();
+        let job = JOBS.load(&deps.storage, job_id).unwrap();
+        assert_eq!(job.deadline, mock_env().block.time.seconds() + 3600);
+
+        let res: JobTypeTimeoutResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetJobTypeTimeout {
+                job_type: "prime_search".to_string(),
+            }).unwrap(),
+        ).unwrap();
+        assert_eq!(res.timeout, 3600);
+        assert!(res.is_default);
+    }
+
+    #[test]
+    fn test_set_job_type_timeout_requires_admin() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let set = ExecuteMsg::SetJobTypeTimeout {
+            job_type: "pi_calculation".to_string(),
+            timeout: Some(86_400),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), set).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+    }
+
+    #[test]
+    fn test_job_ids_start_at_one() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let res: NextJobIdResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetNextJobId {}).unwrap(),
+        ).unwrap();
+        assert_eq!(res.next_job_id, 1);
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        assert_eq!(job_id, 1);
+
+        let res: NextJobIdResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetNextJobId {}).unwrap(),
+        ).unwrap();
+        assert_eq!(res.next_job_id, 2);
+    }
+
+    #[test]
+    fn test_provider_uptime_drops_on_missed_heartbeat() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let uptime = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| -> ProviderUptimeResponse {
+            from_json(
+                query(deps.as_ref(), mock_env(), QueryMsg::GetProviderUptime {
+                    provider: "provider".to_string(),
+                }).unwrap(),
+            ).unwrap()
+        };
+        assert_eq!(uptime(&deps).uptime_percent, Decimal::from_ratio(100u128, 1u128));
+
+        // Two regular heartbeats, one timeout apart
+        let mut env = mock_env();
+        for _ in 0..2 {
+            env.block.time = env.block.time.plus_seconds(300);
+            execute(deps.as_mut(), env.clone(), mock_info("provider", &[]), ExecuteMsg::HeartBeat {})
+                .unwrap();
+        }
+        assert_eq!(uptime(&deps).uptime_percent, Decimal::from_ratio(100u128, 1u128));
+        assert_eq!(uptime(&deps).total_heartbeat_windows, 2);
+
+        // A burst inside the same window earns nothing extra
+        for _ in 0..5 {
+            env.block.time = env.block.time.plus_seconds(10);
+            execute(deps.as_mut(), env.clone(), mock_info("provider", &[]), ExecuteMsg::HeartBeat {})
+                .unwrap();
+        }
+        assert_eq!(uptime(&deps).total_heartbeat_windows, 2);
+
+        // Provider goes silent past the heartbeat timeout
+        env.block.time = env.block.time.plus_seconds(301);
+        execute(deps.as_mut(), env, mock_info("keeper", &[]), ExecuteMsg::ProcessInactiveProviders {})
+            .unwrap();
+
+        let res = uptime(&deps);
+        assert_eq!(res.total_heartbeat_windows, 3);
+        assert_eq!(res.missed_windows, 1);
+        assert_eq!(res.uptime_percent, Decimal::from_ratio(200u128, 3u128));
+    }
+
+    #[test]
+    fn test_job_status_transition_matrix() {
+        use JobStatus::*;
+        let all = [Submitted, Processing, Completed, Failed, Cancelled, Acknowledged, Blocked];
+        let legal = [
+            (Submitted, Processing),
+            (Submitted, Completed),
+            (Submitted, Failed),
+            (Submitted, Cancelled),
+            (Processing, Completed),
+            (Processing, Failed),
+            (Completed, Acknowledged),
+            (Blocked, Submitted),
+            (Blocked, Cancelled),
+        ];
+
+        for from in all.iter() {
+            for to in all.iter() {
+                let expected = legal.iter().any(|(f, t)| f == from && t == to);
+                assert_eq!(
+                    can_transition(from, to),
+                    expected,
+                    "transition {} -> {}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_illegal_transition_rejected() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", job_id);
+
+        // Completed is terminal - failing or cancelling afterwards is illegal
+        let fail = ExecuteMsg::FailJob { job_id, reason: "late".to_string(), refund_percent: None };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), fail).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::IllegalTransition { ref from, ref to }
+                if from == "completed" && to == "failed"
+        ));
+
+        let cancel = ExecuteMsg::CancelJob { job_id, reason: None };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), cancel).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::IllegalTransition { ref from, ref to }
+                if from == "completed" && to == "cancelled"
+        ));
+    }
+
+    #[test]
+    fn test_solvency_tracks_outstanding_jobs() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job1 = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let job2 = submit_job(deps.as_mut(), mock_env(), "client", "provider", 400_000);
+        let job3 = submit_job(deps.as_mut(), mock_env(), "client", "provider", 250_000);
+
+        // Job 1 is paid out, job 2 refunded - only job 3 remains owed
+        complete_job(deps.as_mut(), mock_env(), "provider", job1);
+        let fail = ExecuteMsg::FailJob { job_id: job2, reason: "oom".to_string(), refund_percent: None };
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), fail).unwrap();
+
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(250_000, "umedas"));
+
+        let res: SolvencyResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetSolvency {}).unwrap(),
+        ).unwrap();
+        let outstanding = JOBS.load(&deps.storage, job3).unwrap().payment_amount;
+        assert_eq!(res.obligations, outstanding);
+        assert_eq!(res.balance.u128(), 250_000);
+        assert!(res.solvent);
+    }
+
+    #[test]
+    fn test_submit_job_with_fallback() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider1");
+        register_provider(deps.as_mut(), mock_env(), "provider2");
+
+        // Fill provider1 to capacity
+        let update = ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: None,
+            capacity: Some(1),
+            payout_address: None,
+            capabilities: None,
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider1", &[]), update).unwrap();
+        submit_job(deps.as_mut(), mock_env(), "client", "provider1", 1_000_000);
+
+        let submit = ExecuteMsg::SubmitJobWithFallback {
+            providers: vec!["provider1".to_string(), "provider2".to_string()],
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            denom: "umedas".to_string(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit,
+        ).unwrap();
+
+        let provider = res.attributes.iter().find(|a| a.key == "provider").unwrap();
+        assert_eq!(provider.value, "provider2");
+        let job_id: u64 = res.attributes.iter()
+            .find(|a| a.key == "job_id")
+            .unwrap()
+            .value
+            .parse()
+            .unwrap();
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().provider.as_str(), "provider2");
+    }
+
+    #[test]
+    fn test_submit_job_with_fallback_none_qualify() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider1");
+
+        // provider1 doesn't offer the job type, provider2 isn't registered
+        let submit = ExecuteMsg::SubmitJobWithFallback {
+            providers: vec!["provider1".to_string(), "provider2".to_string()],
+            job_type: "prime_search".to_string(),
+            parameters: r#"{"quantity":1}"#.to_string(),
+            denom: "umedas".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit,
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::NoQualifiedProvider {}));
+    }
+
+    #[test]
+    fn test_heartbeat_not_required() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = config_update! { require_heartbeat: Some(false) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        // A week without heartbeats
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(7 * 86_400);
+        execute(deps.as_mut(), env, mock_info("keeper", &[]), ExecuteMsg::ProcessInactiveProviders {})
+            .unwrap();
+
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert!(provider.active);
+    }
+
+    #[test]
+    fn test_payout_address_routing() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let provider_recipient = |res: &cosmwasm_std::Response| -> String {
+            match &res.messages.last().unwrap().msg {
+                CosmosMsg::Bank(BankMsg::Send { to_address, .. }) => {
+                    to_address.clone()
+                }
+                other => panic!("unexpected message {:?}", other),
+            }
+        };
+
+        // Defaults to the provider key
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let complete = ExecuteMsg::CompleteJob {
+            job_id,
+            result_hash: "a".to_string(),
+            result_url: "a".to_string(),
+            encryption: None,
+            result_size_bytes: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), complete).unwrap();
+        assert_eq!(provider_recipient(&res), "provider");
+
+        // Routed to the cold wallet once configured
+        let update = ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: None,
+            capacity: None,
+            payout_address: Some("cold_wallet".to_string()),
+            capabilities: None,
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), update).unwrap();
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let complete = ExecuteMsg::CompleteJob {
+            job_id,
+            result_hash: "b".to_string(),
+            result_url: "b".to_string(),
+            encryption: None,
+            result_size_bytes: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), complete).unwrap();
+        assert_eq!(provider_recipient(&res), "cold_wallet");
+    }
+
+    #[test]
+    fn test_complete_job_with_multiple_results() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let results = vec![
+            JobResult {
+                hash: "h1".to_string(),
+                url: "https://result.test/digits.txt".to_string(),
+                content_type: "text/plain".to_string(),
+            },
+            JobResult {
+                hash: "h2".to_string(),
+                url: "https://result.test/summary.json".to_string(),
+                content_type: "application/json".to_string(),
+            },
+            JobResult {
+                hash: "h3".to_string(),
+                url: "https://result.test/log.gz".to_string(),
+                content_type: "application/gzip".to_string(),
+            },
+        ];
+        let complete = ExecuteMsg::CompleteJobMulti { job_id, results: results.clone() };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), complete).unwrap();
+        assert_eq!(res.messages.len(), 2);
+
+        let job: JobResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id }).unwrap(),
+        ).unwrap();
+        assert_eq!(job.status, "completed");
+        assert_eq!(job.results, results);
+        assert_eq!(job.result_hash, Some("h1".to_string()));
+    }
+
+    #[test]
+    fn test_complete_job_multi_requires_results() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let complete = ExecuteMsg::CompleteJobMulti { job_id, results: vec![] };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), complete).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::NoResults {}));
+    }
+
+    #[test]
+    fn test_accept_job_moves_to_processing() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[]),
+            ExecuteMsg::AcceptJob { job_id },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), ExecuteMsg::AcceptJob { job_id })
+            .unwrap();
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().status, JobStatus::Processing);
+
+        complete_job(deps.as_mut(), mock_env(), "provider", job_id);
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().status, JobStatus::Completed);
+    }
+
+    #[test]
+    fn test_fallback_provider_exclusive_window() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider1");
+        register_provider(deps.as_mut(), mock_env(), "provider2");
+
+        let submit = ExecuteMsg::SubmitJobWithFallback {
+            providers: vec!["provider1".to_string(), "provider2".to_string()],
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            denom: "umedas".to_string(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit,
+        ).unwrap();
+        let job_id: u64 = res.attributes.iter()
+            .find(|a| a.key == "job_id")
+            .unwrap()
+            .value
+            .parse()
+            .unwrap();
+
+        // provider2 must wait for provider1's exclusive window
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider2", &[]),
+            ExecuteMsg::AcceptJob { job_id },
+        ).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::ExclusiveWindowActive { .. }
+        ));
+
+        // After the window lapses provider2 takes the job over
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(300);
+        execute(deps.as_mut(), env, mock_info("provider2", &[]), ExecuteMsg::AcceptJob { job_id })
+            .unwrap();
+
+        let job = JOBS.load(&deps.storage, job_id).unwrap();
+        assert_eq!(job.provider.as_str(), "provider2");
+        assert_eq!(job.preferred_provider, Some(Addr::unchecked("provider1")));
+        assert_eq!(job.status, JobStatus::Processing);
+        assert_eq!(PROVIDERS.load(&deps.storage, &Addr::unchecked("provider1")).unwrap().active_jobs, 0);
+        assert_eq!(PROVIDERS.load(&deps.storage, &Addr::unchecked("provider2")).unwrap().active_jobs, 1);
+
+        complete_job(deps.as_mut(), mock_env(), "provider2", job_id);
+    }
+
+    #[test]
+    fn test_update_provider_zero_capacity_rejected() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: None,
+            capacity: Some(0),
+            payout_address: None,
+            capabilities: None,
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), update).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidCapacity {}));
+
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(provider.capacity, 10);
+    }
+
+    #[test]
+    fn test_cancel_without_fee_refunds_in_full() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJob { job_id, reason: None },
+        ).unwrap();
+
+        assert_eq!(res.messages.len(), 1);
+        let refund = res.attributes.iter().find(|a| a.key == "refund_amount").unwrap();
+        assert_eq!(refund.value, "1000000");
+    }
+
+    #[test]
+    fn test_cancel_fee_split_between_client_and_provider() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = config_update! { cancel_fee_percent: Some(10) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJob { job_id, reason: None },
+        ).unwrap();
+
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "provider".to_string(),
+                amount: coins(100_000, "umedas"),
+            })
+        );
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "client".to_string(),
+                amount: coins(900_000, "umedas"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_submit_job_to_malformed_provider_address() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let submit = ExecuteMsg::SubmitJob {
+            provider: "Not A Valid Address".to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit,
+        ).unwrap_err();
+
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::InvalidProviderAddress { ref address, .. }
+                if address == "Not A Valid Address"
+        ));
+    }
+
+    #[test]
+    fn test_register_providers_batch() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let batch = ExecuteMsg::RegisterProvidersBatch {
+            providers: vec![
+                pi_registration("node1"),
+                pi_registration("node2"),
+                pi_registration("node3"),
+            ],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), batch).unwrap();
+        let count = res.attributes.iter().find(|a| a.key == "registered_count").unwrap();
+        assert_eq!(count.value, "3");
+
+        for node in ["node1", "node2", "node3"] {
+            let provider: ProviderResponse = from_json(
+                query(deps.as_ref(), mock_env(), QueryMsg::GetProvider {
+                    address: node.to_string(),
+                }).unwrap(),
+            ).unwrap();
+            assert_eq!(provider.address, node);
+            assert_eq!(provider.endpoint, format!("https://{}.fleet.test", node));
+            assert!(provider.active);
+        }
+    }
+
+    #[test]
+    fn test_register_providers_batch_requires_admin() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let batch = ExecuteMsg::RegisterProvidersBatch {
+            providers: vec![pi_registration("node1")],
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("node1", &[]), batch).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+    }
+
+    #[test]
+    fn test_market_price_statistics() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let mut batch = vec![];
+        for (node, price) in [("node1", 1u64), ("node2", 2), ("node3", 6)] {
+            let mut registration = pi_registration(node);
+            registration.pricing.get_mut("pi_calculation").unwrap().base_price =
+                Decimal::percent(price);
+            batch.push(registration);
+        }
+        // A provider for another service doesn't affect pi_calculation pricing
+        let mut other = pi_registration("node4");
+        other.pricing = HashMap::from([("prime_search".to_string(), PricingTier {
+            base_price: Decimal::percent(50),
+            unit: "number".to_string(),
+        })]);
+        batch.push(other);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::RegisterProvidersBatch { providers: batch },
+        ).unwrap();
+
+        let res: MarketPriceResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetMarketPrice {
+                job_type: "pi_calculation".to_string(),
+            }).unwrap(),
+        ).unwrap();
+
+        assert_eq!(res.provider_count, 3);
+        assert_eq!(res.min_price, Decimal::percent(1));
+        assert_eq!(res.max_price, Decimal::percent(6));
+        assert_eq!(res.avg_price, Decimal::percent(3));
+    }
+
+    fn capabilities(count: usize) -> Vec<ServiceCapability> {
+        (0..count)
+            .map(|i| ServiceCapability {
+                service_type: format!("service_{}", i),
+                max_complexity: 1000,
+                avg_completion_time: 60,
+                required_parameters: vec![],
+                enabled: true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_max_capabilities_on_register() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let update = config_update! { max_capabilities: Some(3) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        // Exactly at the limit
+        let register = ExecuteMsg::RegisterProvider {
+            name: "Provider".to_string(),
+            capabilities: capabilities(3),
+            pricing: HashMap::new(),
+            endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider1", &[]), register).unwrap();
+
+        // One over
+        let register = ExecuteMsg::RegisterProvider {
+            name: "Provider".to_string(),
+            capabilities: capabilities(4),
+            pricing: HashMap::new(),
+            endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider2", &[]), register).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::TooManyCapabilities { max: 3 }));
+    }
+
+    #[test]
+    fn test_max_capabilities_on_update() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = |count: usize| ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: None,
+            capacity: None,
+            payout_address: None,
+            capabilities: Some(capabilities(count)),
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), update(20)).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), update(21))
+            .unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::TooManyCapabilities { max: 20 }));
+    }
+
+    #[test]
+    fn test_acknowledge_result() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        // Nothing to acknowledge before completion
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::AcknowledgeResult { job_id },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::IllegalTransition { .. }));
+
+        complete_job(deps.as_mut(), mock_env(), "provider", job_id);
+
+        // Only the client may acknowledge
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::AcknowledgeResult { job_id },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::AcknowledgeResult { job_id },
+        ).unwrap();
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().status, JobStatus::Acknowledged);
+
+        // Obligations were released once, at completion
+        let res: SolvencyResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetSolvency {}).unwrap(),
+        ).unwrap();
+        assert!(res.obligations.is_zero());
+    }
+
+    #[test]
+    fn test_reputation_recovers_after_recent_successes() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        // Early bad streak
+        for _ in 0..5 {
+            let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("provider", &[]),
+                ExecuteMsg::FailJob { job_id, reason: "crashed".to_string(), refund_percent: None },
+            ).unwrap();
+        }
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(provider.reputation, Decimal::zero());
+
+        // Provider improves and fills the recent window with successes
+        for _ in 0..RECENT_OUTCOMES_WINDOW {
+            let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+            complete_job(deps.as_mut(), mock_env(), "provider", job_id);
+        }
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(provider.recent_outcomes.len(), RECENT_OUTCOMES_WINDOW);
+        assert!(provider.recent_outcomes.iter().all(|ok| *ok));
+
+        // Lifetime rate is 66%, but recent performance dominates: 0.3 * 66 + 0.7 * 100
+        assert_eq!(provider.reputation, Decimal::from_ratio(8980u128, 100u128));
+    }
+
+    #[test]
+    fn test_list_jobs_by_provider_and_status() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        register_provider(deps.as_mut(), mock_env(), "other");
+
+        let completed = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", completed);
+        let mut failed = vec![];
+        for _ in 0..3 {
+            let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("provider", &[]),
+                ExecuteMsg::FailJob { job_id, reason: "crashed".to_string(), refund_percent: None },
+            ).unwrap();
+            failed.push(job_id);
+        }
+        let submitted = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        // Another provider's failed job must not show up
+        let other = submit_job(deps.as_mut(), mock_env(), "client", "other", 1_000_000);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other", &[]),
+            ExecuteMsg::FailJob { job_id: other, reason: "crashed".to_string(), refund_percent: None },
+        ).unwrap();
+
+        let list = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, status: &str, start_after, limit| {
+            let res: JobsResponse = from_json(
+                query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::ListJobsByProviderAndStatus {
+                        provider: "provider".to_string(),
+                        status: status.to_string(),
+                        start_after,
+                        limit,
+                    },
+                ).unwrap(),
+            ).unwrap();
+            res.jobs.into_iter().map(|job| job.id).collect::<Vec<_>>()
+        };
+
+        assert_eq!(list(&deps, "failed", None, None), failed);
+        assert_eq!(list(&deps, "completed", None, None), vec![completed]);
+        assert_eq!(list(&deps, "submitted", None, None), vec![submitted]);
+        assert!(list(&deps, "cancelled", None, None).is_empty());
+
+        // Pagination continues past non-matching jobs
+        assert_eq!(list(&deps, "failed", None, Some(2)), failed[..2].to_vec());
+        assert_eq!(list(&deps, "failed", Some(failed[1]), Some(2)), failed[2..].to_vec());
+
+        // A full page hands back the last job scanned; an exhausted index hands back none
+        let page = |status: &str, limit| {
+            let res: JobsResponse = from_json(
+                query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::ListJobsByProviderAndStatus {
+                        provider: "provider".to_string(),
+                        status: status.to_string(),
+                        start_after: None,
+                        limit: Some(limit),
+                    },
+                ).unwrap(),
+            ).unwrap();
+            res.next_start_after
+        };
+        assert_eq!(page("failed", 2), Some(failed[1]));
+        assert_eq!(page("failed", 10), None);
+
+        // Unknown statuses are rejected rather than silently matching nothing
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ListJobsByProviderAndStatus {
+                provider: "provider".to_string(),
+                status: "Failed".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        ).unwrap_err();
+    }
+
+    #[test]
+    fn test_query_pause_blocks_data_queries() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        let update = |query_paused| config_update! { query_paused: Some(query_paused) };
+
+        // Only the admin can flip the switch
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), update(true)).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update(true)).unwrap();
+
+        let blocked = [
+            QueryMsg::GetJob { job_id },
+            QueryMsg::GetProvider { address: "provider".to_string() },
+            QueryMsg::ListJobsByClient { client: "client".to_string(), start_after: None, limit: None },
+            QueryMsg::GetConfig {},
+        ];
+        for msg in blocked.clone() {
+            let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+            assert!(err.to_string().contains("Queries are paused"));
+        }
+
+        let health: HealthResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetHealth {}).unwrap(),
+        ).unwrap();
+        assert!(health.query_paused);
+        assert!(!health.paused);
+        assert_eq!(health.contract, "crates.io:medas-computing-contract");
+
+        // Lifting the switch restores normal reads
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update(false)).unwrap();
+        for msg in blocked {
+            query(deps.as_ref(), mock_env(), msg).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_registration_fee() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        // Zero fee: registration stays free and nothing is forwarded
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("free", &[]),
+            ExecuteMsg::RegisterProvider {
+                name: "Free".to_string(),
+                capabilities: capabilities(1),
+                pricing: HashMap::new(),
+                endpoint: "https://free.example".to_string(),
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap();
+        assert!(res.messages.is_empty());
+
+        let update = config_update! { registration_fee: Some(Uint128::new(5_000_000)) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let register = ExecuteMsg::RegisterProvider {
+            name: "Provider".to_string(),
+            capabilities: capabilities(1),
+            pricing: HashMap::new(),
+            endpoint: "https://provider.example".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
+        };
+
+        // Underpayment is rejected
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &coins(4_999_999, "umedas")),
+            register.clone(),
+        ).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::RegistrationFeeRequired { expected } if expected == Uint128::new(5_000_000)
+        ));
+        assert!(!PROVIDERS.has(&deps.storage, &Addr::unchecked("provider")));
+
+        // Sufficient payment registers and forwards the fee to the community pool
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &coins(5_000_000, "umedas")),
+            register,
+        ).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "medas1community...".to_string(),
+                amount: coins(5_000_000, "umedas"),
+            })
+        );
+        assert!(PROVIDERS.has(&deps.storage, &Addr::unchecked("provider")));
+    }
+
+    #[test]
+    fn test_reputation_snapshot_at_submit() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let first = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let second = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let initial = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap().reputation;
+
+        // A later job changes the provider's reputation...
+        complete_job(deps.as_mut(), mock_env(), "provider", second);
+        let current = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap().reputation;
+        assert_ne!(current, initial);
+
+        // ...but not the snapshot taken when the earlier job was submitted
+        let job: JobResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id: first }).unwrap(),
+        ).unwrap();
+        assert_eq!(job.provider_reputation_at_submit, initial);
+
+        let third = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let job: JobResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id: third }).unwrap(),
+        ).unwrap();
+        assert_eq!(job.provider_reputation_at_submit, current);
+    }
+
+    #[test]
+    fn test_payout_rounding_modes() {
+        // (payment, mode, expected community fee) with a 15% community fee
+        let cases = [
+            // 150_000.45
+            (1_000_003u128, RoundingMode::TowardProvider, 150_000u128),
+            (1_000_003, RoundingMode::TowardCommunity, 150_001),
+            (1_000_003, RoundingMode::Banker, 150_000),
+            // 150_000.6
+            (1_000_004, RoundingMode::Banker, 150_001),
+            // Exact halves go to the even neighbour: 1.5 -> 2, 4.5 -> 4
+            (10, RoundingMode::Banker, 2),
+            (30, RoundingMode::Banker, 4),
+            (10, RoundingMode::TowardProvider, 1),
+            (30, RoundingMode::TowardCommunity, 5),
+        ];
+
+        for (payment, mode, expected_fee) in cases {
+            let mut deps = mock_dependencies();
+            setup_contract(deps.as_mut());
+            register_provider(deps.as_mut(), mock_env(), "provider");
+
+            let update = config_update! { rounding_mode: Some(mode.clone()) };
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+            let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", payment);
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("provider", &[]),
+                ExecuteMsg::CompleteJob {
+                    job_id,
+                    result_hash: "hash".to_string(),
+                    result_url: "https://results.example/1".to_string(),
+                    encryption: None,
+                    result_size_bytes: None,
+                },
+            ).unwrap();
+
+            let attr = |key: &str| -> u128 {
+                res.attributes.iter().find(|a| a.key == key).unwrap().value.parse().unwrap()
+            };
+            assert_eq!(attr("community_fee"), expected_fee, "{:?} on {}", mode, payment);
+            assert_eq!(attr("community_fee") + attr("provider_payment"), payment);
+        }
+    }
+
+    #[test]
+    fn test_preview_provider_selection_matches_auto_submit() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        for addr in ["busy", "idle", "trusted"] {
+            register_provider(deps.as_mut(), mock_env(), addr);
+        }
+
+        // "trusted" gets the best reputation, "busy" the highest utilization
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "trusted", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "trusted", job_id);
+        submit_job(deps.as_mut(), mock_env(), "client", "busy", 1_000_000);
+        submit_job(deps.as_mut(), mock_env(), "client", "busy", 1_000_000);
+        submit_job(deps.as_mut(), mock_env(), "client", "trusted", 1_000_000);
+
+        for (strategy, expected) in [
+            (SelectionStrategy::HighestReputation, "trusted"),
+            (SelectionStrategy::LowestUtilization, "idle"),
+        ] {
+            let next_before = NEXT_JOB_ID.load(&deps.storage).unwrap();
+            let preview: ProviderSelectionResponse = from_json(
+                query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::PreviewProviderSelection {
+                        job_type: "pi_calculation".to_string(),
+                        parameters: r#"{"digits":10000}"#.to_string(),
+                        strategy: strategy.clone(),
+                    },
+                ).unwrap(),
+            ).unwrap();
+            assert_eq!(preview.provider, Some(expected.to_string()));
+            // 10000 digits at 0.01 each, the same price submit checks against max_price
+            assert_eq!(preview.estimated_cost, Some(Uint128::new(100)));
+            // The preview is a dry run
+            assert_eq!(NEXT_JOB_ID.load(&deps.storage).unwrap(), next_before);
+
+            let job_id = NEXT_JOB_ID.load(&deps.storage).unwrap();
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("client", &coins(1_000_000, "umedas")),
+                ExecuteMsg::AutoSubmitJob {
+                    job_type: "pi_calculation".to_string(),
+                    parameters: r#"{"digits":10000}"#.to_string(),
+                    strategy: strategy.clone(),
+                    denom: "umedas".to_string(),
+                },
+            ).unwrap();
+            assert!(res.attributes.iter().any(|a| a.key == "auto_selected"));
+            let job = JOBS.load(&deps.storage, job_id).unwrap();
+            assert_eq!(job.provider, Addr::unchecked(expected));
+        }
+
+        // Nobody offers the job type
+        let preview: ProviderSelectionResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::PreviewProviderSelection {
+                    job_type: "rendering".to_string(),
+                    parameters: r#"{"quantity":1}"#.to_string(),
+                    strategy: SelectionStrategy::HighestReputation,
+                },
+            ).unwrap(),
+        ).unwrap();
+        assert_eq!(preview.provider, None);
+        assert_eq!(preview.estimated_cost, None);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::AutoSubmitJob {
+                job_type: "rendering".to_string(),
+                parameters: r#"{"quantity":1}"#.to_string(),
+                strategy: SelectionStrategy::HighestReputation,
+                denom: "umedas".to_string(),
+            },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::NoQualifiedProvider {}));
+    }
+
+    #[test]
+    fn test_cancel_reason_persisted() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        // Overlong reasons are rejected
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJob { job_id, reason: Some("x".repeat(257)) },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ReasonTooLong { max: 256 }));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJob { job_id, reason: Some("submitted wrong parameters".to_string()) },
+        ).unwrap();
+
+        let job: JobResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id }).unwrap(),
+        ).unwrap();
+        assert_eq!(job.status, "cancelled");
+        assert_eq!(job.cancel_reason, Some("submitted wrong parameters".to_string()));
+    }
+
+    #[test]
+    fn test_max_providers_per_service() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let update = config_update! { max_providers_per_service: Some(2) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        // Fill both pi_calculation slots
+        register_provider(deps.as_mut(), mock_env(), "provider1");
+        register_provider(deps.as_mut(), mock_env(), "provider2");
+
+        let register = ExecuteMsg::RegisterProvider {
+            name: "Provider".to_string(),
+            capabilities: vec![ServiceCapability {
+                service_type: "pi_calculation".to_string(),
+                max_complexity: 1000,
+                avg_completion_time: 60,
+                required_parameters: vec![],
+                enabled: true,
+            }],
+            pricing: HashMap::new(),
+            endpoint: "https://provider.example".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider3", &[]), register).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::ServiceSlotsFull { service_type } if service_type == "pi_calculation"
+        ));
+
+        // Adding the service later through an update is capped too
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider3", &[]),
+            ExecuteMsg::RegisterProvider {
+                name: "Provider".to_string(),
+                capabilities: capabilities(1),
+                pricing: HashMap::new(),
+                endpoint: "https://provider.example".to_string(),
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap();
+        let mut caps = capabilities(1);
+        caps.push(ServiceCapability {
+            service_type: "pi_calculation".to_string(),
+            max_complexity: 1000,
+            avg_completion_time: 60,
+            required_parameters: vec![],
+            enabled: true,
+        });
+        let add_pi = ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: None,
+            capacity: None,
+            payout_address: None,
+            capabilities: Some(caps),
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider3", &[]), add_pi.clone()).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ServiceSlotsFull { .. }));
+
+        // A holder re-advertising its service keeps its slot
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider2", &[]),
+            ExecuteMsg::UpdateProvider {
+                name: Some("Renamed".to_string()),
+                endpoint: None,
+                pricing: None,
+                capacity: None,
+                payout_address: None,
+                capabilities: Some(vec![ServiceCapability {
+                    service_type: "pi_calculation".to_string(),
+                    max_complexity: 5000,
+                    avg_completion_time: 30,
+                    required_parameters: vec![],
+                    enabled: true,
+                }]),
+                auto_accept: None,
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap();
+
+        // Dropping the service frees a slot for a newcomer
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider1", &[]),
+            ExecuteMsg::UpdateProvider {
+                name: None,
+                endpoint: None,
+                pricing: None,
+                capacity: None,
+                payout_address: None,
+                capabilities: Some(capabilities(1)),
+                auto_accept: None,
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("provider3", &[]), add_pi).unwrap();
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        use medas_computing_contract::ContractError;
+
+        // Valid input, other fields are ignored
+        assert_eq!(
+            parse_quantity(r#"{"digits":10000,"method":"chudnovsky"}"#, "pi_calculation").unwrap(),
+            10000
+        );
+        assert_eq!(parse_quantity(r#"{"quantity":3}"#, "rendering").unwrap(), 3);
+
+        // Missing field
+        let err = parse_quantity(r#"{"method":"chudnovsky"}"#, "pi_calculation").unwrap_err();
+        assert!(matches!(err, ContractError::InvalidJobParameters { reason } if reason.contains("digits")));
+        let err = parse_quantity(r#"{"digits":10000}"#, "rendering").unwrap_err();
+        assert!(matches!(err, ContractError::InvalidJobParameters { reason } if reason.contains("quantity")));
+
+        // Non-numeric and zero values
+        let err = parse_quantity(r#"{"digits":"lots"}"#, "pi_calculation").unwrap_err();
+        assert!(matches!(err, ContractError::InvalidJobParameters { .. }));
+        let err = parse_quantity(r#"{"digits":-5}"#, "pi_calculation").unwrap_err();
+        assert!(matches!(err, ContractError::InvalidJobParameters { .. }));
+        let err = parse_quantity(r#"{"digits":0}"#, "pi_calculation").unwrap_err();
+        assert!(matches!(err, ContractError::InvalidJobParameters { .. }));
+
+        // Completely malformed input
+        for input in ["", "not json", "{\"digits\":", "[1,2,3]"] {
+            let err = parse_quantity(input, "pi_calculation").unwrap_err();
+            assert!(matches!(err, ContractError::InvalidJobParameters { .. }), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_quantity_only_required_for_priced_services() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        // Rendering is offered but not priced per unit
+        let mut pricing = HashMap::new();
+        pricing.insert("pi_calculation".to_string(), PricingTier {
+            base_price: Decimal::percent(1),
+            unit: "digit".to_string(),
+        });
+        let capability = |service_type: &str| ServiceCapability {
+            service_type: service_type.to_string(),
+            max_complexity: 100000,
+            avg_completion_time: 180,
+            required_parameters: vec![],
+            enabled: true,
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::RegisterProvider {
+                name: "Provider".to_string(),
+                capabilities: vec![capability("pi_calculation"), capability("rendering")],
+                pricing,
+                endpoint: "https://test.com".to_string(),
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap();
+
+        let submit = |job_type: &str, parameters: &str| ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: job_type.to_string(),
+            parameters: parameters.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let funds = coins(1_000_000, "umedas");
+
+        // The unpriced service runs as a single unit without a quantity
+        execute(deps.as_mut(), mock_env(), mock_info("client", &funds), submit("rendering", r#"{"scene":"a"}"#))
+            .unwrap();
+
+        // A quantity it does give is still checked
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &funds), submit("rendering", r#"{"quantity":0}"#))
+            .unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidJobParameters { .. }));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &funds),
+            submit("rendering", r#"{"quantity":200000}"#),
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ComplexityTooHigh { .. }));
+
+        // The per-digit service still needs its digits
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &funds), submit("pi_calculation", "{}"))
+            .unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidJobParameters { reason } if reason.contains("digits")));
+    }
+
+    #[test]
+    fn test_keeper_work() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        let start = mock_env();
+
+        // Stale heartbeat: registered long before the others
+        let mut early = mock_env();
+        early.block.time = start.block.time.minus_seconds(4_000);
+        register_provider(deps.as_mut(), early.clone(), "stale");
+        register_provider(deps.as_mut(), start.clone(), "provider");
+
+        // Two jobs time out, one is close to its deadline, one is far off,
+        // and a completed job is no longer tracked
+        submit_job(deps.as_mut(), early.clone(), "client", "stale", 1_000_000);
+        submit_job(deps.as_mut(), early.clone(), "client", "provider", 1_000_000);
+        let done = submit_job(deps.as_mut(), early, "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), start.clone(), "provider", done);
+        let mut nearly = start.clone();
+        nearly.block.time = start.block.time.minus_seconds(3_300);
+        submit_job(deps.as_mut(), nearly, "client", "provider", 1_000_000);
+        submit_job(deps.as_mut(), start.clone(), "client", "provider", 1_000_000);
+
+        let work: KeeperWorkResponse = from_json(
+            query(deps.as_ref(), start.clone(), QueryMsg::GetKeeperWork {}).unwrap(),
+        ).unwrap();
+        assert_eq!(work.timed_out_job_count, 2);
+        assert_eq!(work.expiring_job_count, 1);
+        assert_eq!(work.inactive_provider_count, 1);
+        assert!(!work.has_more);
+
+        // After the keeper runs, nothing is left to do
+        execute(deps.as_mut(), start.clone(), mock_info("keeper", &[]), ExecuteMsg::ProcessTimedOutJobs { limit: None }).unwrap();
+        execute(deps.as_mut(), start.clone(), mock_info("keeper", &[]), ExecuteMsg::ProcessInactiveProviders {}).unwrap();
+        let work: KeeperWorkResponse = from_json(
+            query(deps.as_ref(), start, QueryMsg::GetKeeperWork {}).unwrap(),
+        ).unwrap();
+        assert_eq!(work.timed_out_job_count, 0);
+        assert_eq!(work.expiring_job_count, 1);
+        assert_eq!(work.inactive_provider_count, 0);
+    }
+
+    #[test]
+    fn test_keeper_work_count_is_capped() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        for _ in 0..501 {
+            submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        }
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(3_601);
+        let work: KeeperWorkResponse = from_json(
+            query(deps.as_ref(), later.clone(), QueryMsg::GetKeeperWork {}).unwrap(),
+        ).unwrap();
+        assert_eq!(work.timed_out_job_count, 500);
+        assert!(work.has_more);
+
+        // Once the backlog is under the cap the count is exact again
+        execute(deps.as_mut(), later.clone(), mock_info("keeper", &[]), ExecuteMsg::ProcessTimedOutJobs { limit: Some(2) })
+            .unwrap();
+        let work: KeeperWorkResponse = from_json(
+            query(deps.as_ref(), later, QueryMsg::GetKeeperWork {}).unwrap(),
+        ).unwrap();
+        assert_eq!(work.timed_out_job_count, 499);
+        assert!(!work.has_more);
+    }
+
+    #[test]
+    fn test_result_encryption_metadata() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let encryption = EncryptionInfo {
+            scheme: "x25519-xsalsa20-poly1305".to_string(),
+            pubkey_hint: Some("client-key-1".to_string()),
+        };
+        let encrypted = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::CompleteJob {
+                job_id: encrypted,
+                result_hash: "hash".to_string(),
+                result_url: "https://results.example/sealed".to_string(),
+                encryption: Some(encryption.clone()),
+                result_size_bytes: None,
+            },
+        ).unwrap();
+
+        let plain = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", plain);
+
+        let job: JobResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id: encrypted }).unwrap(),
+        ).unwrap();
+        assert_eq!(job.encryption, Some(encryption));
+
+        let job: JobResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id: plain }).unwrap(),
+        ).unwrap();
+        assert_eq!(job.encryption, None);
+    }
+
+    #[test]
+    fn test_auto_accept_provider() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "manual");
+        register_provider(deps.as_mut(), mock_env(), "automated");
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("automated", &[]),
+            ExecuteMsg::UpdateProvider {
+                name: None,
+                endpoint: None,
+                pricing: None,
+                capacity: None,
+                payout_address: None,
+                capabilities: None,
+                auto_accept: Some(true),
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap();
+
+        let manual_job = submit_job(deps.as_mut(), mock_env(), "client", "manual", 1_000_000);
+        let auto_job = submit_job(deps.as_mut(), mock_env(), "client", "automated", 1_000_000);
+
+        assert_eq!(JOBS.load(&deps.storage, manual_job).unwrap().status, JobStatus::Submitted);
+        assert_eq!(JOBS.load(&deps.storage, auto_job).unwrap().status, JobStatus::Processing);
+
+        // Already accepted - a manual AcceptJob is an illegal transition
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("automated", &[]),
+            ExecuteMsg::AcceptJob { job_id: auto_job },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::IllegalTransition { .. }));
+
+        // And it completes as usual
+        complete_job(deps.as_mut(), mock_env(), "automated", auto_job);
+        assert_eq!(JOBS.load(&deps.storage, auto_job).unwrap().status, JobStatus::Completed);
+    }
+
+    #[test]
+    fn test_lowered_max_complexity_applies_to_new_jobs() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let submit = |digits: u64| ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: format!(r#"{{"digits":{}}}"#, digits),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit(50_000),
+        ).unwrap();
+        let old_job: u64 = res.attributes.iter().find(|a| a.key == "job_id").unwrap().value.parse().unwrap();
+
+        // Above the advertised maximum
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit(200_000),
+        ).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::ComplexityTooHigh { complexity: 200_000, max: 100_000 }
+        ));
+
+        // Provider lowers its limit
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::UpdateProvider {
+                name: None,
+                endpoint: None,
+                pricing: None,
+                capacity: None,
+                payout_address: None,
+                capabilities: Some(vec![ServiceCapability {
+                    service_type: "pi_calculation".to_string(),
+                    max_complexity: 20_000,
+                    avg_completion_time: 180,
+                    required_parameters: vec![],
+                    enabled: true,
+                }]),
+                auto_accept: None,
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap();
+
+        // The previously valid complexity is now rejected for new jobs...
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit(50_000),
+        ).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::ComplexityTooHigh { complexity: 50_000, max: 20_000 }
+        ));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit(20_000),
+        ).unwrap();
+
+        // ...while the grandfathered job completes normally
+        complete_job(deps.as_mut(), mock_env(), "provider", old_job);
+        assert_eq!(JOBS.load(&deps.storage, old_job).unwrap().status, JobStatus::Completed);
+    }
+
+    #[test]
+    fn test_status_distribution() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let distribution = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| -> StatusDistributionResponse {
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetStatusDistribution {}).unwrap()).unwrap()
+        };
+        assert_eq!(distribution(&deps).submitted, 0);
+
+        let jobs: Vec<u64> = (0..5)
+            .map(|_| submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000))
+            .collect();
+        assert_eq!(distribution(&deps).submitted, 5);
+
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), ExecuteMsg::AcceptJob { job_id: jobs[0] }).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), ExecuteMsg::AcceptJob { job_id: jobs[1] }).unwrap();
+        complete_job(deps.as_mut(), mock_env(), "provider", jobs[1]);
+        complete_job(deps.as_mut(), mock_env(), "provider", jobs[2]);
+        execute(deps.as_mut(), mock_env(), mock_info("client", &[]), ExecuteMsg::AcknowledgeResult { job_id: jobs[2] }).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::FailJob { job_id: jobs[3], reason: "crashed".to_string(), refund_percent: None },
+        ).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJob { job_id: jobs[4], reason: None },
+        ).unwrap();
+
+        assert_eq!(
+            distribution(&deps),
+            StatusDistributionResponse {
+                submitted: 0,
+                processing: 1,
+                completed: 1,
+                failed: 1,
+                cancelled: 1,
+                acknowledged: 1,
+                blocked: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_capability_growth_gated_by_reputation() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let update = config_update! {
+            capability_reputation_threshold: Some(Decimal::from_ratio(80u128, 1u128)),
+            low_reputation_max_capabilities: Some(3),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        for addr in ["novice", "veteran"] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(addr, &[]),
+                ExecuteMsg::RegisterProvider {
+                    name: "Provider".to_string(),
+                    capabilities: capabilities(3),
+                    pricing: HashMap::new(),
+                    endpoint: "https://provider.example".to_string(),
+                    refund_guarantee: None,
+                    max_payment: None,
+                },
+            ).unwrap();
+        }
+
+        // The veteran builds a track record
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::SubmitJob {
+                provider: "veteran".to_string(),
+                job_type: "service_0".to_string(),
+                parameters: r#"{"quantity":1}"#.to_string(),
+                referrer: None,
+                depends_on: None,
+                denom: "umedas".to_string(),
+                max_price: None,
+            },
+        ).unwrap();
+        let job_id = res.attributes.iter().find(|a| a.key == "job_id").unwrap().value.parse().unwrap();
+        complete_job(deps.as_mut(), mock_env(), "veteran", job_id);
+
+        let grow = ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: None,
+            capacity: None,
+            payout_address: None,
+            capabilities: Some(capabilities(4)),
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("novice", &[]), grow.clone()).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ReputationTooLow { max: 3 }));
+
+        execute(deps.as_mut(), mock_env(), mock_info("veteran", &[]), grow).unwrap();
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("veteran")).unwrap();
+        assert_eq!(provider.capabilities.len(), 4);
+    }
+
+    #[test]
+    fn test_submit_job_sums_duplicate_denom_coins() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let funds = vec![
+            cosmwasm_std::Coin::new(600_000, "umedas"),
+            cosmwasm_std::Coin::new(400_000, "umedas"),
+        ];
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &funds),
+            ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                referrer: None,
+                depends_on: None,
+                denom: "umedas".to_string(),
+                max_price: None,
+            },
+        ).unwrap();
+        let job_id: u64 = res.attributes.iter().find(|a| a.key == "job_id").unwrap().value.parse().unwrap();
+
+        let job = JOBS.load(&deps.storage, job_id).unwrap();
+        assert_eq!(job.payment_amount, Uint128::new(1_000_000));
+        let res: SolvencyResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetSolvency {}).unwrap(),
+        ).unwrap();
+        assert_eq!(res.obligations, Uint128::new(1_000_000));
+    }
+
+    #[test]
+    fn test_refund_guarantee() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "regular");
+
+        let mut pricing = HashMap::new();
+        pricing.insert("pi_calculation".to_string(), PricingTier {
+            base_price: Decimal::percent(1),
+            unit: "digit".to_string(),
+        });
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("guaranteed", &[]),
+            ExecuteMsg::RegisterProvider {
+                name: "Guaranteed".to_string(),
+                capabilities: vec![ServiceCapability {
+                    service_type: "pi_calculation".to_string(),
+                    max_complexity: 100000,
+                    avg_completion_time: 180,
+                    required_parameters: vec![],
+                    enabled: true,
+                }],
+                pricing,
+                endpoint: "https://guaranteed.example".to_string(),
+                refund_guarantee: Some(true),
+                max_payment: None,
+            },
+        ).unwrap();
+
+        let res: ProviderResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetProvider { address: "guaranteed".to_string() }).unwrap(),
+        ).unwrap();
+        assert!(res.refund_guarantee);
+
+        // A regular provider can keep part of the payment for partial work
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "regular", 1_000_000);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("regular", &[]),
+            ExecuteMsg::FailJob { job_id, reason: "partial".to_string(), refund_percent: Some(60) },
+        ).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "client".to_string(),
+                amount: coins(600_000, "umedas"),
+            })
+        );
+        // 400_000 retained, 15% of it to the community pool
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "medas1community...".to_string(),
+                amount: coins(60_000, "umedas"),
+            })
+        );
+        assert_eq!(
+            res.messages[2].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "regular".to_string(),
+                amount: coins(340_000, "umedas"),
+            })
+        );
+
+        // The guaranteed provider's partial-refund request is ignored
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "guaranteed", 1_000_000);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("guaranteed", &[]),
+            ExecuteMsg::FailJob { job_id, reason: "partial".to_string(), refund_percent: Some(60) },
+        ).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "client".to_string(),
+                amount: coins(1_000_000, "umedas"),
+            })
+        );
+
+        // Out of range percentages are rejected
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "regular", 1_000_000);
+        let failed_before = PROVIDERS.load(&deps.storage, &Addr::unchecked("regular")).unwrap().total_failed;
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("regular", &[]),
+            ExecuteMsg::FailJob { job_id, reason: "partial".to_string(), refund_percent: Some(101) },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidRefundPercent { percent: 101 }));
+        // Rejected before the job or the provider's record is touched
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().status, JobStatus::Submitted);
+        assert_eq!(PROVIDERS.load(&deps.storage, &Addr::unchecked("regular")).unwrap().total_failed, failed_before);
+    }
+
+    #[test]
+    fn test_provider_update_cooldown() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = config_update! { update_cooldown: Some(600) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let rename = |name: &str| ExecuteMsg::UpdateProvider {
+            name: Some(name.to_string()),
+            endpoint: None,
+            pricing: None,
+            capacity: None,
+            payout_address: None,
+            capabilities: None,
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        let start = mock_env();
+        execute(deps.as_mut(), start.clone(), mock_info("provider", &[]), rename("First")).unwrap();
+
+        // Second update inside the cooldown
+        let mut env = start.clone();
+        env.block.time = start.block.time.plus_seconds(599);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("provider", &[]), rename("Second")).unwrap_err();
+        let retry_after = start.block.time.seconds() + 600;
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::UpdateTooFrequent { retry_after: r } if r == retry_after
+        ));
+
+        // Heartbeats are exempt
+        execute(deps.as_mut(), env.clone(), mock_info("provider", &[]), ExecuteMsg::HeartBeat {}).unwrap();
+
+        env.block.time = start.block.time.plus_seconds(600);
+        execute(deps.as_mut(), env, mock_info("provider", &[]), rename("Second")).unwrap();
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(provider.name, "Second");
+    }
+
+    #[test]
+    fn test_provider_rating_breakdown() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let rate = |job_id, rating| ExecuteMsg::RateJob { job_id, rating };
+
+        // Only finished jobs can be rated
+        let pending = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), rate(pending, 5)).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidJobState {}));
+
+        for rating in [5u8, 5, 5, 4, 1] {
+            let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+            complete_job(deps.as_mut(), mock_env(), "provider", job_id);
+            execute(deps.as_mut(), mock_env(), mock_info("client", &[]), rate(job_id, rating)).unwrap();
+
+            // One rating per job, by the client only, within 1-5
+            let err = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), rate(job_id, 3)).unwrap_err();
+            assert!(matches!(err, medas_computing_contract::ContractError::AlreadyRated { .. }));
+        }
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", job_id);
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), rate(job_id, 5)).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), rate(job_id, 6)).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidRating { rating: 6 }));
+
+        let res: ProviderRatingsResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetProviderRatings { provider: "provider".to_string() }).unwrap(),
+        ).unwrap();
+        assert_eq!(
+            (res.rating_1, res.rating_2, res.rating_3, res.rating_4, res.rating_5),
+            (1, 0, 0, 1, 3)
+        );
+        assert_eq!(res.total_ratings, 5);
+        // (1 + 4 + 15) / 5
+        assert_eq!(res.average, Some(Decimal::from_ratio(4u128, 1u128)));
+    }
+
+    fn submit_dependent_job(deps: DepsMut, depends_on: u64) -> u64 {
+        let res = execute(
+            deps,
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                referrer: None,
+                depends_on: Some(depends_on),
+                denom: "umedas".to_string(),
+                max_price: None,
+            },
+        ).unwrap();
+        res.attributes.iter().find(|a| a.key == "job_id").unwrap().value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_dependent_job_promoted_on_completion() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let first = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let second = submit_dependent_job(deps.as_mut(), first);
+        let third = submit_dependent_job(deps.as_mut(), second);
+        assert_eq!(JOBS.load(&deps.storage, second).unwrap().status, JobStatus::Blocked);
+
+        // Not eligible yet
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::AcceptJob { job_id: second },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::IllegalTransition { .. }));
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(1_000);
+        complete_job(deps.as_mut(), later.clone(), "provider", first);
+
+        // Only the direct dependent is promoted, with a fresh deadline
+        let job = JOBS.load(&deps.storage, second).unwrap();
+        assert_eq!(job.status, JobStatus::Submitted);
+        assert_eq!(job.deadline, later.block.time.seconds() + 3600);
+        assert_eq!(JOBS.load(&deps.storage, third).unwrap().status, JobStatus::Blocked);
+
+        complete_job(deps.as_mut(), later, "provider", second);
+        assert_eq!(JOBS.load(&deps.storage, third).unwrap().status, JobStatus::Submitted);
+
+        // Depending on an already completed job starts eligible
+        let fourth = submit_dependent_job(deps.as_mut(), first);
+        assert_eq!(JOBS.load(&deps.storage, fourth).unwrap().status, JobStatus::Submitted);
+    }
+
+    #[test]
+    fn test_dependent_job_promotion_follows_submit_rules() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::UpdateProvider {
+                name: None,
+                endpoint: None,
+                pricing: None,
+                capacity: None,
+                payout_address: None,
+                capabilities: None,
+                auto_accept: Some(true),
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap();
+
+        // Only the client's own jobs can be depended on
+        let first = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("mallory", &coins(1_000_000, "umedas")),
+            ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                referrer: None,
+                depends_on: Some(first),
+                denom: "umedas".to_string(),
+                max_price: None,
+            },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ForeignDependency { job_id } if job_id == first));
+
+        // An auto-accepting provider takes the promoted job straight away
+        let second = submit_dependent_job(deps.as_mut(), first);
+        complete_job(deps.as_mut(), mock_env(), "provider", first);
+        assert_eq!(JOBS.load(&deps.storage, second).unwrap().status, JobStatus::Processing);
+
+        // A client that no longer passes the checks is refunded instead of promoted
+        let third = submit_dependent_job(deps.as_mut(), second);
+        let update = config_update! { require_client_allowlist: Some(true) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::CompleteJob {
+                job_id: second,
+                result_hash: "hash".to_string(),
+                result_url: "https://result.test".to_string(),
+                encryption: None,
+                result_size_bytes: None,
+            },
+        ).unwrap();
+        let job = JOBS.load(&deps.storage, third).unwrap();
+        assert_eq!(job.status, JobStatus::Cancelled);
+        assert!(job.cancel_reason.unwrap().contains("allow-list"));
+        assert!(res.messages.iter().any(|m| m.msg
+            == CosmosMsg::Bank(BankMsg::Send {
+                to_address: "client".to_string(),
+                amount: coins(1_000_000, "umedas"),
+            })));
+    }
+
+    #[test]
+    fn test_dependent_job_cancelled_when_dependency_fails() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let first = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let second = submit_dependent_job(deps.as_mut(), first);
+        let third = submit_dependent_job(deps.as_mut(), second);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::FailJob { job_id: first, reason: "crashed".to_string(), refund_percent: None },
+        ).unwrap();
+
+        // The whole chain is cancelled and every client payment refunded
+        for job_id in [second, third] {
+            let job = JOBS.load(&deps.storage, job_id).unwrap();
+            assert_eq!(job.status, JobStatus::Cancelled);
+            assert!(job.cancel_reason.is_some());
+        }
+        let refunds = res
+            .messages
+            .iter()
+            .filter(|m| {
+                m.msg
+                    == CosmosMsg::Bank(BankMsg::Send {
+                        to_address: "client".to_string(),
+                        amount: coins(1_000_000, "umedas"),
+                    })
+            })
+            .count();
+        assert_eq!(refunds, 3);
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(provider.active_jobs, 0);
+
+        // A failed dependency can't be depended on
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                referrer: None,
+                depends_on: Some(first),
+                denom: "umedas".to_string(),
+                max_price: None,
+            },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidJobState {}));
+    }
+
+    #[test]
+    fn test_global_active_job_cap() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            config_update! { max_total_active_jobs: Some(2) },
+        ).unwrap();
+
+        let first = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        let submit = ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other_client", &coins(1_000_000, "umedas")),
+            submit.clone(),
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::GlobalCapacityReached {}));
+
+        // Completing a job frees its slot
+        complete_job(deps.as_mut(), mock_env(), "provider", first);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other_client", &coins(1_000_000, "umedas")),
+            submit,
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_backfills_deadline_index() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let open = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let done = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", done);
+
+        // Simulate a deployment from before the deadline index existed
+        let deadline = JOBS.load(&deps.storage, open).unwrap().deadline;
+        JOBS_BY_DEADLINE.remove(deps.as_mut().storage, (deadline, open));
+        cw2::set_contract_version(deps.as_mut().storage, "crates.io:medas-computing-contract", "0.1.0").unwrap();
+
+        let msg = MigrateMsg { default_job_timeout: None, heartbeat_timeout: None, admin: None };
+        let res = migrate(deps.as_mut(), mock_env(), msg.clone()).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "from_version" && a.value == "0.1.0"));
+
+        let indexed: Vec<(u64, u64)> = JOBS_BY_DEADLINE
+            .keys(&deps.storage, None, None, cosmwasm_std::Order::Ascending)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(indexed, vec![(deadline, open)]);
+
+        // Migrating again at the same version leaves the index alone
+        JOBS_BY_DEADLINE.remove(deps.as_mut().storage, (deadline, open));
+        migrate(deps.as_mut(), mock_env(), msg).unwrap();
+        assert!(!JOBS_BY_DEADLINE.has(&deps.storage, (deadline, open)));
+    }
+
+    #[test]
+    fn test_refund_ledger_records_each_refund_path() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let failed = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let cancelled = submit_job(deps.as_mut(), mock_env(), "client", "provider", 2_000_000);
+        let timed_out = submit_job(deps.as_mut(), mock_env(), "other_client", "provider", 3_000_000);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::FailJob { job_id: failed, reason: "crashed".to_string(), refund_percent: None },
+        ).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJob { job_id: cancelled, reason: None },
+        ).unwrap();
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(3_601);
+        execute(deps.as_mut(), later.clone(), mock_info("anyone", &[]), ExecuteMsg::ProcessTimedOutJobs { limit: None }).unwrap();
+
+        let res: RefundsResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::ListRefunds { start_after: None, limit: None }).unwrap(),
+        ).unwrap();
+        let entries: Vec<(u64, &str, u128, RefundReason)> = res
+            .refunds
+            .iter()
+            .map(|r| (r.job_id, r.client.as_str(), r.amount.u128(), r.reason.clone()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (failed, "client", 1_000_000, RefundReason::Fail),
+                (cancelled, "client", 2_000_000, RefundReason::Cancel),
+                (timed_out, "other_client", 3_000_000, RefundReason::Timeout),
+            ]
+        );
+        assert_eq!(res.refunds[2].timestamp, later.block.time);
+
+        let res: RefundsResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetRefundsByClient { client: "other_client".to_string(), start_after: None, limit: None },
+            ).unwrap(),
+        ).unwrap();
+        assert_eq!(res.refunds.len(), 1);
+        assert_eq!(res.refunds[0].job_id, timed_out);
+
+        // Paged by refund id
+        let client_refunds = |start_after: Option<u64>| -> Vec<(u64, u64)> {
+            let res: RefundsResponse = from_json(
+                query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::GetRefundsByClient { client: "client".to_string(), start_after, limit: Some(1) },
+                ).unwrap(),
+            ).unwrap();
+            res.refunds.iter().map(|r| (r.id, r.job_id)).collect()
+        };
+        let first = client_refunds(None);
+        assert_eq!(first.iter().map(|(_, job_id)| *job_id).collect::<Vec<_>>(), vec![failed]);
+        let second = client_refunds(Some(first[0].0));
+        assert_eq!(second.iter().map(|(_, job_id)| *job_id).collect::<Vec<_>>(), vec![cancelled]);
+    }
+
+    #[test]
+    fn test_required_parameters_checked_on_submit() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let register = ExecuteMsg::RegisterProvider {
+            name: "Strict".to_string(),
+            capabilities: vec![ServiceCapability {
+                service_type: "pi_calculation".to_string(),
+                max_complexity: 100000,
+                avg_completion_time: 180,
+                required_parameters: vec!["digits".to_string(), "algorithm".to_string()],
+                enabled: true,
+            }],
+            pricing: HashMap::new(),
+            endpoint: "https://strict.test".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), register).unwrap();
+
+        let submit = |parameters: &str| ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: parameters.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit(r#"{"digits":10000,"algorithm":"chudnovsky","extra":[1,2]}"#),
+        ).unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit(r#"{"digits":10000}"#),
+        ).unwrap_err();
+        match err {
+            medas_computing_contract::ContractError::MissingRequiredParameters { missing } => {
+                assert_eq!(missing, "algorithm")
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_result_hash_reuse() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let complete = |job_id: u64, hash: &str| ExecuteMsg::CompleteJob {
+            job_id,
+            result_hash: hash.to_string(),
+            result_url: "https://result.test".to_string(),
+            encryption: None,
+            result_size_bytes: None,
+        };
+
+        // Reuse is allowed until enforcement is switched on
+        let first = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let second = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), complete(first, "hash_a")).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), complete(second, "hash_a")).unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            config_update! { enforce_unique_results: Some(true) },
+        ).unwrap();
+
+        let third = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let fourth = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), complete(third, "hash_b")).unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            complete(fourth, "hash_b"),
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ResultHashReused {}));
+        // Hashes from before enforcement count too
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            complete(fourth, "hash_a"),
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ResultHashReused {}));
+
+        // Another provider may legitimately produce the same result
+        register_provider(deps.as_mut(), mock_env(), "provider2");
+        let fifth = submit_job(deps.as_mut(), mock_env(), "client", "provider2", 1_000_000);
+        execute(deps.as_mut(), mock_env(), mock_info("provider2", &[]), complete(fifth, "hash_b")).unwrap();
+    }
+
+    #[test]
+    fn test_provider_response_failure_count_and_heartbeat() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::FailJob { job_id, reason: "crashed".to_string(), refund_percent: None },
+        ).unwrap();
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(120);
+        execute(deps.as_mut(), later.clone(), mock_info("provider", &[]), ExecuteMsg::HeartBeat {}).unwrap();
+
+        let provider: ProviderResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetProvider { address: "provider".to_string() }).unwrap(),
+        ).unwrap();
+        assert_eq!(provider.total_failed, 1);
+        assert_eq!(provider.last_heartbeat, later.block.time.seconds());
+
+        let res: ProvidersResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::ListProviders { start_after: None, limit: None }).unwrap(),
+        ).unwrap();
+        assert_eq!(res.providers[0].total_failed, 1);
+        assert_eq!(res.providers[0].last_heartbeat, later.block.time.seconds());
+    }
+
+    #[test]
+    fn test_result_deadline_from_acceptance() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            config_update! { result_deadline_buffer: Some(60) },
+        ).unwrap();
+
+        let on_time = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let late = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        assert_eq!(JOBS.load(&deps.storage, on_time).unwrap().result_deadline, None);
+
+        let mut accepted = mock_env();
+        accepted.block.time = accepted.block.time.plus_seconds(100);
+        for job_id in [on_time, late] {
+            execute(deps.as_mut(), accepted.clone(), mock_info("provider", &[]), ExecuteMsg::AcceptJob { job_id }).unwrap();
+        }
+
+        // avg_completion_time of 180 plus the 60 second buffer
+        let deadline = accepted.block.time.seconds() + 240;
+        let job: JobResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id: late }).unwrap(),
+        ).unwrap();
+        assert_eq!(job.result_deadline, Some(deadline));
+
+        let mut at_deadline = accepted.clone();
+        at_deadline.block.time = accepted.block.time.plus_seconds(240);
+        complete_job(deps.as_mut(), at_deadline.clone(), "provider", on_time);
+
+        let mut past = at_deadline;
+        past.block.time = past.block.time.plus_seconds(1);
+        let err = execute(
+            deps.as_mut(),
+            past,
+            mock_info("provider", &[]),
+            ExecuteMsg::CompleteJob {
+                job_id: late,
+                result_hash: "hash".to_string(),
+                result_url: "https://result.test".to_string(),
+                encryption: None,
+                result_size_bytes: None,
+            },
+        ).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::ResultDeadlinePassed { deadline: d } if d == deadline
+        ));
+    }
+
+    #[test]
+    fn test_cancel_jobs_batch_skips_ineligible() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let eligible = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let processing = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let foreign = submit_job(deps.as_mut(), mock_env(), "other_client", "provider", 1_000_000);
+        let also_eligible = submit_job(deps.as_mut(), mock_env(), "client", "provider", 2_000_000);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::AcceptJob { job_id: processing },
+        ).unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJobsBatch { job_ids: vec![eligible, processing, foreign, 999, also_eligible] },
+        ).unwrap();
+
+        let attr = |key: &str| res.attributes.iter().find(|a| a.key == key).unwrap().value.clone();
+        assert_eq!(attr(&format!("job_{}", eligible)), "cancelled");
+        assert!(attr(&format!("job_{}", processing)).starts_with("skipped"));
+        assert!(attr(&format!("job_{}", foreign)).starts_with("skipped"));
+        assert!(attr("job_999").starts_with("skipped"));
+        assert_eq!(attr("cancelled_count"), "2");
+
+        // Each cancellation keeps its own attributes, as a single CancelJob would report them
+        let cancels: Vec<(String, String)> = res
+            .events
+            .iter()
+            .filter(|e| e.ty == "cancel_job")
+            .map(|e| {
+                let get = |key: &str| e.attributes.iter().find(|a| a.key == key).unwrap().value.clone();
+                (get("job_id"), get("refund_amount"))
+            })
+            .collect();
+        assert_eq!(
+            cancels,
+            vec![
+                (eligible.to_string(), "1000000".to_string()),
+                (also_eligible.to_string(), "2000000".to_string()),
+            ]
+        );
+
+        assert_eq!(JOBS.load(&deps.storage, eligible).unwrap().status, JobStatus::Cancelled);
+        assert_eq!(JOBS.load(&deps.storage, also_eligible).unwrap().status, JobStatus::Cancelled);
+        assert_eq!(JOBS.load(&deps.storage, processing).unwrap().status, JobStatus::Processing);
+        assert_eq!(JOBS.load(&deps.storage, foreign).unwrap().status, JobStatus::Submitted);
+
+        // One refund per cancelled job
+        assert_eq!(
+            res.messages.iter().map(|m| m.msg.clone()).collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "client".to_string(),
+                    amount: coins(1_000_000, "umedas"),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "client".to_string(),
+                    amount: coins(2_000_000, "umedas"),
+                }),
+            ]
+        );
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(provider.active_jobs, 2);
+    }
+
+    #[test]
+    fn test_provider_sla_compliance() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let stats = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| -> ProviderResponse {
+            from_json(
+                query(deps.as_ref(), mock_env(), QueryMsg::GetProviderStats { address: "provider".to_string() }).unwrap(),
+            ).unwrap()
+        };
+        assert_eq!(stats(&deps).sla_compliance, Decimal::from_ratio(100u128, 1u128));
+
+        // avg_completion_time is 180 seconds: three on time, one late
+        for elapsed in [60, 180, 90, 181] {
+            let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+            let mut done = mock_env();
+            done.block.time = done.block.time.plus_seconds(elapsed);
+            complete_job(deps.as_mut(), done, "provider", job_id);
+        }
+
+        let provider = stats(&deps);
+        assert_eq!(provider.on_time_count, 3);
+        assert_eq!(provider.late_count, 1);
+        assert_eq!(provider.sla_compliance, Decimal::from_ratio(75u128, 1u128));
+    }
+
+    #[test]
+    fn test_min_heartbeat_interval() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            config_update! { min_heartbeat_interval: Some(60) },
+        ).unwrap();
+
+        // Registration counts as the first heartbeat
+        let mut soon = mock_env();
+        soon.block.time = soon.block.time.plus_seconds(30);
+        let err = execute(deps.as_mut(), soon, mock_info("provider", &[]), ExecuteMsg::HeartBeat {}).unwrap_err();
+        let retry_after = mock_env().block.time.seconds() + 60;
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::HeartbeatTooSoon { retry_after: r } if r == retry_after
+        ));
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(60);
+        execute(deps.as_mut(), later.clone(), mock_info("provider", &[]), ExecuteMsg::HeartBeat {}).unwrap();
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(provider.last_heartbeat, later.block.time.seconds());
+    }
+
+    #[test]
+    fn test_provider_earnings_window() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let start = mock_env().block.time.seconds();
+        for elapsed in [100, 200, 300] {
+            let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+            let mut done = mock_env();
+            done.block.time = done.block.time.plus_seconds(elapsed);
+            complete_job(deps.as_mut(), done, "provider", job_id);
+        }
+        // Failed jobs earn nothing even inside the window
+        let failed = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let mut fail_time = mock_env();
+        fail_time.block.time = fail_time.block.time.plus_seconds(250);
+        execute(
+            deps.as_mut(),
+            fail_time,
+            mock_info("provider", &[]),
+            ExecuteMsg::FailJob { job_id: failed, reason: "crashed".to_string(), refund_percent: None },
+        ).unwrap();
+
+        let earnings = |start_after: Option<u64>, limit: Option<u32>| -> ProviderEarningsResponse {
+            from_json(
+                query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::GetProviderEarnings {
+                        provider: "provider".to_string(),
+                        from: start + 150,
+                        to: start + 300,
+                        start_after,
+                        limit,
+                    },
+                ).unwrap(),
+            ).unwrap()
+        };
+
+        // 85% of each 1_000_000 payment, the job at +100 is outside the window
+        let res = earnings(None, None);
+        assert_eq!(res.job_count, 2);
+        assert_eq!(res.earnings, coins(1_700_000, "umedas"));
+        assert_eq!(res.next_start_after, None);
+
+        // The same total, read two jobs at a time
+        let first = earnings(None, Some(2));
+        assert_eq!(first.job_count, 1);
+        let second = earnings(first.next_start_after, Some(2));
+        assert_eq!(second.job_count, 1);
+        assert_eq!(second.earnings, coins(850_000, "umedas"));
+        let last = earnings(second.next_start_after, Some(2));
+        assert_eq!((last.job_count, last.next_start_after), (0, None));
+    }
+
+    #[test]
+    fn test_cancel_community_fee() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = |cancel_fee_percent: Option<u64>, cancel_community_fee_percent: Option<u64>| {
+            config_update! {
+                cancel_fee_percent: cancel_fee_percent,
+                cancel_community_fee_percent: cancel_community_fee_percent,
+            }
+        };
+
+        // Zero by default - the whole payment is refunded
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJob { job_id, reason: None },
+        ).unwrap();
+        assert_eq!(
+            res.messages.iter().map(|m| m.msg.clone()).collect::<Vec<_>>(),
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: "client".to_string(),
+                amount: coins(1_000_000, "umedas"),
+            })]
+        );
+
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update(None, Some(5))).unwrap();
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJob { job_id, reason: None },
+        ).unwrap();
+        assert_eq!(
+            res.messages.iter().map(|m| m.msg.clone()).collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "medas1community...".to_string(),
+                    amount: coins(50_000, "umedas"),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "client".to_string(),
+                    amount: coins(950_000, "umedas"),
+                }),
+            ]
+        );
+
+        // Provider and community cancellation fees can't exceed the payment together
+        let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update(Some(96), None)).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidFeePercent { percent: 101 }));
+    }
+
+    #[test]
+    fn test_cheapest_providers_order() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        for (address, percent) in [("pricey", 5), ("cheap", 1), ("mid_a", 2), ("mid_b", 2), ("offline", 1)] {
+            let mut registration = pi_registration(address);
+            registration.pricing.get_mut("pi_calculation").unwrap().base_price = Decimal::percent(percent);
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(address, &[]),
+                ExecuteMsg::RegisterProvider {
+                    name: registration.name,
+                    capabilities: registration.capabilities,
+                    pricing: registration.pricing,
+                    endpoint: registration.endpoint,
+                    refund_guarantee: None,
+                    max_payment: None,
+                },
+            ).unwrap();
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("offline", &[]),
+            ExecuteMsg::UpdateProviderStatus { active: false },
+        ).unwrap();
+
+        // A completed job lifts mid_b's reputation above mid_a's
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "mid_b", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "mid_b", job_id);
+
+        let res: CheapestProvidersResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::CheapestProviders { job_type: "pi_calculation".to_string(), limit: None },
+            ).unwrap(),
+        ).unwrap();
+        let order: Vec<(&str, Decimal)> = res
+            .providers
+            .iter()
+            .map(|p| (p.provider.as_str(), p.base_price))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("cheap", Decimal::percent(1)),
+                ("mid_b", Decimal::percent(2)),
+                ("mid_a", Decimal::percent(2)),
+                ("pricey", Decimal::percent(5)),
+            ]
+        );
+
+        let res: CheapestProvidersResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::CheapestProviders { job_type: "pi_calculation".to_string(), limit: Some(1) },
+            ).unwrap(),
+        ).unwrap();
+        assert_eq!(res.providers.len(), 1);
+        assert_eq!(res.providers[0].provider, "cheap");
+    }
+
+    #[test]
+    fn test_job_id_exhaustion_is_a_clean_error() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        NEXT_JOB_ID.save(deps.as_mut().storage, &(u64::MAX - 1)).unwrap();
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        assert_eq!(job_id, u64::MAX - 1);
+
+        // The last id can't be handed out without overflowing the counter
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                referrer: None,
+                depends_on: None,
+                denom: "umedas".to_string(),
+                max_price: None,
+            },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::JobIdsExhausted {}));
+        assert_eq!(NEXT_JOB_ID.load(&deps.storage).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn test_imported_provider_keeps_seeded_reputation() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let mut veteran = pi_registration("veteran");
+        veteran.reputation = Some(Decimal::percent(9_000));
+        veteran.total_completed = Some(90);
+        veteran.total_failed = Some(10);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::RegisterProvidersBatch { providers: vec![veteran] },
+        ).unwrap();
+
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("veteran")).unwrap();
+        assert_eq!(provider.reputation, Decimal::from_ratio(90u128, 1u128));
+        assert_eq!((provider.total_completed, provider.total_failed), (90, 10));
+
+        // The next job recalculates from the carried-over counts:
+        // lifetime 91/101 rounds down to 90, recent window is 100
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "veteran", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "veteran", job_id);
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("veteran")).unwrap();
+        assert_eq!(provider.total_completed, 91);
+        assert_eq!(provider.reputation, Decimal::from_ratio(97u128, 1u128));
+
+        // A reputation given without counts is carried as 100 jobs' worth of history
+        let mut shaky = pi_registration("shaky");
+        shaky.reputation = Some(Decimal::percent(4_000));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::RegisterProvidersBatch { providers: vec![shaky] },
+        ).unwrap();
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("shaky")).unwrap();
+        assert_eq!(provider.reputation, Decimal::from_ratio(40u128, 1u128));
+        assert_eq!((provider.total_completed, provider.total_failed), (40, 60));
+
+        // The next job recalculates from that history rather than from a single outcome:
+        // lifetime 41/101 rounds down to 40, recent window is 100
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "shaky", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "shaky", job_id);
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("shaky")).unwrap();
+        assert_eq!(provider.reputation, Decimal::from_ratio(82u128, 1u128));
+
+        let mut overrated = pi_registration("overrated");
+        overrated.reputation = Some(Decimal::percent(10_001));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::RegisterProvidersBatch { providers: vec![overrated] },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidReputation { .. }));
+    }
+
+    #[test]
+    fn test_job_with_provider() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        let res: JobWithProviderResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetJobWithProvider { job_id }).unwrap(),
+        ).unwrap();
+        assert_eq!(res.job.id, job_id);
+        let provider = res.provider.unwrap();
+        assert_eq!(provider.address, "provider");
+        assert_eq!(provider.endpoint, "https://test.com");
+
+        // A provider record that no longer exists is reported as None
+        PROVIDERS.remove(deps.as_mut().storage, &Addr::unchecked("provider"));
+        let res: JobWithProviderResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetJobWithProvider { job_id }).unwrap(),
+        ).unwrap();
+        assert_eq!(res.job.provider, "provider");
+        assert!(res.provider.is_none());
+    }
+
+    #[test]
+    fn test_request_extension() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let original_deadline = JOBS.load(&deps.storage, job_id).unwrap().deadline;
+
+        let extend = |additional_seconds: u64| ExecuteMsg::RequestExtension { job_id, additional_seconds };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), extend(600)).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), extend(1_000)).unwrap();
+        let job = JOBS.load(&deps.storage, job_id).unwrap();
+        assert_eq!(job.deadline, original_deadline + 1_000);
+
+        // The keeper no longer sees the job as timed out at the original deadline
+        let mut after_original = mock_env();
+        after_original.block.time = after_original.block.time.plus_seconds(3_601);
+        execute(deps.as_mut(), after_original, mock_info("anyone", &[]), ExecuteMsg::ProcessTimedOutJobs { limit: None }).unwrap();
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().status, JobStatus::Submitted);
+
+        // Default limits are two extensions and an hour in total
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), extend(2_601)).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ExtensionLimitReached { .. }));
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), extend(2_600)).unwrap();
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), extend(0)).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::ExtensionLimitReached { max_extensions: 2, max_total: 3600 }
+        ));
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().deadline, original_deadline + 3_600);
+    }
+
+    #[test]
+    fn test_progress_releases_payment() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), ExecuteMsg::AcceptJob { job_id }).unwrap();
+
+        let report = |percent: u64| ExecuteMsg::ReportProgress { job_id, percent };
+        let approve = ExecuteMsg::ApproveProgress { job_id };
+        let sent = |res: &cosmwasm_std::Response| -> Vec<CosmosMsg> {
+            res.messages.iter().map(|m| m.msg.clone()).collect()
+        };
+
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), report(50)).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), approve.clone()).unwrap();
+        // Half the payment, split 15% community / 85% provider
+        assert_eq!(
+            sent(&res),
+            vec![
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "medas1community...".to_string(),
+                    amount: coins(75_000, "umedas"),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "provider".to_string(),
+                    amount: coins(425_000, "umedas"),
+                }),
+            ]
+        );
+
+        // Approving again without new progress releases nothing
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), approve.clone()).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidProgress { .. }));
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), report(101)).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidProgress { percent: 101 }));
+
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), report(100)).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), approve).unwrap();
+        let released = res.attributes.iter().find(|a| a.key == "released_amount").unwrap();
+        assert_eq!(released.value, "500000");
+        let job = JOBS.load(&deps.storage, job_id).unwrap();
+        assert_eq!(job.released_amount, Uint128::new(1_000_000));
+
+        // Completion has nothing left in escrow to pay out
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::CompleteJob {
+                job_id,
+                result_hash: "hash".to_string(),
+                result_url: "https://result.test".to_string(),
+                encryption: None,
+                result_size_bytes: None,
+            },
+        ).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().provider_fee, Uint128::new(850_000));
+    }
+
+    #[test]
+    fn test_submit_job_type_not_offered() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "prime_search".to_string(),
+                parameters: r#"{"quantity":1}"#.to_string(),
+                referrer: None,
+                depends_on: None,
+                denom: "umedas".to_string(),
+                max_price: None,
+            },
+        ).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::ServiceNotOffered { ref job_type } if job_type == "prime_search"
+        ));
+    }
+
+    #[test]
+    fn test_client_allowlist() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        // Open by default
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            config_update! { require_client_allowlist: Some(true) },
+        ).unwrap();
+
+        let submit = ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit.clone(),
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ClientNotAllowed {}));
+
+        let allow = ExecuteMsg::AddAllowedClient { address: "client".to_string() };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), allow.clone()).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), allow).unwrap();
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::RemoveAllowedClient { address: "client".to_string() },
+        ).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            submit,
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ClientNotAllowed {}));
+    }
+
+    #[test]
+    fn test_get_role() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        let role = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str| -> (bool, bool, bool) {
+            let res: RoleResponse = from_json(
+                query(deps.as_ref(), mock_env(), QueryMsg::GetRole { address: address.to_string() }).unwrap(),
+            ).unwrap();
+            (res.is_admin, res.is_provider, res.has_jobs_as_client)
+        };
+
+        assert_eq!(role(&deps, "creator"), (true, false, false));
+        assert_eq!(role(&deps, "provider"), (false, true, false));
+        assert_eq!(role(&deps, "client"), (false, false, true));
+        assert_eq!(role(&deps, "stranger"), (false, false, false));
+    }
+
+    #[test]
+    fn test_volume_fee_discount() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::SetFeeDiscountTiers {
+                tiers: vec![
+                    FeeDiscountTier { min_completed_jobs: 2, discount_percent: 20 },
+                    FeeDiscountTier { min_completed_jobs: 5, discount_percent: 50 },
+                ],
+            },
+        ).unwrap();
+
+        let community_share = |res: &cosmwasm_std::Response| -> String {
+            res.attributes.iter().find(|a| a.key == "community_fee").unwrap().value.clone()
+        };
+        let run_job = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>| {
+            let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("provider", &[]),
+                ExecuteMsg::CompleteJob {
+                    job_id,
+                    result_hash: format!("hash{}", job_id),
+                    result_url: "https://result.test".to_string(),
+                    encryption: None,
+                    result_size_bytes: None,
+                },
+            ).unwrap()
+        };
+
+        // Full 15% until the client has two completed jobs behind it
+        assert_eq!(community_share(&run_job(&mut deps)), "150000");
+        assert_eq!(community_share(&run_job(&mut deps)), "150000");
+        assert_eq!(community_share(&run_job(&mut deps)), "120000");
+
+        let stats: ClientStatsResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetClientStats { client: "client".to_string() }).unwrap(),
+        ).unwrap();
+        assert_eq!(stats.completed_jobs, 3);
+        assert_eq!(stats.total_spent, coins(3_000_000, "umedas"));
+        assert_eq!(stats.fee_discount_percent, 20);
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::SetFeeDiscountTiers {
+                tiers: vec![FeeDiscountTier { min_completed_jobs: 1, discount_percent: 101 }],
+            },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidDiscountPercent { percent: 101 }));
+    }
+
+    #[test]
+    fn test_complete_job_with_missing_provider_record() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        PROVIDERS.remove(deps.as_mut().storage, &Addr::unchecked("provider"));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::CompleteJob {
+                job_id,
+                result_hash: "hash".to_string(),
+                result_url: "https://result.test".to_string(),
+                encryption: None,
+                result_size_bytes: None,
+            },
+        ).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::ProviderNotRegistered { ref address } if address == "provider"
+        ));
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().status, JobStatus::Submitted);
+    }
+
+    #[test]
+    fn test_default_reputation_applies_to_new_providers() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "early");
+
+        let update = |reputation: Decimal| config_update! { default_reputation: Some(reputation) };
+        let seventy = Decimal::from_ratio(70u128, 1u128);
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update(seventy)).unwrap();
+        register_provider(deps.as_mut(), mock_env(), "late");
+
+        let reputation = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, addr: &str| {
+            PROVIDERS.load(&deps.storage, &Addr::unchecked(addr)).unwrap().reputation
+        };
+        assert_eq!(reputation(&deps, "early"), Decimal::percent(50));
+        assert_eq!(reputation(&deps, "late"), seventy);
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            update(Decimal::from_ratio(101u128, 1u128)),
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidReputation { .. }));
+    }
+
+    #[test]
+    fn test_remove_capability() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let mut pricing = HashMap::new();
+        for service_type in ["service_0", "service_1"] {
+            pricing.insert(service_type.to_string(), PricingTier {
+                base_price: Decimal::percent(1),
+                unit: "unit".to_string(),
+            });
+        }
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::RegisterProvider {
+                name: "Provider".to_string(),
+                capabilities: capabilities(2),
+                pricing,
+                endpoint: "https://provider.example".to_string(),
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap();
+
+        let remove = |service_type: &str| ExecuteMsg::RemoveCapability { service_type: service_type.to_string() };
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), remove("service_0")).unwrap();
+
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(provider.capabilities.len(), 1);
+        assert_eq!(provider.capabilities[0].service_type, "service_1");
+        assert!(!provider.pricing.contains_key("service_0"));
+        assert!(provider.pricing.contains_key("service_1"));
+
+        // The service index no longer lists the provider for the dropped service
+        let res: CheapestProvidersResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::CheapestProviders { job_type: "service_0".to_string(), limit: None },
+            ).unwrap(),
+        ).unwrap();
+        assert!(res.providers.is_empty());
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), remove("service_1")).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::LastCapability {}));
+    }
+
+    #[test]
+    fn test_keeper_maintenance_runs_while_paused() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::PauseContract {}).unwrap();
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(3_601);
+        let res = execute(
+            deps.as_mut(),
+            later.clone(),
+            mock_info("keeper", &[]),
+            ExecuteMsg::ProcessTimedOutJobs { limit: None },
+        ).unwrap();
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().status, JobStatus::Failed);
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "client".to_string(),
+                amount: coins(1_000_000, "umedas"),
+            })
+        );
+        execute(deps.as_mut(), later, mock_info("keeper", &[]), ExecuteMsg::ProcessInactiveProviders {}).unwrap();
+
+        // Everything else stays blocked
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                referrer: None,
+                depends_on: None,
+                denom: "umedas".to_string(),
+                max_price: None,
+            },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ContractPaused {}));
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), ExecuteMsg::HeartBeat {}).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ContractPaused {}));
+    }
+
+    #[test]
+    fn test_job_denom_chosen_per_job_and_used_for_refunds() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = config_update! { accepted_denoms: Some(vec!["umedas".to_string(), "uatom".to_string()]) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let submit = |denom: &str| ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: denom.to_string(),
+            max_price: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &coins(1_000, "ujuno")), submit("ujuno")).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::DenomNotAccepted { .. }));
+        let mixed = vec![cosmwasm_std::Coin::new(1_000, "uatom"), cosmwasm_std::Coin::new(1_000, "umedas")];
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &mixed), submit("uatom")).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::UnexpectedDenom { denom } if denom == "umedas"));
+
+        let mut job_ids = vec![];
+        for (denom, amount) in [("umedas", 1_000_000u128), ("uatom", 2_000u128)] {
+            let res = execute(deps.as_mut(), mock_env(), mock_info("client", &coins(amount, denom)), submit(denom)).unwrap();
+            let job_id: u64 = res.attributes.iter().find(|a| a.key == "job_id").unwrap().value.parse().unwrap();
+            assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().denom, denom);
+            job_ids.push((job_id, denom, amount));
+        }
+
+        // Only umedas escrow counts against the umedas balance
+        let res: SolvencyResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetSolvency {}).unwrap(),
+        ).unwrap();
+        assert_eq!(res.obligations, Uint128::new(1_000_000));
+
+        for (job_id, denom, amount) in job_ids {
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("client", &[]),
+                ExecuteMsg::CancelJob { job_id, reason: None },
+            ).unwrap();
+            assert_eq!(
+                res.messages[0].msg,
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "client".to_string(),
+                    amount: coins(amount, denom),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_recent_completions_newest_first() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        register_provider(deps.as_mut(), mock_env(), "other_provider");
+
+        let first = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let second = submit_job(deps.as_mut(), mock_env(), "other_client", "other_provider", 1_000_000);
+        let third = submit_job(deps.as_mut(), mock_env(), "client", "other_provider", 1_000_000);
+        let pending = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        for (offset, provider, job_id) in [(10, "provider", first), (20, "other_provider", second), (30, "other_provider", third)] {
+            let mut env = mock_env();
+            env.block.time = env.block.time.plus_seconds(offset);
+            complete_job(deps.as_mut(), env, provider, job_id);
+        }
+
+        let recent = |deps: Deps, limit| -> Vec<u64> {
+            let res: JobsResponse = from_json(
+                query(deps, mock_env(), QueryMsg::RecentCompletions { limit }).unwrap(),
+            ).unwrap();
+            res.jobs.iter().map(|j| j.id).collect()
+        };
+        assert_eq!(recent(deps.as_ref(), None), vec![third, second, first]);
+        assert_eq!(recent(deps.as_ref(), Some(2)), vec![third, second]);
+        assert!(!recent(deps.as_ref(), None).contains(&pending));
+    }
+
+    #[test]
+    fn test_submit_job_rejects_price_above_max() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        // 10000 digits at 0.01 umedas per digit
+        let submit = |max_price: u128| ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: Some(Uint128::new(max_price)),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("client", &coins(1_000_000, "umedas")), submit(100)).unwrap();
+
+        // The provider doubles its price before the next submission lands
+        let mut pricing = HashMap::new();
+        pricing.insert("pi_calculation".to_string(), PricingTier {
+            base_price: Decimal::percent(2),
+            unit: "digit".to_string(),
+        });
+        let reprice = ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: Some(pricing),
+            capacity: None,
+            payout_address: None,
+            capabilities: None,
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), reprice).unwrap();
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &coins(1_000_000, "umedas")), submit(150)).unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::PriceExceededMax { max, actual }
+                if max == Uint128::new(150) && actual == Uint128::new(200)
+        ));
+    }
+
+    #[test]
+    fn test_check_invariants_reports_corrupted_counters() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let completed = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", completed);
+        let cancelled = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJob { job_id: cancelled, reason: None },
+        ).unwrap();
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        let check = |deps: Deps| -> Vec<String> {
+            let res: InvariantsResponse = from_json(
+                query(deps, mock_env(), QueryMsg::CheckInvariants {}).unwrap(),
+            ).unwrap();
+            res.violations
+        };
+        assert!(check(deps.as_ref()).is_empty());
+
+        // Drift the provider's job counter
+        let mut provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        provider.active_jobs += 1;
+        PROVIDERS.save(&mut deps.storage, &Addr::unchecked("provider"), &provider).unwrap();
+        assert_eq!(check(deps.as_ref()), vec!["provider active_jobs total 2 != open jobs 1".to_string()]);
+
+        // Roll the id counter back onto an existing job
+        NEXT_JOB_ID.save(&mut deps.storage, &completed).unwrap();
+        let violations = check(deps.as_ref());
+        assert_eq!(violations.len(), 2);
+        assert!(violations[1].starts_with("next job id"));
+    }
+
+    #[test]
+    fn test_completion_receipt_carries_canonical_fields_and_nonce() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        for expected_nonce in 1..=2u64 {
+            let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("provider", &[]),
+                ExecuteMsg::CompleteJob {
+                    job_id,
+                    result_hash: format!("hash{}", job_id),
+                    result_url: "https://result.test".to_string(),
+                    encryption: None,
+                    result_size_bytes: None,
+                },
+            ).unwrap();
+
+            let receipt = res.events.iter().find(|e| e.ty == "completion_receipt").unwrap();
+            let fields: Vec<(&str, String)> = receipt
+                .attributes
+                .iter()
+                .map(|a| (a.key.as_str(), a.value.clone()))
+                .collect();
+            assert_eq!(
+                fields,
+                vec![
+                    ("job_id", job_id.to_string()),
+                    ("provider", "provider".to_string()),
+                    ("result_hash", format!("hash{}", job_id)),
+                    ("completed_at", mock_env().block.time.seconds().to_string()),
+                    ("nonce", expected_nonce.to_string()),
+                ]
+            );
+            let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+            assert_eq!(provider.completion_nonce, expected_nonce);
+        }
+    }
+
+    #[test]
+    fn test_timeout_sweep_processes_in_batches() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job_ids: Vec<u64> = (0..5)
+            .map(|_| submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000))
+            .collect();
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(3_601);
+        let res = execute(
+            deps.as_mut(),
+            later.clone(),
+            mock_info("keeper", &[]),
+            ExecuteMsg::ProcessTimedOutJobs { limit: Some(2) },
+        ).unwrap();
+
+        let attr = |key: &str| res.attributes.iter().find(|a| a.key == key).unwrap().value.clone();
+        assert_eq!(attr("processed_count"), "2");
+        assert_eq!(attr("has_more"), "true");
+        assert_eq!(res.messages.len(), 2);
+        assert_eq!(JOBS.load(&deps.storage, job_ids[1]).unwrap().status, JobStatus::Failed);
+        assert_eq!(JOBS.load(&deps.storage, job_ids[2]).unwrap().status, JobStatus::Submitted);
+
+        // The last three fit exactly, leaving nothing behind
+        let res = execute(
+            deps.as_mut(),
+            later,
+            mock_info("keeper", &[]),
+            ExecuteMsg::ProcessTimedOutJobs { limit: Some(3) },
+        ).unwrap();
+        let attr = |key: &str| res.attributes.iter().find(|a| a.key == key).unwrap().value.clone();
+        assert_eq!(attr("processed_count"), "3");
+        assert_eq!(attr("has_more"), "false");
+    }
+
+    #[test]
+    fn test_client_prefs_set_and_cleared() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let prefs = |deps: Deps| -> Option<String> {
+            let res: ClientPrefsResponse = from_json(
+                query(deps, mock_env(), QueryMsg::GetClientPrefs { client: "client".to_string() }).unwrap(),
+            ).unwrap();
+            res.notify_url
+        };
+        assert_eq!(prefs(deps.as_ref()), None);
+
+        let set = |notify_url: Option<String>| ExecuteMsg::SetClientPrefs { notify_url };
+        execute(deps.as_mut(), mock_env(), mock_info("client", &[]), set(Some("https://hooks.test/client".to_string()))).unwrap();
+        assert_eq!(prefs(deps.as_ref()), Some("https://hooks.test/client".to_string()));
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), set(Some("x".repeat(257)))).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::NotifyUrlTooLong { max: 256 }));
+        assert_eq!(prefs(deps.as_ref()), Some("https://hooks.test/client".to_string()));
+
+        execute(deps.as_mut(), mock_env(), mock_info("client", &[]), set(None)).unwrap();
+        assert_eq!(prefs(deps.as_ref()), None);
+    }
+
+    #[test]
+    fn test_requeued_job_gets_community_fee_discount() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = config_update! { requeue_fee_discount_percent: Some(100) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let failed = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::FailJob { job_id: failed, reason: "crashed".to_string(), refund_percent: None },
+        ).unwrap();
+
+        // Only the original client can requeue
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("someone", &coins(1_000_000, "umedas")),
+            ExecuteMsg::RequeueJob { job_id: failed },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::RequeueJob { job_id: failed },
+        ).unwrap();
+        let requeued: u64 = res.attributes.iter().find(|a| a.key == "job_id").unwrap().value.parse().unwrap();
+        assert_eq!(JOBS.load(&deps.storage, requeued).unwrap().original_job_id, Some(failed));
+        assert_eq!(JOBS.load(&deps.storage, failed).unwrap().requeued_as, Some(requeued));
+
+        // The discount can't be farmed by requeueing the same failure again
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::RequeueJob { job_id: failed },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::AlreadyRequeued { job_id } if job_id == failed));
+
+        let fresh = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        let community_fee = |deps: DepsMut, job_id: u64| -> String {
+            let res = execute(
+                deps,
+                mock_env(),
+                mock_info("provider", &[]),
+                ExecuteMsg::CompleteJob {
+                    job_id,
+                    result_hash: format!("hash{}", job_id),
+                    result_url: "https://result.test".to_string(),
+                    encryption: None,
+                    result_size_bytes: None,
+                },
+            ).unwrap();
+            res.attributes.iter().find(|a| a.key == "community_fee").unwrap().value.clone()
+        };
+        assert_eq!(community_fee(deps.as_mut(), requeued), "0");
+        assert_eq!(community_fee(deps.as_mut(), fresh), "150000");
+    }
+
+    #[test]
+    fn test_tvl_reports_balance_and_obligations_per_denom() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = config_update! { accepted_denoms: Some(vec!["umedas".to_string(), "uatom".to_string()]) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let submit = |denom: &str| ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: denom.to_string(),
+            max_price: None,
+        };
+        let mut job_ids = vec![];
+        for (denom, amount) in [("umedas", 1_000_000u128), ("uatom", 2_000u128), ("uatom", 3_000u128)] {
+            let res = execute(deps.as_mut(), mock_env(), mock_info("client", &coins(amount, denom)), submit(denom)).unwrap();
+            job_ids.push(res.attributes.iter().find(|a| a.key == "job_id").unwrap().value.parse::<u64>().unwrap());
+        }
+        complete_job(deps.as_mut(), mock_env(), "provider", job_ids[2]);
+
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR,
+            vec![
+                cosmwasm_std::Coin::new(1_000_000, "umedas"),
+                cosmwasm_std::Coin::new(2_000, "uatom"),
+                cosmwasm_std::Coin::new(7, "uother"),
+            ],
+        );
+        let res: TvlResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetTVL {}).unwrap(),
+        ).unwrap();
+        assert_eq!(
+            res.balances,
+            vec![cosmwasm_std::Coin::new(1_000_000, "umedas"), cosmwasm_std::Coin::new(2_000, "uatom")]
+        );
+        assert_eq!(
+            res.obligations,
+            vec![cosmwasm_std::Coin::new(1_000_000, "umedas"), cosmwasm_std::Coin::new(2_000, "uatom")]
+        );
+    }
+
+    #[test]
+    fn test_deactivation_refunds_submitted_jobs_when_enabled() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let update = config_update! {
+            require_heartbeat: Some(true),
+            refund_on_deactivation: Some(true),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let first = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let second = submit_job(deps.as_mut(), mock_env(), "other_client", "provider", 2_000_000);
+        let accepted = submit_job(deps.as_mut(), mock_env(), "client", "provider", 3_000_000);
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), ExecuteMsg::AcceptJob { job_id: accepted })
+            .unwrap();
+
+        let reputation = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap().reputation;
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(301);
+        let res = execute(deps.as_mut(), later, mock_info("keeper", &[]), ExecuteMsg::ProcessInactiveProviders {})
+            .unwrap();
+
+        // Newest jobs are refunded first
+        assert_eq!(
+            res.messages.iter().map(|m| m.msg.clone()).collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "other_client".to_string(),
+                    amount: coins(2_000_000, "umedas"),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "client".to_string(),
+                    amount: coins(1_000_000, "umedas"),
+                }),
+            ]
+        );
+        assert_eq!(JOBS.load(&deps.storage, first).unwrap().status, JobStatus::Failed);
+        assert_eq!(JOBS.load(&deps.storage, second).unwrap().status, JobStatus::Failed);
+        // Work already underway is left to finish or time out
+        assert_eq!(JOBS.load(&deps.storage, accepted).unwrap().status, JobStatus::Processing);
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert!(!provider.active);
+        assert_eq!(provider.active_jobs, 1);
+        // Administrative refunds are not failures of the provider or the market
+        assert_eq!(provider.total_failed, 0);
+        assert_eq!(provider.reputation, reputation);
+        let rate: FailureRateResponse =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetFailureRate {}).unwrap()).unwrap();
+        assert_eq!(rate.failures, 0);
+    }
+
+    #[test]
+    fn test_oldest_providers_by_tenure() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        // Registered in reverse address order so tenure, not address, decides
+        let mut env = mock_env();
+        for provider in ["carol", "bob", "alice"] {
+            register_provider(deps.as_mut(), env.clone(), provider);
+            env.block.time = env.block.time.plus_seconds(86_400);
+        }
+
+        env.block.time = env.block.time.plus_seconds(86_400);
+        let res: OldestProvidersResponse = from_json(
+            query(deps.as_ref(), env, QueryMsg::OldestProviders { limit: Some(2) }).unwrap(),
+        ).unwrap();
+        let tenures: Vec<(&str, u64)> = res
+            .providers
+            .iter()
+            .map(|p| (p.provider.as_str(), p.tenure_days))
+            .collect();
+        assert_eq!(tenures, vec![("carol", 4), ("bob", 3)]);
+    }
+
+    #[test]
+    fn test_completion_at_fee_boundaries() {
+        let instantiate_with_fee = |community_fee_percent| {
+            let mut deps = mock_dependencies();
+            let init_msg = InstantiateMsg {
+                community_pool: "medas1community...".to_string(),
+                community_fee_percent,
+                default_job_timeout: 3600,
+                heartbeat_timeout: 300,
+            };
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).map(|_| deps)
+        };
+        let complete = |deps: DepsMut, job_id: u64| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info("provider", &[]),
+                ExecuteMsg::CompleteJob {
+                    job_id,
+                    result_hash: "hash".to_string(),
+                    result_url: "https://result.test".to_string(),
+                    encryption: None,
+                    result_size_bytes: None,
+                },
+            ).unwrap()
+        };
+
+        // 0% - the provider gets the whole payment in a single send
+        let mut deps = instantiate_with_fee(0).unwrap();
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let res = complete(deps.as_mut(), job_id);
+        assert_eq!(
+            res.messages.iter().map(|m| m.msg.clone()).collect::<Vec<_>>(),
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: "provider".to_string(),
+                amount: coins(1_000_000, "umedas"),
+            })]
+        );
+
+        // 100% - everything goes to the pool and no empty send is built for the provider
+        let mut deps = instantiate_with_fee(100).unwrap();
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let res = complete(deps.as_mut(), job_id);
+        assert_eq!(
+            res.messages.iter().map(|m| m.msg.clone()).collect::<Vec<_>>(),
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: "medas1community...".to_string(),
+                amount: coins(1_000_000, "umedas"),
+            })]
+        );
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().provider_fee, Uint128::zero());
+
+        let err = instantiate_with_fee(101).err().unwrap();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidFeePercent { percent: 101 }));
+    }
+
+    #[test]
+    fn test_job_escrow_matches_balance_after_payouts() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let completed = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let partial = submit_job(deps.as_mut(), mock_env(), "client", "provider", 2_000_000);
+        let cancelled = submit_job(deps.as_mut(), mock_env(), "other_client", "provider", 3_000_000);
+        let untouched = submit_job(deps.as_mut(), mock_env(), "other_client", "provider", 4_000_000);
+        let deposited = 10_000_000u128;
+
+        // Fees are forwarded as soon as they are charged, so everything sent out leaves the balance
+        let mut sent = 0u128;
+        let mut track = |res: cosmwasm_std::Response| {
+            for m in res.messages {
+                if let CosmosMsg::Bank(BankMsg::Send { amount, .. }) = m.msg {
+                    sent += amount.iter().map(|c| c.amount.u128()).sum::<u128>();
+                }
+            }
+        };
+        track(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::CompleteJob {
+                job_id: completed,
+                result_hash: "hash".to_string(),
+                result_url: "https://result.test".to_string(),
+                encryption: None,
+                result_size_bytes: None,
+            },
+        ).unwrap());
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), ExecuteMsg::AcceptJob { job_id: partial }).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::ReportProgress { job_id: partial, percent: 25 },
+        ).unwrap();
+        track(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::ApproveProgress { job_id: partial },
+        ).unwrap());
+        track(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other_client", &[]),
+            ExecuteMsg::CancelJob { job_id: cancelled, reason: None },
+        ).unwrap());
+
+        let escrow: Vec<(u64, Uint128)> = JOB_ESCROW
+            .range(&deps.storage, None, None, cosmwasm_std::Order::Ascending)
+            .collect::<cosmwasm_std::StdResult<_>>()
+            .unwrap();
+        assert_eq!(escrow, vec![(partial, Uint128::new(1_500_000)), (untouched, Uint128::new(4_000_000))]);
+        let held: u128 = escrow.iter().map(|(_, amount)| amount.u128()).sum();
+        assert_eq!(held, deposited - sent);
+
+        let res: InvariantsResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::CheckInvariants {}).unwrap(),
+        ).unwrap();
+        assert!(res.violations.is_empty());
+    }
+
+    #[test]
+    fn test_prune_stale_providers() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "stale");
+        register_provider(deps.as_mut(), mock_env(), "busy");
+        register_provider(deps.as_mut(), mock_env(), "alive");
+        submit_job(deps.as_mut(), mock_env(), "client", "busy", 1_000_000);
+
+        let update = config_update! { deregister_after: Some(30 * 86_400) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(31 * 86_400);
+        execute(deps.as_mut(), later.clone(), mock_info("alive", &[]), ExecuteMsg::HeartBeat {}).unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            later,
+            mock_info("keeper", &[]),
+            ExecuteMsg::PruneStaleProviders { limit: None },
+        ).unwrap();
+        assert_eq!(res.attributes.iter().find(|a| a.key == "providers").unwrap().value, "stale");
+
+        assert!(!PROVIDERS.has(&deps.storage, &Addr::unchecked("stale")));
+        assert!(PROVIDERS.has(&deps.storage, &Addr::unchecked("busy")));
+        assert!(PROVIDERS.has(&deps.storage, &Addr::unchecked("alive")));
+        let res: CheapestProvidersResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::CheapestProviders {
+                job_type: "pi_calculation".to_string(),
+                limit: None,
+            }).unwrap(),
+        ).unwrap();
+        assert!(res.providers.iter().all(|p| p.provider != "stale"));
+    }
+
+    #[test]
+    fn test_prune_keeps_providers_with_history() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "idle");
+        register_provider(deps.as_mut(), mock_env(), "veteran");
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "veteran", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "veteran", job_id);
+
+        let update = config_update! { deregister_after: Some(30 * 86_400) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(31 * 86_400);
+        let res = execute(
+            deps.as_mut(),
+            later.clone(),
+            mock_info("keeper", &[]),
+            ExecuteMsg::PruneStaleProviders { limit: None },
+        ).unwrap();
+        assert_eq!(res.attributes.iter().find(|a| a.key == "providers").unwrap().value, "idle");
+        let veteran = PROVIDERS.load(&deps.storage, &Addr::unchecked("veteran")).unwrap();
+
+        // The veteran's finished job can still be rated
+        execute(
+            deps.as_mut(),
+            later.clone(),
+            mock_info("client", &[]),
+            ExecuteMsg::RateJob { job_id, rating: 5 },
+        ).unwrap();
+
+        // A pruned provider starts over; one with a record can't re-register to reset it
+        register_provider(deps.as_mut(), later.clone(), "idle");
+        let err = execute(
+            deps.as_mut(),
+            later,
+            mock_info("veteran", &[]),
+            ExecuteMsg::RegisterProvider {
+                name: "Veteran".to_string(),
+                capabilities: veteran.capabilities.clone(),
+                pricing: veteran.pricing.clone(),
+                endpoint: veteran.endpoint.clone(),
+                refund_guarantee: None,
+                max_payment: None,
+            },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ProviderAlreadyRegistered {}));
+        assert_eq!(PROVIDERS.load(&deps.storage, &Addr::unchecked("veteran")).unwrap().total_completed, 1);
+    }
+
+    #[test]
+    fn test_result_size_recorded_on_completion() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let sized = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::CompleteJob {
+                job_id: sized,
+                result_hash: "hash".to_string(),
+                result_url: "https://result.test".to_string(),
+                encryption: None,
+                result_size_bytes: Some(4_096),
+            },
+        ).unwrap();
+        let unsized_job = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", unsized_job);
+
+        let size = |job_id| {
+            let res: JobResponse = from_json(
+                query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id }).unwrap(),
+            ).unwrap();
+            res.result_size_bytes
+        };
+        assert_eq!(size(sized), Some(4_096));
+        assert_eq!(size(unsized_job), None);
+    }
+
+    #[test]
+    fn test_provider_job_counters_track_lifecycle() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let completed = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let cancelled = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", completed);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &[]),
+            ExecuteMsg::CancelJob { job_id: cancelled, reason: None },
+        ).unwrap();
+
+        let res: ProviderResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetProvider { address: "provider".to_string() }).unwrap(),
+        ).unwrap();
+        assert_eq!(res.active_jobs, 1);
+        assert_eq!(res.total_job_count, 3);
+    }
+
+    #[test]
+    fn test_raw_state_survives_missing_config() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        CONFIG.remove(&mut deps.storage);
+        assert!(query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).is_err());
+
+        let res: RawStateResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetRawState {}).unwrap(),
+        ).unwrap();
+        assert_eq!(
+            res,
+            RawStateResponse {
+                config_ok: false,
+                next_job_id: Some(2),
+                provider_count: 1,
+                job_count: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_provider_max_payment_caps_submissions() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let cap = |max: u128| ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: None,
+            capacity: None,
+            payout_address: None,
+            capabilities: None,
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: Some(Uint128::new(max)),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), cap(1_000_000)).unwrap();
+
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let submit = ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &coins(1_000_001, "umedas")), submit.clone())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::PaymentTooLarge { max } if max == Uint128::new(1_000_000)
+        ));
+
+        // Zero lifts the cap again
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), cap(0)).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("client", &coins(1_000_001, "umedas")), submit).unwrap();
+    }
+
+    #[test]
+    fn test_list_providers_with_pricing() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "alice");
+        register_provider(deps.as_mut(), mock_env(), "bob");
+
+        let mut pricing = HashMap::new();
+        pricing.insert("pi_calculation".to_string(), PricingTier {
+            base_price: Decimal::percent(3),
+            unit: "digit".to_string(),
+        });
+        let reprice = ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: Some(pricing),
+            capacity: None,
+            payout_address: None,
+            capabilities: None,
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), reprice).unwrap();
+
+        // Offers a different service only
+        let mut pricing = HashMap::new();
+        pricing.insert("prime_search".to_string(), PricingTier {
+            base_price: Decimal::percent(2),
+            unit: "number".to_string(),
+        });
+        let register = ExecuteMsg::RegisterProvider {
+            name: "Carol".to_string(),
+            capabilities: vec![ServiceCapability {
+                service_type: "prime_search".to_string(),
+                max_complexity: 1000,
+                avg_completion_time: 60,
+                required_parameters: vec![],
+                enabled: true,
+            }],
+            pricing,
+            endpoint: "https://carol.test".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("carol", &[]), register).unwrap();
+
+        let list = |start_after: Option<&str>, limit| -> ProvidersWithPricingResponse {
+            from_json(
+                query(deps.as_ref(), mock_env(), QueryMsg::ListProvidersWithPricing {
+                    service_type: "pi_calculation".to_string(),
+                    start_after: start_after.map(|s| s.to_string()),
+                    limit,
+                }).unwrap(),
+            ).unwrap()
+        };
+        let res = list(None, None);
+        let prices: Vec<(&str, Option<Decimal>)> = res
+            .providers
+            .iter()
+            .map(|p| (p.provider.as_str(), p.pricing.as_ref().map(|t| t.base_price)))
+            .collect();
+        assert_eq!(
+            prices,
+            vec![("alice", Some(Decimal::percent(1))), ("bob", Some(Decimal::percent(3)))]
+        );
+
+        let res = list(Some("alice"), Some(1));
+        assert_eq!(res.providers.len(), 1);
+        assert_eq!(res.providers[0].provider, "bob");
+    }
+
+    #[test]
+    fn test_price_overflow_is_a_clean_error() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let mut pricing = HashMap::new();
+        pricing.insert("pi_calculation".to_string(), PricingTier {
+            base_price: Decimal::MAX,
+            unit: "digit".to_string(),
+        });
+        let reprice = ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: Some(pricing),
+            capacity: None,
+            payout_address: None,
+            capabilities: Some(vec![ServiceCapability {
+                service_type: "pi_calculation".to_string(),
+                max_complexity: u64::MAX,
+                avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
+            }]),
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), reprice).unwrap();
+
+        // Caught whether or not the client quoted a maximum price
+        for max_price in [Some(Uint128::new(1_000_000)), None] {
+            let submit = ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "pi_calculation".to_string(),
+                parameters: format!(r#"{{"digits":{}}}"#, u64::MAX),
+                referrer: None,
+                depends_on: None,
+                denom: "umedas".to_string(),
+                max_price,
+            };
+            let err = execute(deps.as_mut(), mock_env(), mock_info("client", &coins(1_000_000, "umedas")), submit)
+                .unwrap_err();
+            assert!(matches!(err, medas_computing_contract::ContractError::PriceOverflow {}));
+        }
+    }
+
+    #[test]
+    fn test_probation_caps_payment_for_new_providers() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let update = config_update! {
+            probation_period: Some(7 * 86_400),
+            probation_max_payment: Some(Uint128::new(500_000)),
+            probation_min_jobs: Some(1),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        // The veteran registered well before and has a completed job behind it
+        let mut now = mock_env();
+        register_provider(deps.as_mut(), now.clone(), "veteran");
+        let job_id = submit_job(deps.as_mut(), now.clone(), "client", "veteran", 500_000);
+        complete_job(deps.as_mut(), now.clone(), "veteran", job_id);
+        now.block.time = now.block.time.plus_seconds(8 * 86_400);
+        register_provider(deps.as_mut(), now.clone(), "newcomer");
+
+        let submit = |provider: &str| ExecuteMsg::SubmitJob {
+            provider: provider.to_string(),
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":10000}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let err = execute(deps.as_mut(), now.clone(), mock_info("client", &coins(1_000_000, "umedas")), submit("newcomer"))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::PaymentTooLarge { max } if max == Uint128::new(500_000)
+        ));
+        execute(deps.as_mut(), now.clone(), mock_info("client", &coins(500_000, "umedas")), submit("newcomer")).unwrap();
+        execute(deps.as_mut(), now, mock_info("client", &coins(1_000_000, "umedas")), submit("veteran")).unwrap();
+    }
+
+    #[test]
+    fn test_actionable_jobs_exclude_expired_and_blocked() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let expired = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let done = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", done);
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(3_000);
+        let open = submit_job(deps.as_mut(), later.clone(), "client", "provider", 1_000_000);
+        execute(
+            deps.as_mut(),
+            later.clone(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                referrer: None,
+                depends_on: Some(open),
+                denom: "umedas".to_string(),
+                max_price: None,
+            },
+        ).unwrap();
+
+        later.block.time = later.block.time.plus_seconds(700);
+        let newer = submit_job(deps.as_mut(), later.clone(), "client", "provider", 1_000_000);
+        let actionable = |start_after: Option<u64>, limit: Option<u32>| -> Vec<u64> {
+            let res: JobsResponse = from_json(
+                query(
+                    deps.as_ref(),
+                    later.clone(),
+                    QueryMsg::GetActionableJobs { provider: "provider".to_string(), start_after, limit },
+                ).unwrap(),
+            ).unwrap();
+            res.jobs.iter().map(|j| j.id).collect()
+        };
+        let ids = actionable(None, None);
+        assert_eq!(ids, vec![open, newer]);
+        assert!(!ids.contains(&expired));
+
+        // Paged with start_after like the other job listings
+        assert_eq!(actionable(None, Some(1)), vec![open]);
+        assert_eq!(actionable(Some(open), Some(1)), vec![newer]);
+    }
+
+    #[test]
+    fn test_actionable_jobs_cursor_crosses_scan_cap() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        // More expired jobs than one call scans, ahead of the only actionable one
+        for _ in 0..501 {
+            submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        }
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(4_000);
+        let open = submit_job(deps.as_mut(), later.clone(), "client", "provider", 1_000_000);
+
+        let page = |start_after: Option<u64>| -> JobsResponse {
+            from_json(
+                query(
+                    deps.as_ref(),
+                    later.clone(),
+                    QueryMsg::GetActionableJobs { provider: "provider".to_string(), start_after, limit: None },
+                ).unwrap(),
+            ).unwrap()
+        };
+
+        // The first call comes back empty but hands over where it stopped
+        let first = page(None);
+        assert!(first.jobs.is_empty());
+        assert_eq!(first.next_start_after, Some(500));
+
+        let second = page(first.next_start_after);
+        assert_eq!(second.jobs.iter().map(|j| j.id).collect::<Vec<_>>(), vec![open]);
+        assert_eq!(second.next_start_after, None);
+    }
+
+    #[test]
+    fn test_waive_penalty_restores_provider_standing() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let done = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", done);
+        let before = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+
+        let failed = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::FailJob { job_id: failed, reason: "datacenter outage".to_string(), refund_percent: None },
+        ).unwrap();
+        let penalised = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert!(penalised.reputation < before.reputation);
+
+        let waive = ExecuteMsg::WaivePenalty { job_id: failed };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), waive.clone()).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), waive.clone()).unwrap();
+
+        let after = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(after.total_failed, before.total_failed);
+        assert_eq!(after.recent_outcomes, before.recent_outcomes);
+        assert_eq!(after.reputation, before.reputation);
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), waive).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::PenaltyAlreadyWaived { .. }));
+    }
+
+    #[test]
+    fn test_job_history_records_each_transition() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(60);
+        execute(deps.as_mut(), env.clone(), mock_info("provider", &[]), ExecuteMsg::AcceptJob { job_id }).unwrap();
+
+        env.block.time = env.block.time.plus_seconds(60);
+        complete_job(deps.as_mut(), env, "provider", job_id);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id }).unwrap();
+        let job: JobResponse = from_json(&res).unwrap();
+        let statuses: Vec<&str> = job.history.iter().map(|change| change.status.as_str()).collect();
+        assert_eq!(statuses, vec!["submitted", "processing", "completed"]);
+        let start = mock_env().block.time;
+        let times: Vec<_> = job.history.iter().map(|change| change.at).collect();
+        assert_eq!(times, vec![start, start.plus_seconds(60), start.plus_seconds(120)]);
+    }
+
+    #[test]
+    fn test_failure_burst_trips_auto_pause() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        let update = config_update! {
+            auto_pause_failure_rate: Some(Decimal::percent(50)),
+            auto_pause_min_jobs: Some(6),
+            failure_window: Some(14400),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let sweep = |deps: DepsMut, env: Env| {
+            execute(deps, env, mock_info("keeper", &[]), ExecuteMsg::ProcessTimedOutJobs { limit: None }).unwrap()
+        };
+
+        // Healthy traffic: three completions and two jobs that will time out
+        let mut env = mock_env();
+        for _ in 0..3 {
+            let job_id = submit_job(deps.as_mut(), env.clone(), "client", "provider", 1_000_000);
+            complete_job(deps.as_mut(), env.clone(), "provider", job_id);
+        }
+        for _ in 0..2 {
+            submit_job(deps.as_mut(), env.clone(), "client", "provider", 1_000_000);
+        }
+        env.block.time = env.block.time.plus_seconds(3601);
+        let res = sweep(deps.as_mut(), env.clone());
+        assert!(res.events.is_empty());
+
+        let rate: FailureRateResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetFailureRate {}).unwrap()).unwrap();
+        assert_eq!(rate.failures, 2);
+        assert_eq!(rate.completions, 3);
+        assert_eq!(rate.failure_rate, Decimal::percent(40));
+
+        // Provider-reported failures count like provider-reported completions; at exactly half
+        // over the minimum sample nothing trips yet
+        let start = env.clone();
+        let job_id = submit_job(deps.as_mut(), env.clone(), "client", "provider", 1_000_000);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("provider", &[]),
+            ExecuteMsg::FailJob { job_id, reason: "node crashed".to_string(), refund_percent: None },
+        )
+        .unwrap();
+        assert!(res.events.is_empty());
+        let rate: FailureRateResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetFailureRate {}).unwrap()).unwrap();
+        assert_eq!((rate.failures, rate.completions), (3, 3));
+
+        // Self-dealt jobs never move the rate
+        for _ in 0..3 {
+            submit_job(deps.as_mut(), env.clone(), "provider", "provider", 1_000_000);
+        }
+        env.block.time = env.block.time.plus_seconds(3601);
+        let res = sweep(deps.as_mut(), env.clone());
+        assert!(res.events.is_empty());
+        let rate: FailureRateResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetFailureRate {}).unwrap()).unwrap();
+        assert_eq!((rate.failures, rate.completions), (3, 3));
+
+        // Genuine timeouts push the rate past half over enough jobs
+        submit_job(deps.as_mut(), start.clone(), "client", "provider", 1_000_000);
+        submit_job(deps.as_mut(), start, "client", "provider", 1_000_000);
+        let res = sweep(deps.as_mut(), env.clone());
+        assert_eq!(res.events.len(), 1);
+        assert_eq!(res.events[0].ty, "auto_pause");
+
+        let config: ConfigResponse =
+            from_json(query(deps.as_ref(), env, QueryMsg::GetConfig {}).unwrap()).unwrap();
+        assert!(config.paused);
+    }
+
+    #[test]
+    fn test_failures_below_minimum_sample_or_outside_window_do_not_pause() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        let update = config_update! {
+            auto_pause_failure_rate: Some(Decimal::percent(50)),
+            auto_pause_min_jobs: Some(6),
+            failure_window: Some(14400),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        // Every job fails, but no single window ever holds the minimum sample
+        let mut env = mock_env();
+        for _ in 0..4 {
+            for _ in 0..4 {
+                submit_job(deps.as_mut(), env.clone(), "client", "provider", 1_000_000);
+            }
+            env.block.time = env.block.time.plus_seconds(3601);
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("keeper", &[]),
+                ExecuteMsg::ProcessTimedOutJobs { limit: None },
+            )
+            .unwrap();
+            env.block.time = env.block.time.plus_seconds(14400);
+        }
+
+        let config: ConfigResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetConfig {}).unwrap()).unwrap();
+        assert!(!config.paused);
+        let rate: FailureRateResponse =
+            from_json(query(deps.as_ref(), env, QueryMsg::GetFailureRate {}).unwrap()).unwrap();
+        assert_eq!(rate.failures, 0);
+    }
+
+    #[test]
+    fn test_disabled_service_rejects_jobs_while_others_accept() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let mut pricing = HashMap::new();
+        for service in ["pi_calculation", "gpu_render"] {
+            pricing.insert(service.to_string(), PricingTier {
+                base_price: Decimal::percent(1),
+                unit: "digit".to_string(),
+            });
+        }
+        let capabilities = ["pi_calculation", "gpu_render"]
+            .iter()
+            .map(|service| ServiceCapability {
+                service_type: service.to_string(),
+                max_complexity: 100000,
+                avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
+            })
+            .collect();
+        let register = ExecuteMsg::RegisterProvider {
+            name: "Provider".to_string(),
+            capabilities,
+            pricing,
+            endpoint: "https://test.com".to_string(),
+            refund_guarantee: None,
+            max_payment: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), register).unwrap();
+
+        let toggle = |enabled| ExecuteMsg::SetServiceEnabled {
+            service_type: "gpu_render".to_string(),
+            enabled,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), toggle(false)).unwrap();
+
+        let submit = |job_type: &str| ExecuteMsg::SubmitJob {
+            provider: "provider".to_string(),
+            job_type: job_type.to_string(),
+            parameters: r#"{"digits":100,"quantity":100}"#.to_string(),
+            referrer: None,
+            depends_on: None,
+            denom: "umedas".to_string(),
+            max_price: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &coins(1_000, "umedas")), submit("gpu_render"))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            medas_computing_contract::ContractError::ServiceDisabled { ref service_type } if service_type == "gpu_render"
+        ));
+        execute(deps.as_mut(), mock_env(), mock_info("client", &coins(1_000, "umedas")), submit("pi_calculation"))
+            .unwrap();
+
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), toggle(true)).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("client", &coins(1_000, "umedas")), submit("gpu_render"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_preview_fee_split_sums_to_amount() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let preview = |deps: Deps, amount: u128| -> FeeSplitResponse {
+            let msg = QueryMsg::PreviewFeeSplit { amount: Uint128::new(amount) };
+            from_json(query(deps, mock_env(), msg).unwrap()).unwrap()
+        };
+
+        // 15% community fee, rounded toward the provider by default
+        for (amount, fee) in [(0u128, 0u128), (100, 15), (1_000_000, 150_000), (7, 1), (333, 49), (1, 0)] {
+            let split = preview(deps.as_ref(), amount);
+            assert_eq!(split.community_fee, Uint128::new(fee), "amount {}", amount);
+            assert_eq!(split.community_fee + split.provider_payout, Uint128::new(amount));
+        }
+
+        let update = config_update! { rounding_mode: Some(RoundingMode::TowardCommunity) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+        let split = preview(deps.as_ref(), 333);
+        assert_eq!(split.community_fee, Uint128::new(50));
+        assert_eq!(split.provider_payout, Uint128::new(283));
+    }
+
+    #[test]
+    fn test_reputation_rating_weight_blends_ratings() {
+        // Two successful jobs rated 5 and 3 stars: success 100, ratings average 75
+        for (weight, expected) in [(0u64, "100"), (50, "87.5"), (100, "75")] {
+            let mut deps = mock_dependencies();
+            setup_contract(deps.as_mut());
+            let update = config_update! { reputation_rating_weight: Some(weight) };
+            execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+            register_provider(deps.as_mut(), mock_env(), "provider");
+
+            for rating in [5u8, 3] {
+                let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+                complete_job(deps.as_mut(), mock_env(), "provider", job_id);
+                execute(
+                    deps.as_mut(),
+                    mock_env(),
+                    mock_info("client", &[]),
+                    ExecuteMsg::RateJob { job_id, rating },
+                )
+                .unwrap();
+            }
+
+            let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+            assert_eq!(provider.reputation, expected.parse::<Decimal>().unwrap(), "weight {}", weight);
+        }
+
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        let weight = 101;
+        let update = config_update! { reputation_rating_weight: Some(weight) };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidRatingWeight { weight: 101 }));
+    }
+
+    #[test]
+    fn test_top_up_job_increases_payout() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let top_up = ExecuteMsg::TopUpJob { job_id };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("stranger", &coins(500_000, "umedas")), top_up.clone())
+            .unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+        execute(deps.as_mut(), mock_env(), mock_info("client", &coins(500_000, "umedas")), top_up.clone()).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id }).unwrap();
+        let job: JobResponse = from_json(&res).unwrap();
+        assert_eq!(job.payment_amount, Uint128::new(1_500_000));
+
+        let complete = ExecuteMsg::CompleteJob {
+            job_id,
+            result_hash: "hash".to_string(),
+            result_url: "https://results.test/1".to_string(),
+            encryption: None,
+            result_size_bytes: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), complete).unwrap();
+        // 15% of the topped-up total goes to the pool, the rest to the provider
+        let sent: Vec<_> = res
+            .messages
+            .iter()
+            .filter_map(|m| match &m.msg {
+                CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => Some((to_address.as_str(), amount[0].amount.u128())),
+                _ => None,
+            })
+            .collect();
+        assert!(sent.contains(&("medas1community...", 225_000)));
+        assert!(sent.contains(&("provider", 1_275_000)));
+
+        let invariants: InvariantsResponse =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::CheckInvariants {}).unwrap()).unwrap();
+        assert!(invariants.violations.is_empty());
+
+        // Terminal jobs can no longer be topped up
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &coins(500_000, "umedas")), top_up)
+            .unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::InvalidJobState {}));
+    }
+
+    #[test]
+    fn test_list_idle_providers() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let registered = mock_env();
+        for provider in ["provider_a", "provider_b", "provider_c"] {
+            register_provider(deps.as_mut(), registered.clone(), provider);
+        }
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(2 * 86_400);
+        register_provider(deps.as_mut(), later.clone(), "provider_d");
+
+        // provider_b has done real work
+        let job_id = submit_job(deps.as_mut(), later.clone(), "client", "provider_b", 1_000_000);
+        complete_job(deps.as_mut(), later.clone(), "provider_b", job_id);
+
+        let idle = |deps: Deps, min_age: Option<u64>| -> Vec<String> {
+            let msg = QueryMsg::ListIdleProviders { limit: None, min_age };
+            let res: IdleProvidersResponse = from_json(query(deps, later.clone(), msg).unwrap()).unwrap();
+            res.providers.into_iter().map(|p| p.provider).collect()
+        };
+        assert_eq!(idle(deps.as_ref(), None), vec!["provider_a", "provider_c", "provider_d"]);
+        // Only providers idle for at least a day
+        assert_eq!(idle(deps.as_ref(), Some(86_400)), vec!["provider_a", "provider_c"]);
+    }
+
+    #[test]
+    fn test_sweep_dust_only_sends_excess() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        // Two open jobs escrow 1.5M; 333 umedas and a stray denom are dust
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        submit_job(deps.as_mut(), mock_env(), "client", "provider", 500_000);
+        deps.querier.update_balance(
+            MOCK_CONTRACT_ADDR,
+            vec![Coin::new(1_500_333, "umedas"), Coin::new(42, "uatom")],
+        );
+
+        let err = execute(deps.as_mut(), mock_env(), mock_info("client", &[]), ExecuteMsg::SweepDust {})
+            .unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::Unauthorized {}));
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SweepDust {}).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "medas1community...".to_string(),
+                amount: vec![Coin::new(333, "umedas"), Coin::new(42, "uatom")],
+            })
+        );
+
+        // Nothing beyond the escrow left to sweep
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(1_500_000, "umedas"));
+        let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SweepDust {}).unwrap();
+        assert!(res.messages.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_from_baseline_state() {
+        let mut deps = mock_dependencies();
+        store_baseline_state(deps.as_mut());
+
+        let msg = MigrateMsg { default_job_timeout: None, heartbeat_timeout: None, admin: None };
+        let err = migrate(deps.as_mut(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::MigrationAdminRequired {}));
+
+        let msg = MigrateMsg { default_job_timeout: None, heartbeat_timeout: None, admin: Some("admin".to_string()) };
+        let res = migrate(deps.as_mut(), mock_env(), msg).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "from_version" && a.value == "0.1.0"));
+
+        let config = CONFIG.load(&deps.storage).unwrap();
+        assert_eq!(config.admin, Addr::unchecked("admin"));
+        assert_eq!(config.community_fee_percent, 15);
+        assert_eq!(config.accepted_denoms, vec!["umedas".to_string()]);
+
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert!(provider.capabilities[0].enabled);
+        assert!(provider.capabilities[0].required_parameters.is_empty());
+
+        let job: JobResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetJob { job_id: 1 }).unwrap()).unwrap();
+        assert_eq!(job.status, "submitted");
+        assert_eq!(JOBS.load(&deps.storage, 1).unwrap().denom, "umedas");
+
+        // Only the open job is still owed
+        let tvl: TvlResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetTVL {}).unwrap()).unwrap();
+        assert_eq!(tvl.obligations, coins(1_000_000, "umedas"));
+        let solvency: SolvencyResponse =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetSolvency {}).unwrap()).unwrap();
+        assert_eq!(solvency.obligations, Uint128::new(1_000_000));
+
+        assert_eq!(JOB_ESCROW.load(&deps.storage, 1).unwrap(), Uint128::new(1_000_000));
+        assert!(!JOB_ESCROW.has(&deps.storage, 2));
+
+        // The upgraded contract takes new work and settles the old
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        assert_eq!(job_id, 3);
+        complete_job(deps.as_mut(), mock_env(), "provider", 1);
+        assert!(!JOB_ESCROW.has(&deps.storage, 1));
+        let invariants: InvariantsResponse =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::CheckInvariants {}).unwrap()).unwrap();
+        assert!(invariants.violations.is_empty(), "{:?}", invariants.violations);
+    }
+
+    #[test]
+    fn test_legacy_job_without_escrow_entry_still_settles() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        // A job whose escrow entry was never written, as before the ledger existed
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        JOB_ESCROW.remove(deps.as_mut().storage, job_id);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::FailJob { job_id, reason: "legacy".to_string(), refund_percent: None },
+        )
+        .unwrap();
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().status, JobStatus::Failed);
+    }
+
+    #[test]
+    fn test_sweep_dust_keeps_legacy_escrow() {
+        // Ledgers that were never backfilled cannot be trusted
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        LEDGERS_BACKFILLED.remove(deps.as_mut().storage);
+        let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), ExecuteMsg::SweepDust {}).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::LedgersNotBackfilled {}));
+
+        let mut deps = mock_dependencies();
+        store_baseline_state(deps.as_mut());
+        // Legacy job 1 still holds 1_000_000; 500 is dust
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(1_000_500, "umedas"));
+        let msg = MigrateMsg { default_job_timeout: None, heartbeat_timeout: None, admin: Some("admin".to_string()) };
+        migrate(deps.as_mut(), mock_env(), msg).unwrap();
+        assert!(LEDGERS_BACKFILLED.load(&deps.storage).unwrap());
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), ExecuteMsg::SweepDust {}).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "medas1community...".to_string(),
+                amount: coins(500, "umedas"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_fallback_takeover_respects_provider_limits() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider1");
+        register_provider(deps.as_mut(), mock_env(), "provider2");
+
+        let submit = ExecuteMsg::SubmitJobWithFallback {
+            providers: vec!["provider1".to_string(), "provider2".to_string()],
+            job_type: "pi_calculation".to_string(),
+            parameters: r#"{"digits":50000}"#.to_string(),
+            denom: "umedas".to_string(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("client", &coins(1_000_000, "umedas")), submit)
+            .unwrap();
+        let job_id: u64 = res.attributes.iter().find(|a| a.key == "job_id").unwrap().value.parse().unwrap();
+
+        // provider2 would not have accepted this payment on a direct submission
+        let update = ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: None,
+            capacity: None,
+            payout_address: None,
+            capabilities: None,
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: Some(Uint128::new(500_000)),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("provider2", &[]), update).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(300);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("provider2", &[]), ExecuteMsg::AcceptJob { job_id })
+            .unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::PaymentTooLarge { .. }));
+
+        // Nor a job above its maximum complexity
+        let update = ExecuteMsg::UpdateProvider {
+            name: None,
+            endpoint: None,
+            pricing: None,
+            capacity: None,
+            payout_address: None,
+            capabilities: Some(vec![ServiceCapability {
+                service_type: "pi_calculation".to_string(),
+                max_complexity: 10_000,
+                avg_completion_time: 180,
+                required_parameters: vec![],
+                enabled: true,
+            }]),
+            auto_accept: None,
+            refund_guarantee: None,
+            max_payment: Some(Uint128::zero()),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("provider2", &[]), update).unwrap();
+        let err = execute(deps.as_mut(), env, mock_info("provider2", &[]), ExecuteMsg::AcceptJob { job_id })
+            .unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::ComplexityTooHigh { .. }));
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().provider.as_str(), "provider1");
+    }
+
+    #[test]
+    fn test_extension_moves_result_deadline() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            config_update! { result_deadline_buffer: Some(60) },
+        ).unwrap();
+
+        let job_id = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        execute(deps.as_mut(), mock_env(), mock_info("provider", &[]), ExecuteMsg::AcceptJob { job_id }).unwrap();
+        let result_deadline = mock_env().block.time.seconds() + 240;
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().result_deadline, Some(result_deadline));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("provider", &[]),
+            ExecuteMsg::RequestExtension { job_id, additional_seconds: 600 },
+        ).unwrap();
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().result_deadline, Some(result_deadline + 600));
+
+        // Past the original result deadline but within the extended one
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(500);
+        complete_job(deps.as_mut(), later, "provider", job_id);
+        assert_eq!(JOBS.load(&deps.storage, job_id).unwrap().status, JobStatus::Completed);
+    }
+
+    #[test]
+    fn test_submit_rejects_malformed_parameters() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        for parameters in ["not json", "{}", r#"{"digits":"many"}"#, r#"{"digits":0}"#] {
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("client", &coins(1_000_000, "umedas")),
+                ExecuteMsg::SubmitJob {
+                    provider: "provider".to_string(),
+                    job_type: "pi_calculation".to_string(),
+                    parameters: parameters.to_string(),
+                    referrer: None,
+                    depends_on: None,
+                    denom: "umedas".to_string(),
+                    max_price: None,
+                },
+            ).unwrap_err();
+            assert!(matches!(err, medas_computing_contract::ContractError::InvalidJobParameters { .. }));
+        }
+    }
+
+    #[test]
+    fn test_routed_submissions_use_the_chosen_denom() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let update = config_update! { accepted_denoms: Some(vec!["umedas".to_string(), "uatom".to_string()]) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let fallback = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(2_000, "uatom")),
+            ExecuteMsg::SubmitJobWithFallback {
+                providers: vec!["provider".to_string()],
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                denom: "uatom".to_string(),
+            },
+        ).unwrap();
+        let auto = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(3_000, "uatom")),
+            ExecuteMsg::AutoSubmitJob {
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                strategy: SelectionStrategy::HighestReputation,
+                denom: "uatom".to_string(),
+            },
+        ).unwrap();
+
+        for (res, amount) in [(fallback, 2_000u128), (auto, 3_000)] {
+            let job_id: u64 = res.attributes.iter().find(|a| a.key == "job_id").unwrap().value.parse().unwrap();
+            let job = JOBS.load(&deps.storage, job_id).unwrap();
+            assert_eq!(job.denom, "uatom");
+            assert_eq!(job.payment_amount, Uint128::new(amount));
+        }
+
+        // Funds in a denom other than the one named are refused
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(1_000_000, "umedas")),
+            ExecuteMsg::AutoSubmitJob {
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                strategy: SelectionStrategy::HighestReputation,
+                denom: "uatom".to_string(),
+            },
+        ).unwrap_err();
+        assert!(matches!(err, medas_computing_contract::ContractError::UnexpectedDenom { .. }));
+    }
+
+    #[test]
+    fn test_spending_and_earnings_totals_kept_per_denom() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+        let update = config_update! { accepted_denoms: Some(vec!["umedas".to_string(), "uatom".to_string()]) };
+        execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), update).unwrap();
+
+        let umedas_job = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("client", &coins(2_000, "uatom")),
+            ExecuteMsg::SubmitJob {
+                provider: "provider".to_string(),
+                job_type: "pi_calculation".to_string(),
+                parameters: r#"{"digits":10000}"#.to_string(),
+                referrer: None,
+                depends_on: None,
+                denom: "uatom".to_string(),
+                max_price: None,
+            },
+        ).unwrap();
+        let uatom_job: u64 = res.attributes.iter().find(|a| a.key == "job_id").unwrap().value.parse().unwrap();
+        complete_job(deps.as_mut(), mock_env(), "provider", umedas_job);
+        complete_job(deps.as_mut(), mock_env(), "provider", uatom_job);
+
+        let stats: ClientStatsResponse = from_json(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetClientStats { client: "client".to_string() }).unwrap(),
+        ).unwrap();
+        assert_eq!(
+            stats.total_spent,
+            vec![cosmwasm_std::Coin::new(1_000_000, "umedas"), cosmwasm_std::Coin::new(2_000, "uatom")]
+        );
+
+        let now = mock_env().block.time.seconds();
+        let earnings: ProviderEarningsResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetProviderEarnings {
+                    provider: "provider".to_string(),
+                    from: now,
+                    to: now,
+                    start_after: None,
+                    limit: None,
+                },
+            ).unwrap(),
+        ).unwrap();
+        assert_eq!(earnings.job_count, 2);
+        assert_eq!(
+            earnings.earnings,
+            vec![cosmwasm_std::Coin::new(850_000, "umedas"), cosmwasm_std::Coin::new(1_700, "uatom")]
+        );
+    }
+
+    #[test]
+    fn test_waive_penalty_removes_that_jobs_failure() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        register_provider(deps.as_mut(), mock_env(), "provider");
+
+        let first_done = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), mock_env(), "provider", first_done);
+        let timed_out = submit_job(deps.as_mut(), mock_env(), "client", "provider", 1_000_000);
+
+        let mut later = mock_env();
+        later.block.time = later.block.time.plus_seconds(3_601);
+        execute(deps.as_mut(), later.clone(), mock_info("keeper", &[]), ExecuteMsg::ProcessTimedOutJobs { limit: None })
+            .unwrap();
+        let second_done = submit_job(deps.as_mut(), later.clone(), "client", "provider", 1_000_000);
+        complete_job(deps.as_mut(), later.clone(), "provider", second_done);
+        let abandoned = submit_job(deps.as_mut(), later.clone(), "client", "provider", 1_000_000);
+        execute(
+            deps.as_mut(),
+            later.clone(),
+            mock_info("provider", &[]),
+            ExecuteMsg::FailJob { job_id: abandoned, reason: "gave up".to_string(), refund_percent: None },
+        ).unwrap();
+
+        let failure_count = |deps: Deps| -> u32 {
+            let rate: FailureRateResponse =
+                from_json(query(deps, later.clone(), QueryMsg::GetFailureRate {}).unwrap()).unwrap();
+            rate.failures
+        };
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(provider.recent_outcomes, vec![true, false, true, false]);
+        assert_eq!(failure_count(deps.as_ref()), 2);
+
+        // Waiving the older failure drops its own entry, not the most recent failure
+        execute(
+            deps.as_mut(),
+            later.clone(),
+            mock_info("creator", &[]),
+            ExecuteMsg::WaivePenalty { job_id: timed_out },
+        ).unwrap();
+        let provider = PROVIDERS.load(&deps.storage, &Addr::unchecked("provider")).unwrap();
+        assert_eq!(provider.recent_outcomes, vec![true, true, false]);
+        assert_eq!(provider.recent_outcome_jobs, vec![first_done, second_done, abandoned]);
+        assert_eq!(failure_count(deps.as_ref()), 1);
+
+        // A waived FailJob comes back out of the window as well
+        execute(
+            deps.as_mut(),
+            later.clone(),
+            mock_info("creator", &[]),
+            ExecuteMsg::WaivePenalty { job_id: abandoned },
+        ).unwrap();
+        assert_eq!(failure_count(deps.as_ref()), 0);
+    }
 }